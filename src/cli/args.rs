@@ -1,4 +1,14 @@
-use clap::{Parser, Subcommand};
+use crate::convert::PdfProfile;
+use crate::error::CertgenError;
+use crate::filename::{CollisionPolicy, SanitizePolicy};
+use crate::odf::{ImageFit, MissingPolicy};
+use crate::registry::IdFormat;
+use crate::sign::SignatureRect;
+use crate::smtp::{parse_mailbox, AuthMechanism, SmtpSecurity};
+use crate::template::dates::{parse_date, DateStyle, Locale};
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name = "certgen")]
@@ -11,6 +21,10 @@ pub struct Cli {
     /// Verbose output
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    /// Configuration file (JSON); defaults to ./certgen.config.json if present
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -33,17 +47,18 @@ pub enum Commands {
         #[arg(short = 'T', long)]
         title: String,
 
-        /// Date for single-day courses (or end date for multi-day)
-        #[arg(short, long)]
-        date: String,
+        /// Date for single-day courses (or end date for multi-day), e.g. 15.01.2024 or 2024-01-15
+        #[arg(short, long, value_parser = parse_date)]
+        date: NaiveDate,
 
         /// Start date for multi-day courses (optional)
-        #[arg(long)]
-        date_from: Option<String>,
+        #[arg(long, value_parser = parse_date)]
+        date_from: Option<NaiveDate>,
 
-        /// End date for multi-day courses (optional)
-        #[arg(long)]
-        date_to: Option<String>,
+        /// End date for multi-day courses (optional, needs --date-from; without it the
+        /// course is a single day at --date-from)
+        #[arg(long, value_parser = parse_date, requires = "date_from")]
+        date_to: Option<NaiveDate>,
 
         /// Agenda/Course content
         #[arg(short, long)]
@@ -52,6 +67,36 @@ pub enum Commands {
         /// Additional custom fields in format KEY=VALUE (can be used multiple times)
         #[arg(short = 'f', long = "field", value_parser = parse_key_val)]
         custom_fields: Vec<(String, String)>,
+
+        /// Fail if the template contains placeholders without a value
+        #[arg(long)]
+        strict: bool,
+
+        /// What to do with placeholders left without a value: keep, empty, error or default=TEXT
+        /// (defaults to `error` with --strict, otherwise `keep`)
+        #[arg(long)]
+        on_missing: Option<MissingPolicy>,
+
+        #[command(flatten)]
+        converter: ConverterArgs,
+
+        #[command(flatten)]
+        signing: SigningArgs,
+
+        #[command(flatten)]
+        registry: RegistryArgs,
+
+        #[command(flatten)]
+        qr: QrArgs,
+
+        #[command(flatten)]
+        images: ImageArgs,
+
+        #[command(flatten)]
+        dates: DateArgs,
+
+        #[command(flatten)]
+        languages: LanguageArgs,
     },
 
     /// Fill certificates from JSON file
@@ -60,13 +105,139 @@ pub enum Commands {
         #[arg(short, long)]
         template: String,
 
-        /// JSON file with certificate data
-        #[arg(short, long)]
+        /// File with certificate data: JSON, CSV, ODS or XLSX
+        #[arg(short, long, visible_alias = "input")]
         json: String,
 
         /// Output directory
         #[arg(short, long, default_value = "output")]
         output_dir: String,
+
+        /// Validate all records against the template before rendering and abort on unfilled placeholders
+        #[arg(long)]
+        strict: bool,
+
+        /// What to do with placeholders left without a value: keep, empty, error or default=TEXT
+        /// (defaults to `error` with --strict, otherwise `keep`)
+        #[arg(long)]
+        on_missing: Option<MissingPolicy>,
+
+        /// Number of certificates rendered in parallel (each with its own LibreOffice profile)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: u16,
+
+        /// Convert all certificates with a single soffice invocation instead of one per file
+        #[arg(long)]
+        bulk: bool,
+
+        /// With --bulk: maximum number of documents per soffice invocation (0 = all at once)
+        #[arg(long, default_value_t = 0, requires = "bulk")]
+        chunk_size: usize,
+
+        /// Write generated file names and certificate IDs back into the JSON input
+        /// (atomically, keeping a .bak copy); otherwise they go to certgen-results.json in the output directory
+        #[arg(long)]
+        update_input: bool,
+
+        /// Continue after failed records and report all errors at the end (exit code 1)
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Skip certificates whose output is unchanged since the last run (per manifest.json)
+        #[arg(long)]
+        resume: bool,
+
+        /// Deliver the generated emails over SMTP after the batch (see also the `send` command)
+        #[arg(long)]
+        send: bool,
+
+        #[command(flatten)]
+        converter: ConverterArgs,
+
+        #[command(flatten)]
+        signing: SigningArgs,
+
+        #[command(flatten)]
+        registry: RegistryArgs,
+
+        #[command(flatten)]
+        qr: QrArgs,
+
+        #[command(flatten)]
+        images: ImageArgs,
+
+        #[command(flatten)]
+        dates: DateArgs,
+
+        #[command(flatten)]
+        languages: LanguageArgs,
+
+        #[command(flatten)]
+        source: SourceArgs,
+
+        #[command(flatten)]
+        filenames: FilenameArgs,
+
+        // Box hält die Variante klein genug für clippy::large_enum_variant
+        #[command(flatten)]
+        email: Box<EmailArgs>,
+
+        #[command(flatten)]
+        smtp: SmtpArgs,
+    },
+
+    /// Deliver the emails of an earlier batch run over SMTP (per manifest.json)
+    Send {
+        /// Output directory of the batch run
+        #[arg(short, long, default_value = "output")]
+        output_dir: String,
+
+        /// Send emails again that were already delivered
+        #[arg(long)]
+        resend: bool,
+
+        #[command(flatten)]
+        smtp: SmtpArgs,
+    },
+
+    /// Check a JSON file against the placeholders of a template without rendering
+    Validate {
+        /// Template file path
+        #[arg(short, long)]
+        template: String,
+
+        /// File with certificate data: JSON, CSV, ODS or XLSX
+        #[arg(short, long, visible_alias = "input")]
+        json: String,
+
+        #[command(flatten)]
+        source: SourceArgs,
+    },
+
+    /// Check the digital signatures of generated PDFs
+    VerifySignature {
+        /// PDF files to check
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// PEM file with trusted CA certificates; without it only the integrity is checked
+        #[arg(long)]
+        ca: Option<PathBuf>,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// List all placeholders a template expects
+    Inspect {
+        /// Template file path
+        #[arg(short, long)]
+        template: String,
+
+        /// Print the result as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Generate example JSON file
@@ -88,6 +259,306 @@ pub enum Commands {
     },
 }
 
+/// Available PDF converter backends
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConverterKind {
+    /// Local LibreOffice (`soffice --headless`)
+    Soffice,
+    /// Running unoserver listener, called through `unoconvert`
+    Unoserver,
+    /// Custom command from --converter-command
+    Command,
+}
+
+/// Options selecting the PDF converter
+#[derive(Args, Debug, Clone)]
+pub struct ConverterArgs {
+    /// Backend used to convert filled documents to PDF
+    #[arg(long, value_enum, default_value_t = ConverterKind::Soffice)]
+    pub converter: ConverterKind,
+
+    /// Host of the unoserver listener (with --converter unoserver)
+    #[arg(long, default_value = "127.0.0.1")]
+    pub unoserver_host: String,
+
+    /// Port of the unoserver listener (with --converter unoserver)
+    #[arg(long, default_value_t = 2003)]
+    pub unoserver_port: u16,
+
+    /// Command template with {input}, {output} and {outdir}, e.g. "cp {input} {output}"
+    /// (required with --converter command)
+    #[arg(long, required_if_eq("converter", "command"))]
+    pub converter_command: Option<String>,
+
+    /// LibreOffice executable, e.g. `libreoffice` or `/opt/libreoffice7.6/program/soffice`
+    #[arg(long)]
+    pub soffice: Option<String>,
+
+    /// Dedicated LibreOffice user profile directory
+    #[arg(long)]
+    pub profile_dir: Option<PathBuf>,
+
+    /// Extra options for LibreOffice's PDF export filter (writer_pdf_Export)
+    #[arg(long)]
+    pub filter_options: Option<String>,
+
+    /// Kill a conversion after this many seconds per document (a --bulk chunk gets
+    /// this limit times the number of documents in it)
+    #[arg(long)]
+    pub convert_timeout: Option<u64>,
+
+    /// PDF flavour: pdf, pdfa-1b, pdfa-2b or pdfa-3b
+    #[arg(long)]
+    pub pdf_profile: Option<PdfProfile>,
+
+    /// Embed the standard PDF fonts as well
+    #[arg(long)]
+    pub embed_fonts: bool,
+
+    /// Produce a tagged (accessible) PDF
+    #[arg(long)]
+    pub tagged_pdf: bool,
+
+    /// JPEG quality for images (1-100)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub image_quality: Option<u8>,
+
+    /// Compress images losslessly
+    #[arg(long)]
+    pub lossless_images: bool,
+}
+
+/// Options for certificate IDs and the registry of issued certificates
+#[derive(Args, Debug, Clone)]
+pub struct RegistryArgs {
+    /// Registry file (JSONL) recording every issued certificate
+    /// (defaults to certgen-registry.jsonl in the output directory)
+    #[arg(long)]
+    pub registry: Option<PathBuf>,
+
+    /// Format of new certificate IDs: `ulid` or a pattern such as "B1-{year}-{seq:6}"
+    #[arg(long)]
+    pub id_format: Option<IdFormat>,
+}
+
+/// Options for {{QR}} / {{QR:...}} placeholders
+#[derive(Args, Debug, Clone)]
+pub struct QrArgs {
+    /// Content of {{QR}} placeholders, fields as {KEY}, e.g. "https://example.com/verify/{CERT_ID}"
+    /// (defaults to the certificate ID)
+    #[arg(long)]
+    pub qr_payload: Option<String>,
+
+    /// Edge length of QR codes in the document, e.g. 3cm or 1.2in
+    #[arg(long)]
+    pub qr_size: Option<String>,
+}
+
+/// Options for how dates are written into the certificate
+#[derive(Args, Debug, Clone)]
+pub struct DateArgs {
+    /// Language of the date text (VON_AN/DATE): de, en or fr [default: de]
+    #[arg(long)]
+    pub locale: Option<Locale>,
+
+    /// Date format: numeric (15.01.2024) or long (15. Januar 2024) [default: numeric]
+    #[arg(long)]
+    pub date_style: Option<DateStyle>,
+
+    /// Write month and year of a date range only once, e.g. "10.–15. Januar 2024"
+    #[arg(long)]
+    pub collapse_dates: bool,
+}
+
+/// Options for reading CSV and spreadsheet input
+#[derive(Args, Debug, Clone)]
+pub struct SourceArgs {
+    /// Field delimiter of CSV input (default: detected from the header line)
+    #[arg(long)]
+    pub delimiter: Option<char>,
+
+    /// Character encoding of CSV input, e.g. windows-1252 (default: UTF-8)
+    #[arg(long)]
+    pub encoding: Option<String>,
+
+    /// Sheet of an ODS/XLSX file (default: the first sheet)
+    #[arg(long)]
+    pub sheet: Option<String>,
+
+    /// Read a field from a differently named column in format FIELD=COLUMN,
+    /// e.g. name=Teilnehmer (can be used multiple times)
+    #[arg(long = "column", value_parser = parse_key_val)]
+    pub columns: Vec<(String, String)>,
+}
+
+/// Options for certificates in several languages
+#[derive(Args, Debug, Clone)]
+pub struct LanguageArgs {
+    /// Create one output per language, e.g. de,en (a `language` field in the record takes precedence);
+    /// the language is appended to the file name
+    #[arg(long, value_delimiter = ',')]
+    pub languages: Vec<Locale>,
+
+    /// Template for one language in format LANG=PATH (can be used multiple times)
+    #[arg(long = "language-template", value_parser = parse_language_template)]
+    pub language_templates: Vec<(Locale, PathBuf)>,
+}
+
+/// Options for the names of the generated files in batch mode
+#[derive(Args, Debug, Clone)]
+pub struct FilenameArgs {
+    /// File name template with {{PLACEHOLDER}} fields and filters; `/` creates subdirectories,
+    /// e.g. {{TITLE}}/{{DATE}}_{{CERT_ID}}_{{NAME}}; {{DATE}} is YYYY-MM-DD here (default: {{NAME}}_{{TITLE}})
+    #[arg(long)]
+    pub filename_template: Option<String>,
+
+    /// Maximum length of each part of the file name in characters (default: 100)
+    #[arg(long)]
+    pub max_filename_length: Option<usize>,
+
+    /// What to do if two certificates get the same file name: suffix (append _2, _3, …) or error
+    #[arg(long)]
+    pub on_collision: Option<CollisionPolicy>,
+
+    /// Characters kept in file names: ascii-translit (Ç → C, Ж → Zh), keep-unicode
+    /// or strict (only A-Z, a-z, 0-9, - and _) (default: ascii-translit)
+    #[arg(long)]
+    pub sanitize: Option<SanitizePolicy>,
+
+    /// Transliterate German umlauts and ß as ae, oe, ue, ss instead of a, o, u, s
+    #[arg(long)]
+    pub german_umlauts: bool,
+}
+
+/// Options for the emails created for records with an `email` field
+#[derive(Args, Debug, Clone)]
+pub struct EmailArgs {
+    /// Sender of the emails, e.g. "Kursteam <kurs@example.com>" (required when a record has an email address)
+    #[arg(long, value_parser = mailbox)]
+    pub from: Option<String>,
+
+    /// Address for replies
+    #[arg(long, value_parser = mailbox)]
+    pub reply_to: Option<String>,
+
+    /// Send a copy to this address (can be used multiple times)
+    #[arg(long, value_parser = mailbox)]
+    pub cc: Vec<String>,
+
+    /// Send a blind copy to this address, e.g. an archive mailbox (can be used multiple times)
+    #[arg(long, value_parser = mailbox)]
+    pub bcc: Vec<String>,
+
+    /// File with the subject template, with {{PLACEHOLDER}} fields like the certificate template
+    #[arg(long)]
+    pub subject_template: Option<PathBuf>,
+
+    /// File with the body template, with {{PLACEHOLDER}} fields like the certificate template;
+    /// {{CERT_FILE}} is the name of the attached PDF
+    #[arg(long)]
+    pub body_template: Option<PathBuf>,
+}
+
+/// Options for delivering the generated emails over SMTP
+#[derive(Args, Debug, Clone)]
+pub struct SmtpArgs {
+    /// SMTP server, e.g. mail.example.com
+    #[arg(long)]
+    pub smtp_host: Option<String>,
+
+    /// SMTP port (default: 587 with starttls, 465 with tls, 25 with none)
+    #[arg(long)]
+    pub smtp_port: Option<u16>,
+
+    /// Connection security: starttls, tls (implicit TLS) or none (default: starttls)
+    #[arg(long)]
+    pub smtp_security: Option<SmtpSecurity>,
+
+    /// User name for SMTP authentication
+    #[arg(long)]
+    pub smtp_user: Option<String>,
+
+    /// Password for SMTP authentication
+    #[arg(long, env = "CERTGEN_SMTP_PASSWORD", hide_env_values = true)]
+    pub smtp_password: Option<String>,
+
+    /// Allowed authentication mechanisms, e.g. plain,login (default: both)
+    #[arg(long, value_delimiter = ',')]
+    pub smtp_auth: Vec<AuthMechanism>,
+
+    /// Send at most this many emails per minute
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub rate_limit: Option<u32>,
+
+    /// Retries per email after temporary errors (default: 3)
+    #[arg(long)]
+    pub smtp_retries: Option<u32>,
+}
+
+/// Options for image placeholders (frames named {{KEY}})
+#[derive(Args, Debug, Clone)]
+pub struct ImageArgs {
+    /// How images are fitted into the template frame: stretch (keep the frame size)
+    /// or contain (keep the image aspect ratio inside the frame)
+    #[arg(long)]
+    pub image_fit: Option<ImageFit>,
+}
+
+/// Options for digitally signing the generated PDFs
+#[derive(Args, Debug, Clone)]
+pub struct SigningArgs {
+    /// Sign the PDFs with the key and certificate from this PKCS#12 file (.p12/.pfx)
+    #[arg(long, conflicts_with = "sign_key")]
+    pub sign_p12: Option<PathBuf>,
+
+    /// PEM private key for signing (together with --sign-cert)
+    #[arg(long, requires = "sign_cert")]
+    pub sign_key: Option<PathBuf>,
+
+    /// PEM certificate for --sign-key, optionally followed by its chain
+    #[arg(long, requires = "sign_key")]
+    pub sign_cert: Option<PathBuf>,
+
+    /// Password of the PKCS#12 file or the encrypted PEM key
+    #[arg(long, env = "CERTGEN_SIGN_PASSWORD", hide_env_values = true)]
+    pub sign_password: Option<String>,
+
+    /// Show the signature as a box on the page instead of an invisible field (not with a
+    /// PDF/A --pdf-profile, the box uses a font that is not embedded)
+    #[arg(long)]
+    pub sign_visible: bool,
+
+    /// Page of the visible signature (1 = first page)
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub sign_page: u32,
+
+    /// Position of the visible signature in points: x,y,width,height (origin bottom left)
+    #[arg(long, default_value = "36,36,200,50")]
+    pub sign_rect: SignatureRect,
+
+    /// Reason stored in the signature, e.g. "Teilnahmebestätigung"
+    #[arg(long)]
+    pub sign_reason: Option<String>,
+
+    /// Location stored in the signature
+    #[arg(long)]
+    pub sign_location: Option<String>,
+}
+
+/// Parse a LANG=PATH pair for --language-template
+fn parse_language_template(s: &str) -> Result<(Locale, PathBuf), String> {
+    let (language, path) = parse_key_val(s)?;
+    Ok((language.parse()?, PathBuf::from(path)))
+}
+
+/// Check an email address for --from, --reply-to, --cc and --bcc
+fn mailbox(s: &str) -> Result<String, String> {
+    parse_mailbox(s).map_err(|e| match e {
+        CertgenError::Smtp(message) => message,
+        other => other.to_string(),
+    })
+}
+
 /// Parse a single key-value pair
 fn parse_key_val(s: &str) -> Result<(String, String), String> {
     let pos = s
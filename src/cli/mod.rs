@@ -1,3 +1,3 @@
 pub mod args;
 
-pub use args::{Cli, Commands};
+pub use args::{Cli, Commands, ConverterArgs, ConverterKind, DateArgs, EmailArgs, FilenameArgs, ImageArgs, LanguageArgs, QrArgs, RegistryArgs, SigningArgs, SmtpArgs, SourceArgs};
@@ -0,0 +1,332 @@
+//! Optionale Konfigurationsdatei (JSON) für Einstellungen, die man nicht bei
+//! jedem Aufruf per CLI angeben möchte. CLI-Argumente haben immer Vorrang.
+
+use crate::convert::PdfProfile;
+use crate::error::Result;
+use crate::filename::{CollisionPolicy, SanitizePolicy};
+use crate::odf::ImageFit;
+use crate::registry::IdFormat;
+use crate::smtp::{AuthMechanism, SmtpSecurity};
+use crate::template::{DateStyle, Locale};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Wird automatisch geladen, wenn keine Datei per `--config` angegeben ist
+pub const DEFAULT_CONFIG_FILE: &str = "certgen.config.json";
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub converter: ConverterConfig,
+    pub registry: RegistryConfig,
+    pub qr: QrConfig,
+    pub images: ImageConfig,
+    pub dates: DateConfig,
+    /// Sprachabhängige Einstellungen, nach Sprache (`de`, `en`, `fr`)
+    pub languages: BTreeMap<Locale, LanguageConfig>,
+    pub input: InputConfig,
+    pub output: OutputConfig,
+    pub smtp: SmtpConfig,
+    pub email: EmailConfig,
+}
+
+/// Absender, weitere Empfänger und Textvorlagen der E-Mails
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmailConfig {
+    /// Absender, z.B. `Kursteam <kurs@example.com>`
+    pub from: Option<String>,
+    pub reply_to: Option<String>,
+    /// Empfänger in Kopie
+    pub cc: Vec<String>,
+    /// Empfänger in Blindkopie, z.B. ein Archivpostfach
+    pub bcc: Vec<String>,
+    /// Datei mit der Vorlage für den Betreff (für alle Sprachen)
+    pub subject_template: Option<PathBuf>,
+    /// Datei mit der Vorlage für den Text (für alle Sprachen)
+    pub body_template: Option<PathBuf>,
+}
+
+/// Versand der E-Mails per SMTP; das Passwort kommt aus `CERTGEN_SMTP_PASSWORD`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpConfig {
+    /// SMTP-Server, z.B. `mail.example.com`
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    /// `starttls`, `tls` oder `none`
+    pub security: Option<SmtpSecurity>,
+    /// Benutzername für die Anmeldung
+    pub username: Option<String>,
+    /// Erlaubte Anmeldeverfahren, z.B. `["login"]`
+    pub auth: Vec<AuthMechanism>,
+    /// Höchstens so viele E-Mails pro Minute
+    pub rate_limit: Option<u32>,
+    /// Wiederholungen bei vorübergehenden Fehlern
+    pub retries: Option<u32>,
+    /// Wartezeit vor der ersten Wiederholung in Sekunden
+    pub retry_delay_secs: Option<u64>,
+    /// Zeitlimit je SMTP-Befehl in Sekunden
+    pub timeout_secs: Option<u64>,
+}
+
+/// Dateinamen der erzeugten Zertifikate im Batch-Modus
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OutputConfig {
+    /// Vorlage für Dateinamen, z.B. `{{TITLE}}/{{CERT_ID}}_{{NAME}}`
+    pub filename_template: Option<String>,
+    /// Maximale Länge jedes Namensteils in Zeichen
+    pub max_length: Option<usize>,
+    /// `suffix` oder `error`
+    pub on_collision: Option<CollisionPolicy>,
+    /// `ascii-translit`, `keep-unicode` oder `strict`
+    pub sanitize: Option<SanitizePolicy>,
+    /// Umlaute als `ae`, `oe`, `ue` und `ß` als `ss` schreiben
+    pub german_umlauts: bool,
+}
+
+/// Einstellungen für CSV- und Tabellen-Eingaben im Batch-Modus
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InputConfig {
+    /// Trennzeichen für CSV, z.B. `;`
+    pub delimiter: Option<char>,
+    /// Zeichenkodierung für CSV, z.B. `windows-1252`
+    pub encoding: Option<String>,
+    /// Tabellenblatt in ODS/XLSX
+    pub sheet: Option<String>,
+    /// Spaltennamen je Feld, z.B. `{ "name": "Teilnehmer", "email": "E-Mail" }`
+    pub columns: BTreeMap<String, String>,
+}
+
+/// Einstellungen für eine Sprache bei mehrsprachigen Zertifikaten
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageConfig {
+    /// Eigene Vorlage für diese Sprache (sonst die Vorlage aus `--template`)
+    pub template: Option<PathBuf>,
+    /// Feste Texte als Platzhalter, z.B. `{ "HEADING": "Certificate of attendance" }`;
+    /// gleichnamige Felder im Datensatz haben Vorrang
+    pub strings: BTreeMap<String, String>,
+    /// Formulierung für eintägige Kurse, z.B. `on {date}`
+    pub date_single: Option<String>,
+    /// Formulierung für Zeiträume, z.B. `from {from} to {to}`
+    pub date_range: Option<String>,
+    /// Betreff der E-Mail mit `{{PLACEHOLDER}}`-Feldern, z.B. `Your certificate: {{TITLE}}`
+    pub email_subject: Option<String>,
+    /// Text der E-Mail mit `{{PLACEHOLDER}}`-Feldern; `{{CERT_FILE}}` ist der Anhang
+    pub email_body: Option<String>,
+}
+
+/// Einstellungen für Datumsangaben im Zertifikat
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DateConfig {
+    /// Sprache: `de`, `en` oder `fr`
+    pub locale: Option<Locale>,
+    /// `numeric` oder `long`
+    pub style: Option<DateStyle>,
+    /// Monat und Jahr eines Zeitraums nur einmal ausgeben
+    pub collapse: bool,
+    /// Formulierung für eintägige Kurse, z.B. `am {date}`
+    pub single: Option<String>,
+    /// Formulierung für Zeiträume, z.B. `{from}–{to}`
+    pub range: Option<String>,
+}
+
+/// Einstellungen für Bildplatzhalter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImageConfig {
+    /// Einpassen in den Rahmen: `stretch` oder `contain`
+    pub fit: Option<ImageFit>,
+}
+
+/// Einstellungen für QR-Code-Platzhalter
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QrConfig {
+    /// Inhalt für `{{QR}}`, Felder als `{KEY}`
+    pub payload: Option<String>,
+    /// Kantenlänge im Dokument, z.B. `3cm`
+    pub size: Option<String>,
+}
+
+/// Einstellungen für Zertifikats-IDs und das Register
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Registerdatei (JSONL)
+    pub path: Option<PathBuf>,
+    /// Aufbau neuer IDs, z.B. `B1-{year}-{seq:6}` oder `ulid`
+    pub id_format: Option<IdFormat>,
+}
+
+/// Einstellungen für die PDF-Konvertierung
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConverterConfig {
+    /// Name oder Pfad der LibreOffice-Binary (Standard: `soffice`)
+    pub soffice: Option<String>,
+    /// Eigenes LibreOffice-Benutzerprofil
+    pub profile_dir: Option<PathBuf>,
+    /// Zusätzliche Optionen für den PDF-Exportfilter
+    pub filter_options: Option<String>,
+    /// Maximale Laufzeit pro Dokument in Sekunden (Bulk-Aufrufe: mal Anzahl der Dokumente)
+    pub timeout_secs: Option<u64>,
+    /// PDF-Profil, z.B. `pdfa-2b`
+    pub pdf_profile: Option<PdfProfile>,
+    /// Auch die PDF-Standardschriften einbetten
+    pub embed_fonts: bool,
+    /// Getaggtes PDF erzeugen
+    pub tagged_pdf: bool,
+    /// JPEG-Qualität der Bilder (1-100)
+    pub image_quality: Option<u8>,
+    /// Bilder verlustfrei komprimieren
+    pub lossless_images: bool,
+}
+
+impl Config {
+    /// Lädt die Konfiguration aus einer JSON-Datei
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let config = serde_json::from_str(&content)?;
+        Ok(config)
+    }
+
+    /// Lädt `path` falls angegeben, sonst `certgen.config.json` im aktuellen
+    /// Verzeichnis, falls vorhanden, sonst die Standardwerte
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None if Path::new(DEFAULT_CONFIG_FILE).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_FILE))
+            }
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_partial_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "converter": { "soffice": "/opt/libreoffice7.6/program/soffice", "timeout_secs": 120, "pdf_profile": "pdfa-2b" } }"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.converter.soffice.as_deref(),
+            Some("/opt/libreoffice7.6/program/soffice")
+        );
+        assert_eq!(config.converter.timeout_secs, Some(120));
+        assert_eq!(config.converter.pdf_profile, Some(PdfProfile::PdfA2b));
+        assert!(config.converter.profile_dir.is_none());
+    }
+
+    #[test]
+    fn test_parse_registry_config() {
+        let config: Config =
+            serde_json::from_str(r#"{ "registry": { "path": "zertifikate.jsonl", "id_format": "B1-{year}-{seq:6}" } }"#)
+                .unwrap();
+
+        assert_eq!(config.registry.path, Some(PathBuf::from("zertifikate.jsonl")));
+        assert_eq!(
+            config.registry.id_format,
+            Some(IdFormat::Pattern("B1-{year}-{seq:6}".into()))
+        );
+        assert!(serde_json::from_str::<Config>(r#"{ "registry": { "id_format": "B1" } }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_image_config() {
+        let config: Config = serde_json::from_str(r#"{ "images": { "fit": "contain" } }"#).unwrap();
+        assert_eq!(config.images.fit, Some(ImageFit::Contain));
+        assert!(serde_json::from_str::<Config>(r#"{ "images": { "fit": "cover" } }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_date_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "dates": { "locale": "en", "style": "long", "collapse": true, "range": "{from}–{to}" } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.dates.locale, Some(Locale::En));
+        assert_eq!(config.dates.style, Some(DateStyle::Long));
+        assert!(config.dates.collapse);
+        assert_eq!(config.dates.range.as_deref(), Some("{from}–{to}"));
+    }
+
+    #[test]
+    fn test_parse_language_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "languages": { "en": { "template": "vorlage_en.odt", "strings": { "HEADING": "Certificate" } } } }"#,
+        )
+        .unwrap();
+        let english = &config.languages[&Locale::En];
+        assert_eq!(english.template, Some(PathBuf::from("vorlage_en.odt")));
+        assert_eq!(english.strings["HEADING"], "Certificate");
+        assert!(serde_json::from_str::<Config>(r#"{ "languages": { "es": {} } }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_input_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "input": { "delimiter": ";", "encoding": "windows-1252", "columns": { "name": "Teilnehmer" } } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.input.delimiter, Some(';'));
+        assert_eq!(config.input.columns["name"], "Teilnehmer");
+    }
+
+    #[test]
+    fn test_parse_output_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "output": { "filename_template": "{{TITLE}}/{{NAME}}", "max_length": 40, "on_collision": "error",
+                 "sanitize": "keep-unicode", "german_umlauts": true } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.output.filename_template.as_deref(), Some("{{TITLE}}/{{NAME}}"));
+        assert_eq!(config.output.max_length, Some(40));
+        assert_eq!(config.output.on_collision, Some(CollisionPolicy::Error));
+        assert_eq!(config.output.sanitize, Some(SanitizePolicy::KeepUnicode));
+        assert!(config.output.german_umlauts);
+    }
+
+    #[test]
+    fn test_parse_smtp_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "smtp": { "host": "mail.example.com", "security": "tls", "username": "kurs", "auth": ["login"],
+                 "rate_limit": 20 } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.smtp.host.as_deref(), Some("mail.example.com"));
+        assert_eq!(config.smtp.security, Some(SmtpSecurity::Tls));
+        assert_eq!(config.smtp.auth, vec![AuthMechanism::Login]);
+        assert_eq!(config.smtp.rate_limit, Some(20));
+        assert!(serde_json::from_str::<Config>(r#"{ "smtp": { "password": "geheim" } }"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_email_config() {
+        let config: Config = serde_json::from_str(
+            r#"{ "email": { "from": "Kursteam <kurs@example.com>", "bcc": ["archiv@example.com"],
+                 "body_template": "mail.txt" } }"#,
+        )
+        .unwrap();
+        assert_eq!(config.email.from.as_deref(), Some("Kursteam <kurs@example.com>"));
+        assert!(config.email.cc.is_empty());
+        assert_eq!(config.email.bcc, vec!["archiv@example.com".to_string()]);
+        assert_eq!(config.email.body_template, Some(PathBuf::from("mail.txt")));
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        assert!(serde_json::from_str::<Config>(r#"{ "convertr": {} }"#).is_err());
+    }
+}
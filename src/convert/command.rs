@@ -0,0 +1,140 @@
+use crate::convert::{conversion_failed, pdf_output_path, run_converter, PdfConverter};
+use crate::error::Result;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+/// Konvertierung über ein frei konfigurierbares Kommando, z.B.
+/// `"libreoffice --headless --convert-to pdf --outdir {outdir} {input}"` oder
+/// in Tests `"cp {input} {output}"`.
+///
+/// Unterstützte Platzhalter: `{input}` (.odt), `{output}` (Ziel-PDF), `{outdir}`.
+/// Das Kommando wird ohne Shell ausgeführt, einmal pro Datei.
+pub struct CommandConverter {
+    args: Vec<String>,
+    timeout: Option<Duration>,
+}
+
+impl CommandConverter {
+    /// Zerlegt die Kommandovorlage in Programm und Argumente
+    pub fn new(template: &str) -> Result<Self> {
+        let args = split_args(template);
+        if args.is_empty() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Leere Kommandovorlage für die PDF-Konvertierung",
+            )
+            .into());
+        }
+        Ok(Self { args, timeout: None })
+    }
+
+    /// Maximale Laufzeit pro Dokument; danach wird das Kommando beendet
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    fn expand(&self, input: &Path, outdir: &Path) -> Vec<String> {
+        let output = pdf_output_path(input, outdir);
+        self.args
+            .iter()
+            .map(|arg| {
+                arg.replace("{input}", &input.to_string_lossy())
+                    .replace("{output}", &output.to_string_lossy())
+                    .replace("{outdir}", &outdir.to_string_lossy())
+            })
+            .collect()
+    }
+}
+
+impl PdfConverter for CommandConverter {
+    fn convert(&self, inputs: &[&Path], outdir: &Path, _workdir: Option<&Path>) -> Result<()> {
+        for input in inputs {
+            let args = self.expand(input, outdir);
+            let mut command = Command::new(&args[0]);
+            command.args(&args[1..]);
+
+            let output = run_converter(&mut command, self.timeout)?;
+
+            if !output.status.success() {
+                return Err(conversion_failed(
+                    &format!("Konvertierungskommando für {}", input.display()),
+                    &output,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "command"
+    }
+}
+
+/// Teilt eine Kommandozeile an Leerzeichen; einfache und doppelte Anführungszeichen gruppieren
+fn split_args(s: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_arg = false;
+    let mut quote: Option<char> = None;
+
+    for c in s.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => current.push(c),
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_arg = true;
+            }
+            (None, c) if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut current));
+                    in_arg = false;
+                }
+            }
+            (None, c) => {
+                current.push(c);
+                in_arg = true;
+            }
+        }
+    }
+    if in_arg {
+        args.push(current);
+    }
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_args_with_quotes() {
+        assert_eq!(
+            split_args(r#"convert --title "Mein Kurs" '{input}'  {output}"#),
+            vec!["convert", "--title", "Mein Kurs", "{input}", "{output}"]
+        );
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let converter = CommandConverter::new("cp {input} {output}").unwrap();
+        let args = converter.expand(Path::new("/tmp/stage/a.odt"), Path::new("/out"));
+        assert_eq!(args, vec!["cp", "/tmp/stage/a.odt", "/out/a.pdf"]);
+    }
+
+    #[test]
+    fn test_converts_with_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("cert.odt");
+        std::fs::write(&input, b"odt").unwrap();
+
+        let converter = CommandConverter::new("cp {input} {output}").unwrap();
+        converter.convert(&[input.as_path()], dir.path(), None).unwrap();
+
+        assert_eq!(std::fs::read(dir.path().join("cert.pdf")).unwrap(), b"odt");
+    }
+}
@@ -0,0 +1,149 @@
+//! PDF-Konvertierung der befüllten ODT-Dateien.
+//!
+//! `OdfDocument` erzeugt nur die .odt-Dateien; die Umwandlung nach PDF übernimmt
+//! ein austauschbarer `PdfConverter` (lokales soffice, unoserver oder ein frei
+//! konfigurierbares Kommando).
+
+pub mod command;
+pub mod pdf_export;
+pub mod soffice;
+pub mod unoserver;
+
+pub use command::CommandConverter;
+pub use pdf_export::{PdfExportOptions, PdfProfile};
+pub use soffice::SofficeConverter;
+pub use unoserver::UnoserverConverter;
+
+use crate::error::{CertgenError, Result};
+use log::debug;
+use std::io::Read;
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// Backend für die Umwandlung von .odt nach PDF
+pub trait PdfConverter: Send + Sync {
+    /// Konvertiert alle `inputs` nach PDF. Für jede Eingabe muss danach
+    /// `<outdir>/<dateiname ohne endung>.pdf` existieren.
+    ///
+    /// `workdir` ist ein privates Verzeichnis des aufrufenden Workers (bei parallelem
+    /// Rendern), das z.B. für ein eigenes LibreOffice-Profil genutzt werden kann.
+    fn convert(&self, inputs: &[&Path], outdir: &Path, workdir: Option<&Path>) -> Result<()>;
+
+    /// Name des Backends für Log-Ausgaben
+    fn name(&self) -> &str;
+}
+
+/// Zielpfad, den ein Konverter für `input` in `outdir` erzeugen muss
+pub fn pdf_output_path(input: &Path, outdir: &Path) -> std::path::PathBuf {
+    outdir
+        .join(input.file_stem().unwrap_or_default())
+        .with_extension("pdf")
+}
+
+/// Exit-Status und stderr eines beendeten Konverter-Prozesses
+pub(crate) struct ProcessOutput {
+    pub status: ExitStatus,
+    pub stderr: String,
+}
+
+/// Führt ein Konverter-Kommando aus und sammelt dessen stderr. Läuft der Prozess
+/// länger als `timeout`, wird er beendet und `CertgenError::ConverterTimeout` zurückgegeben.
+pub(crate) fn run_converter(command: &mut Command, timeout: Option<Duration>) -> Result<ProcessOutput> {
+    debug!("Running {:?}", command);
+
+    // soffice startet soffice.bin als Kindprozess; mit eigener Prozessgruppe
+    // lässt sich bei einem Timeout der ganze Baum beenden
+    #[cfg(unix)]
+    if timeout.is_some() {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+
+    let mut child = command.stderr(Stdio::piped()).spawn()?;
+
+    // stderr in einem eigenen Thread lesen, damit ein voller Pipe-Puffer den Prozess nicht blockiert
+    let mut stderr_pipe = child.stderr.take().unwrap();
+    let reader = std::thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = stderr_pipe.read_to_string(&mut buf);
+        buf
+    });
+
+    let started = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            kill_process_tree(&mut child);
+            break None;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let stderr = reader.join().unwrap_or_default();
+    match status {
+        Some(status) => Ok(ProcessOutput { status, stderr }),
+        None => Err(CertgenError::ConverterTimeout {
+            program: command.get_program().to_string_lossy().to_string(),
+            seconds: timeout.unwrap_or_default().as_secs(),
+            stderr,
+        }),
+    }
+}
+
+/// Beendet den Prozess samt seiner Prozessgruppe (siehe `run_converter`)
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pgid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: kill() mit negativer PID sendet nur ein Signal an die Prozessgruppe
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Fehler für einen Konverter-Prozess, der mit Exit-Code != 0 beendet wurde
+pub(crate) fn conversion_failed(what: &str, output: &ProcessOutput) -> CertgenError {
+    std::io::Error::other(format!(
+        "{} schlug fehl (exit: {:?}): {}",
+        what,
+        output.status.code(),
+        output.stderr.trim()
+    ))
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_converter_times_out() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo hängt >&2; sleep 5");
+
+        match run_converter(&mut command, Some(Duration::from_millis(200))) {
+            Err(CertgenError::ConverterTimeout { program, stderr, .. }) => {
+                assert_eq!(program, "sh");
+                assert_eq!(stderr.trim(), "hängt");
+            }
+            other => panic!("expected ConverterTimeout, got {:?}", other.map(|o| o.status)),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_converter_captures_stderr() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("echo kaputt >&2; exit 3");
+
+        let output = run_converter(&mut command, None).unwrap();
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(output.stderr.trim(), "kaputt");
+    }
+}
@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// PDF-Variante für den LibreOffice-Export (Filteroption `SelectPdfVersion`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum PdfProfile {
+    /// Normales PDF (LibreOffice-Standard)
+    #[default]
+    #[serde(rename = "pdf")]
+    Pdf,
+    /// PDF/A-1b (ISO 19005-1)
+    #[serde(rename = "pdfa-1b")]
+    PdfA1b,
+    /// PDF/A-2b (ISO 19005-2)
+    #[serde(rename = "pdfa-2b")]
+    PdfA2b,
+    /// PDF/A-3b (ISO 19005-3)
+    #[serde(rename = "pdfa-3b")]
+    PdfA3b,
+}
+
+impl PdfProfile {
+    /// PDF/A verlangt u.a. eingebettete Schriften
+    pub fn is_pdfa(&self) -> bool {
+        *self != PdfProfile::Pdf
+    }
+
+    /// Wert für `SelectPdfVersion`, `None` für den Standard
+    fn select_pdf_version(&self) -> Option<i64> {
+        match self {
+            PdfProfile::Pdf => None,
+            PdfProfile::PdfA1b => Some(1),
+            PdfProfile::PdfA2b => Some(2),
+            PdfProfile::PdfA3b => Some(3),
+        }
+    }
+}
+
+impl FromStr for PdfProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pdf" => Ok(PdfProfile::Pdf),
+            "pdfa-1b" => Ok(PdfProfile::PdfA1b),
+            "pdfa-2b" => Ok(PdfProfile::PdfA2b),
+            "pdfa-3b" => Ok(PdfProfile::PdfA3b),
+            _ => Err(format!(
+                "invalid PDF profile `{}`: expected pdf, pdfa-1b, pdfa-2b or pdfa-3b",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PdfProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PdfProfile::Pdf => "pdf",
+            PdfProfile::PdfA1b => "pdfa-1b",
+            PdfProfile::PdfA2b => "pdfa-2b",
+            PdfProfile::PdfA3b => "pdfa-3b",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Einstellungen für LibreOffices PDF-Exportfilter (`writer_pdf_Export`)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PdfExportOptions {
+    pub profile: PdfProfile,
+    /// Auch die 14 PDF-Standardschriften einbetten
+    pub embed_fonts: bool,
+    /// Getaggtes (barrierearmes) PDF erzeugen
+    pub tagged: bool,
+    /// JPEG-Qualität der Bilder (1-100)
+    pub image_quality: Option<u8>,
+    /// Bilder verlustfrei komprimieren
+    pub lossless_images: bool,
+}
+
+impl PdfExportOptions {
+    /// Filteroptionen im JSON-Format von LibreOffice (ab 7.4), leer bei Standardwerten
+    pub fn to_filter_properties(&self) -> Map<String, Value> {
+        let mut props = Map::new();
+
+        if let Some(version) = self.profile.select_pdf_version() {
+            props.insert("SelectPdfVersion".into(), property("long", version.to_string()));
+        }
+        if self.embed_fonts {
+            props.insert("EmbedStandardFonts".into(), property("boolean", "true"));
+        }
+        if self.tagged {
+            props.insert("UseTaggedPDF".into(), property("boolean", "true"));
+        }
+        if let Some(quality) = self.image_quality {
+            props.insert("Quality".into(), property("long", quality.clamp(1, 100).to_string()));
+        }
+        if self.lossless_images {
+            props.insert("UseLosslessCompression".into(), property("boolean", "true"));
+        }
+
+        props
+    }
+}
+
+fn property(kind: &str, value: impl Into<String>) -> Value {
+    json!({ "type": kind, "value": value.into() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_options_are_empty() {
+        assert!(PdfExportOptions::default().to_filter_properties().is_empty());
+    }
+
+    #[test]
+    fn test_pdfa_properties() {
+        let options = PdfExportOptions {
+            profile: "pdfa-2b".parse().unwrap(),
+            tagged: true,
+            image_quality: Some(85),
+            ..Default::default()
+        };
+        let props = Value::Object(options.to_filter_properties());
+
+        assert_eq!(
+            props,
+            json!({
+                "SelectPdfVersion": { "type": "long", "value": "2" },
+                "UseTaggedPDF": { "type": "boolean", "value": "true" },
+                "Quality": { "type": "long", "value": "85" }
+            })
+        );
+    }
+}
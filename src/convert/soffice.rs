@@ -0,0 +1,231 @@
+use crate::convert::{conversion_failed, run_converter, PdfConverter, PdfExportOptions};
+use crate::error::Result;
+use log::warn;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+use which::which; // Laufzeit-Check ob 'soffice' vorhanden
+
+/// Konvertierung über eine lokale LibreOffice-Installation (`soffice --headless`).
+/// Mehrere Dateien werden mit einem einzigen Aufruf konvertiert.
+pub struct SofficeConverter {
+    program: String,
+    profile_dir: Option<PathBuf>,
+    filter_options: Option<String>,
+    export: PdfExportOptions,
+    timeout: Option<Duration>,
+}
+
+impl SofficeConverter {
+    pub fn new() -> Self {
+        Self {
+            program: "soffice".to_string(),
+            profile_dir: None,
+            filter_options: None,
+            export: PdfExportOptions::default(),
+            timeout: None,
+        }
+    }
+
+    /// Name oder Pfad der LibreOffice-Binary, z.B. `libreoffice` oder `/opt/libreoffice7.6/program/soffice`
+    pub fn with_program(mut self, program: &str) -> Self {
+        self.program = program.to_string();
+        self
+    }
+
+    /// Eigenes Benutzerprofil statt des Standardprofils. Parallele Worker
+    /// bekommen darin jeweils ein Unterverzeichnis.
+    pub fn with_profile_dir(mut self, dir: PathBuf) -> Self {
+        self.profile_dir = Some(dir);
+        self
+    }
+
+    /// Zusätzliche Optionen für den PDF-Exportfilter, angehängt als
+    /// `--convert-to pdf:writer_pdf_Export:<optionen>`
+    pub fn with_filter_options(mut self, options: &str) -> Self {
+        self.filter_options = Some(options.to_string());
+        self
+    }
+
+    /// PDF-Profil (z.B. PDF/A) und weitere Exporteinstellungen
+    pub fn with_export_options(mut self, export: PdfExportOptions) -> Self {
+        self.export = export;
+        self
+    }
+
+    /// Maximale Laufzeit pro Dokument; ein Aufruf mit mehreren Dokumenten (Bulk-Konvertierung)
+    /// darf entsprechend länger laufen, danach wird der Prozess beendet
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Zeitlimit für einen Aufruf mit `documents` Dokumenten
+    fn timeout_for(&self, documents: usize) -> Option<Duration> {
+        self.timeout.map(|timeout| timeout * documents.max(1) as u32)
+    }
+
+    /// Profilverzeichnis für einen Aufruf: konfiguriertes Profil (bei parallelen
+    /// Workern mit Unterverzeichnis pro Worker) oder das Arbeitsverzeichnis des Workers
+    fn profile_for(&self, workdir: Option<&Path>) -> Option<PathBuf> {
+        match (&self.profile_dir, workdir) {
+            (Some(dir), Some(workdir)) => Some(dir.join(workdir.file_name().unwrap_or_default())),
+            (Some(dir), None) => Some(dir.clone()),
+            (None, workdir) => workdir.map(Path::to_path_buf),
+        }
+    }
+
+    /// Baut das `--convert-to`-Argument. Die Exporteinstellungen werden mit
+    /// `filter_options` zusammengeführt, sofern diese im JSON-Format vorliegen;
+    /// bei gleichen Schlüsseln gewinnen die expliziten Filteroptionen.
+    fn convert_to_arg(&self) -> String {
+        let mut props = self.export.to_filter_properties();
+
+        if let Some(raw) = &self.filter_options {
+            match serde_json::from_str::<Value>(raw) {
+                Ok(Value::Object(extra)) => props.extend(extra),
+                _ => {
+                    if !props.is_empty() {
+                        warn!("Filter options are not in JSON format, PDF export settings are ignored");
+                    }
+                    return format!("pdf:writer_pdf_Export:{}", raw);
+                }
+            }
+        }
+
+        if props.is_empty() {
+            "pdf".to_string()
+        } else {
+            format!("pdf:writer_pdf_Export:{}", Value::Object(props))
+        }
+    }
+}
+
+impl Default for SofficeConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PdfConverter for SofficeConverter {
+    fn convert(&self, inputs: &[&Path], outdir: &Path, workdir: Option<&Path>) -> Result<()> {
+        // Prüfe ob soffice verfügbar ist
+        if which(&self.program).is_err() {
+            // Statt eines nicht-existierenden CertgenError::Generic verwenden wir ein std::io::Error
+            // und konvertieren dieses in CertgenError via bestehende From-Implementierung.
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "LibreOffice ({}) nicht gefunden. Bitte installiere LibreOffice oder gib den Pfad mit --soffice an.",
+                    self.program
+                ),
+            ).into());
+        }
+
+        let mut command = Command::new(&self.program);
+        // Eigenes Profil pro Worker, damit parallele Instanzen sich nicht gegenseitig sperren
+        if let Some(profile) = self.profile_for(workdir) {
+            command.arg(format!("-env:UserInstallation={}", file_url(&profile)));
+        }
+        command
+            .arg("--headless")
+            .arg("--convert-to")
+            .arg(self.convert_to_arg())
+            .arg("--outdir")
+            .arg(outdir)
+            .args(inputs);
+
+        let output = run_converter(&mut command, self.timeout_for(inputs.len()))?;
+
+        if !output.status.success() {
+            return Err(conversion_failed("LibreOffice-Konvertierung", &output));
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "soffice"
+    }
+}
+
+/// Wandelt einen Pfad in eine file://-URL um, wie LibreOffice sie für `-env:UserInstallation` erwartet
+pub(crate) fn file_url(path: &Path) -> String {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()
+            .map(|cwd| cwd.join(path))
+            .unwrap_or_else(|_| path.to_path_buf())
+    };
+
+    let mut url = String::from("file://");
+    for byte in absolute.to_string_lossy().replace('\\', "/").bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    url
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_per_worker() {
+        let converter = SofficeConverter::new().with_profile_dir(PathBuf::from("/srv/lo-profile"));
+        assert_eq!(
+            converter.profile_for(Some(Path::new("/tmp/certgen-worker-1-0"))),
+            Some(PathBuf::from("/srv/lo-profile/certgen-worker-1-0"))
+        );
+        assert_eq!(converter.profile_for(None), Some(PathBuf::from("/srv/lo-profile")));
+        assert_eq!(SofficeConverter::new().profile_for(None), None);
+    }
+
+    #[test]
+    fn test_timeout_scales_with_documents() {
+        let converter = SofficeConverter::new().with_timeout(Duration::from_secs(30));
+        assert_eq!(converter.timeout_for(1), Some(Duration::from_secs(30)));
+        assert_eq!(converter.timeout_for(20), Some(Duration::from_secs(600)));
+        assert_eq!(SofficeConverter::new().timeout_for(20), None);
+    }
+
+    #[test]
+    fn test_filter_options() {
+        assert_eq!(SofficeConverter::new().convert_to_arg(), "pdf");
+        assert_eq!(
+            SofficeConverter::new()
+                .with_filter_options(r#"{"UseLosslessCompression":{"type":"boolean","value":"true"}}"#)
+                .convert_to_arg(),
+            r#"pdf:writer_pdf_Export:{"UseLosslessCompression":{"type":"boolean","value":"true"}}"#
+        );
+    }
+
+    #[test]
+    fn test_pdfa_merged_with_filter_options() {
+        let converter = SofficeConverter::new()
+            .with_export_options(PdfExportOptions {
+                profile: crate::convert::PdfProfile::PdfA1b,
+                ..Default::default()
+            })
+            .with_filter_options(r#"{"Watermark":{"type":"string","value":"ENTWURF"}}"#);
+
+        assert_eq!(
+            converter.convert_to_arg(),
+            r#"pdf:writer_pdf_Export:{"SelectPdfVersion":{"type":"long","value":"1"},"Watermark":{"type":"string","value":"ENTWURF"}}"#
+        );
+    }
+
+    #[test]
+    fn test_file_url_escapes_special_characters() {
+        assert_eq!(
+            file_url(Path::new("/tmp/certgen profile-1")),
+            "file:///tmp/certgen%20profile-1"
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::convert::{conversion_failed, pdf_output_path, run_converter, PdfConverter};
+use crate::error::Result;
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use which::which;
+
+/// Konvertierung über einen dauerhaft laufenden `unoserver`, der auf einem lokalen
+/// Port lauscht. Der Aufruf erfolgt über dessen Client `unoconvert`, dadurch entfällt
+/// der LibreOffice-Start pro Dokument.
+pub struct UnoserverConverter {
+    host: String,
+    port: u16,
+    program: String,
+    timeout: Option<Duration>,
+}
+
+impl UnoserverConverter {
+    pub fn new(host: &str, port: u16) -> Self {
+        Self {
+            host: host.to_string(),
+            port,
+            program: "unoconvert".to_string(),
+            timeout: None,
+        }
+    }
+
+    /// Maximale Laufzeit pro Dokument; danach wird `unoconvert` beendet
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Default for UnoserverConverter {
+    /// unoserver lauscht standardmäßig auf 127.0.0.1:2003
+    fn default() -> Self {
+        Self::new("127.0.0.1", 2003)
+    }
+}
+
+impl PdfConverter for UnoserverConverter {
+    fn convert(&self, inputs: &[&Path], outdir: &Path, _workdir: Option<&Path>) -> Result<()> {
+        if which(&self.program).is_err() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "unoconvert nicht im PATH gefunden. Bitte installiere unoserver (pip install unoserver)."
+            ).into());
+        }
+
+        // unoconvert verarbeitet genau eine Datei pro Aufruf
+        for input in inputs {
+            let mut command = Command::new(&self.program);
+            command
+                .arg("--host")
+                .arg(&self.host)
+                .arg("--port")
+                .arg(self.port.to_string())
+                .arg("--convert-to")
+                .arg("pdf")
+                .arg(input)
+                .arg(pdf_output_path(input, outdir));
+
+            let output = run_converter(&mut command, self.timeout)?;
+
+            if !output.status.success() {
+                return Err(conversion_failed(
+                    &format!("unoserver-Konvertierung von {}", input.display()),
+                    &output,
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn name(&self) -> &str {
+        "unoserver"
+    }
+}
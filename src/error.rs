@@ -11,8 +11,63 @@ pub enum CertgenError {
     #[error("Template file not found: {0}")]
     TemplateNotFound(String),
 
-    #[error("Placeholder '{0}' not found in template")]
-    PlaceholderNotFound(String),
+    #[error("Placeholder '{name}' has no value{}", record_suffix(.record))]
+    PlaceholderNotFound { name: String, record: Option<usize> },
+
+    #[error("Validation failed: {0} record(s) with unfilled placeholders")]
+    ValidationFailed(usize),
+
+    #[error("Batch finished with {0} failed certificate(s)")]
+    BatchFailed(usize),
+
+    #[error("PDF converter '{program}' timed out after {seconds}s: {stderr}")]
+    ConverterTimeout {
+        program: String,
+        seconds: u64,
+        stderr: String,
+    },
+
+    #[error("Signature error: {0}")]
+    Signature(String),
+
+    #[error("OpenSSL error: {0}")]
+    OpenSsl(#[from] openssl::error::ErrorStack),
+
+    #[error("PDF error: {0}")]
+    Pdf(#[from] lopdf::Error),
+
+    #[error("QR code error: {0}")]
+    QrCode(String),
+
+    #[error("Template syntax error: {0}")]
+    TemplateSyntax(String),
+
+    #[error("Filter error: {0}")]
+    Filter(String),
+
+    #[error("Image error: {0}")]
+    Image(String),
+
+    #[error("Date error: {0}")]
+    Date(String),
+
+    #[error("Data source error: {0}")]
+    DataSource(String),
+
+    #[error("Output file name error: {0}")]
+    OutputName(String),
+
+    #[error("Email error: {0}")]
+    Email(String),
+
+    #[error("SMTP error: {0}")]
+    Smtp(String),
+
+    #[error("{0} email(s) could not be delivered")]
+    DeliveryFailed(usize),
+
+    #[error("Registry error: {0}")]
+    Registry(String),
 
     #[error("Invalid template format")]
     InvalidTemplate,
@@ -21,4 +76,21 @@ pub enum CertgenError {
     Serialization(#[from] serde_json::Error),
 }
 
+impl CertgenError {
+    /// Ordnet einen Fehler dem Datensatz mit dem angegebenen Index (Batch) zu
+    pub fn with_record(self, index: usize) -> Self {
+        match self {
+            CertgenError::PlaceholderNotFound { name, .. } => CertgenError::PlaceholderNotFound {
+                name,
+                record: Some(index),
+            },
+            other => other,
+        }
+    }
+}
+
+fn record_suffix(record: &Option<usize>) -> String {
+    record.map(|idx| format!(" (record {})", idx)).unwrap_or_default()
+}
+
 pub type Result<T> = std::result::Result<T, CertgenError>;
@@ -0,0 +1,422 @@
+//! Dateinamen der erzeugten Zertifikate: Vorlage mit `{{PLACEHOLDER}}` (samt Filtern),
+//! Unterverzeichnisse, maximale Länge und Umgang mit doppelten Namen im Batch, sowie die
+//! Bereinigung eingesetzter Werte mit Umschrift nach ASCII.
+
+mod translit;
+
+use crate::error::{CertgenError, Result};
+use crate::odf::{FilterExpression, FilterRegistry};
+use icu_normalizer::ComposingNormalizerBorrowed;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Bisheriges Schema `<Name>_<Titel>`, ohne Titel `<Name>_Kurs`
+pub const DEFAULT_FILENAME_TEMPLATE: &str = r#"{{NAME}}_{{TITLE|default:"Kurs"}}"#;
+
+/// Standardlänge eines Namensteils in Zeichen (ohne Sprachendung, Zähler und `.pdf`)
+pub const DEFAULT_MAX_LENGTH: usize = 100;
+
+/// Zeichen, die in festen Teilen der Vorlage nicht erlaubt sind (Windows und Unix)
+const FORBIDDEN_CHARS: [char; 8] = ['\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Bereinigt einen Wert mit den Standardeinstellungen (`ascii-translit`), z.B.
+/// `Çelik Müller` → `Celik_Muller`
+pub fn sanitize_filename(s: &str) -> String {
+    Sanitizer::default().sanitize(s)
+}
+
+/// Welche Zeichen in Dateinamen erhalten bleiben
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SanitizePolicy {
+    /// Buchstaben nach ASCII umschreiben (`Ç` → `C`, `Ж` → `Zh`), übrige Zeichen → `_`
+    #[default]
+    AsciiTranslit,
+    /// Buchstaben und Ziffern aller Schriften behalten, nur Sonderzeichen → `_`
+    KeepUnicode,
+    /// Nur `A–Z`, `a–z`, `0–9`, `-` und `_`, alles andere → `_` (ohne Umschrift)
+    Strict,
+}
+
+impl FromStr for SanitizePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "ascii-translit" => Ok(SanitizePolicy::AsciiTranslit),
+            "keep-unicode" => Ok(SanitizePolicy::KeepUnicode),
+            "strict" => Ok(SanitizePolicy::Strict),
+            _ => Err(format!(
+                "invalid sanitize policy `{}`: expected ascii-translit, keep-unicode or strict",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SanitizePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SanitizePolicy::AsciiTranslit => "ascii-translit",
+            SanitizePolicy::KeepUnicode => "keep-unicode",
+            SanitizePolicy::Strict => "strict",
+        })
+    }
+}
+
+/// Bereinigt Werte für Dateinamen: Leerzeichen und Sonderzeichen werden zu `_`,
+/// Buchstaben je nach `SanitizePolicy` umgeschrieben oder behalten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sanitizer {
+    pub policy: SanitizePolicy,
+    /// Deutsche Umschrift der Umlaute und des ß (`ä` → `ae`, `ß` → `ss`) statt `ä` → `a`, `ß` → `s`
+    pub german: bool,
+}
+
+impl Sanitizer {
+    pub fn new(policy: SanitizePolicy) -> Self {
+        Self { policy, german: false }
+    }
+
+    /// Schreibt Umlaute als `ae`, `oe`, `ue` und `ß` als `ss` (nur bei `ascii-translit`)
+    pub fn with_german_umlauts(mut self, german: bool) -> Self {
+        self.german = german;
+        self
+    }
+
+    pub fn sanitize(&self, s: &str) -> String {
+        // Zerlegte Zeichen (z.B. u + ◌̈ aus macOS) zuerst zusammensetzen, damit sie wie ü umgeschrieben werden
+        let s = ComposingNormalizerBorrowed::new_nfc().normalize(s);
+        let mut result = String::with_capacity(s.len());
+        for c in s.chars() {
+            match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => result.push(c),
+                _ if c.is_ascii() => result.push('_'),
+                _ => match self.policy {
+                    SanitizePolicy::Strict => result.push('_'),
+                    SanitizePolicy::KeepUnicode if c.is_alphanumeric() || translit::is_combining_mark(c) => {
+                        result.push(c)
+                    }
+                    SanitizePolicy::KeepUnicode => result.push('_'),
+                    SanitizePolicy::AsciiTranslit if translit::is_combining_mark(c) => {}
+                    SanitizePolicy::AsciiTranslit => match translit::transliterate(c, self.german) {
+                        Some(text) => result.push_str(&text),
+                        None => result.push('_'),
+                    },
+                },
+            }
+        }
+        result
+    }
+}
+
+/// Was passiert, wenn zwei Zertifikate im selben Lauf denselben Dateinamen bekommen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Zähler anhängen: `Max_Rust.pdf`, `Max_Rust_2.pdf`, …
+    #[default]
+    Suffix,
+    /// Mit einem Fehler abbrechen
+    Error,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "suffix" => Ok(CollisionPolicy::Suffix),
+            "error" => Ok(CollisionPolicy::Error),
+            _ => Err(format!("invalid collision policy `{}`: expected suffix or error", s)),
+        }
+    }
+}
+
+impl fmt::Display for CollisionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CollisionPolicy::Suffix => "suffix",
+            CollisionPolicy::Error => "error",
+        })
+    }
+}
+
+/// Vorlage für Dateinamen, z.B. `{{TITLE}}/{{DATE}}_{{CERT_ID}}_{{NAME}}`.
+/// `/` trennt Unterverzeichnisse; eingesetzte Werte werden bereinigt und können daher
+/// keine weiteren Verzeichnisse erzeugen.
+#[derive(Clone)]
+pub struct FilenameTemplate {
+    source: String,
+    segments: Vec<String>,
+    max_length: usize,
+    sanitizer: Sanitizer,
+    filters: FilterRegistry,
+}
+
+impl FilenameTemplate {
+    /// Prüft die Vorlage: keine absoluten Pfade, kein `..`, keine leeren Teile und
+    /// keine unzulässigen Zeichen außerhalb der Platzhalter
+    pub fn parse(template: &str) -> Result<Self> {
+        let source = template.trim().to_string();
+        let invalid = |reason: &str| CertgenError::OutputName(format!("file name template `{}` {}", source, reason));
+
+        if source.starts_with('/') {
+            return Err(invalid("must be relative to the output directory"));
+        }
+        let mut segments: Vec<String> = source.split('/').map(str::to_string).collect();
+        if let Some(last) = segments.last_mut() {
+            if last.to_lowercase().ends_with(".pdf") {
+                last.truncate(last.len() - 4);
+            }
+        }
+
+        let re = placeholder_regex();
+        for segment in &segments {
+            for caps in re.captures_iter(segment) {
+                FilterExpression::parse(&caps[1])?;
+            }
+            let literal = re.replace_all(segment, "");
+            if segment.is_empty() || segment == "." || segment == ".." {
+                return Err(invalid("contains an empty, `.` or `..` path component"));
+            }
+            if let Some(c) = literal.chars().find(|c| FORBIDDEN_CHARS.contains(c) || c.is_control()) {
+                return Err(invalid(&format!("contains the character `{}`", c)));
+            }
+            if literal.contains("{{") || literal.contains("}}") {
+                return Err(invalid("contains an unclosed placeholder"));
+            }
+        }
+
+        Ok(Self {
+            source,
+            segments,
+            max_length: DEFAULT_MAX_LENGTH,
+            sanitizer: Sanitizer::default(),
+            filters: FilterRegistry::new(),
+        })
+    }
+
+    /// Maximale Länge jedes Namensteils in Zeichen; längere werden abgeschnitten
+    pub fn with_max_length(mut self, max_length: usize) -> Self {
+        self.max_length = max_length.max(1);
+        self
+    }
+
+    /// Bereinigung der eingesetzten Werte (Standard: `ascii-translit`)
+    pub fn with_sanitizer(mut self, sanitizer: Sanitizer) -> Self {
+        self.sanitizer = sanitizer;
+        self
+    }
+
+    /// Filter für `{{KEY|filter}}` (Standard: die eingebauten Filter)
+    pub fn with_filters(mut self, filters: FilterRegistry) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Relativer Pfad ohne Endung für die Ersetzungen eines Zertifikats
+    pub fn render(&self, replacements: &HashMap<String, String>) -> Result<PathBuf> {
+        let re = placeholder_regex();
+        let mut path = PathBuf::new();
+        for segment in &self.segments {
+            let mut name = String::new();
+            let mut last = 0;
+            for caps in re.captures_iter(segment) {
+                let placeholder = caps.get(0).unwrap();
+                name.push_str(&segment[last..placeholder.start()]);
+                name.push_str(&self.sanitizer.sanitize(&self.value(&caps[1], replacements)?));
+                last = placeholder.end();
+            }
+            name.push_str(&segment[last..]);
+
+            let name: String = name.trim().chars().take(self.max_length).collect();
+            if name.is_empty() || name.chars().all(|c| c == '.') {
+                return Err(CertgenError::OutputName(format!(
+                    "file name template `{}` gives an empty name",
+                    self.source
+                )));
+            }
+            path.push(name);
+        }
+        Ok(path)
+    }
+
+    fn value(&self, inner: &str, replacements: &HashMap<String, String>) -> Result<String> {
+        let expression = FilterExpression::parse(inner)?;
+        let value = self
+            .filters
+            .apply(&expression, replacements.get(&expression.key).map(String::as_str))?;
+        value.ok_or(CertgenError::PlaceholderNotFound {
+            name: expression.key,
+            record: None,
+        })
+    }
+}
+
+impl Default for FilenameTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_FILENAME_TEMPLATE).unwrap()
+    }
+}
+
+impl fmt::Debug for FilenameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FilenameTemplate")
+            .field("source", &self.source)
+            .field("max_length", &self.max_length)
+            .field("sanitizer", &self.sanitizer)
+            .finish()
+    }
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{\{([^{}]+)\}\}").unwrap()
+}
+
+/// Vergibt die Dateinamen eines Laufs und erkennt doppelte Namen (ohne Beachtung der
+/// Groß-/Kleinschreibung, wegen Windows und macOS)
+#[derive(Debug, Default)]
+pub struct OutputNames {
+    policy: CollisionPolicy,
+    used: HashSet<String>,
+}
+
+impl OutputNames {
+    pub fn new(policy: CollisionPolicy) -> Self {
+        Self {
+            policy,
+            used: HashSet::new(),
+        }
+    }
+
+    /// Reserviert `path`; ist der Name schon vergeben, wird je nach Richtlinie ein
+    /// Zähler angehängt oder ein Fehler zurückgegeben
+    pub fn claim(&mut self, path: PathBuf) -> Result<PathBuf> {
+        if self.used.insert(name_key(&path)) {
+            return Ok(path);
+        }
+        if self.policy == CollisionPolicy::Error {
+            return Err(CertgenError::OutputName(format!(
+                "{} would be created for more than one certificate",
+                path.display()
+            )));
+        }
+        let path = (2..)
+            .map(|n| with_counter(&path, n))
+            .find(|candidate| !self.used.contains(&name_key(candidate)))
+            .unwrap();
+        self.used.insert(name_key(&path));
+        Ok(path)
+    }
+}
+
+fn name_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// `Max_Rust.pdf` → `Max_Rust_2.pdf`
+fn with_counter(path: &Path, counter: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, counter, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, counter),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements() -> HashMap<String, String> {
+        HashMap::from([
+            ("NAME".to_string(), "Max Müller".to_string()),
+            ("TITLE".to_string(), "Rust/Grundlagen".to_string()),
+            ("DATE".to_string(), "15.01.2024".to_string()),
+            ("CERT_ID".to_string(), "CERT-2024-000001".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_sanitize_filename() {
+        assert_eq!(sanitize_filename("Max Mustermann"), "Max_Mustermann");
+        assert_eq!(sanitize_filename("Test/File"), "Test_File");
+        assert_eq!(sanitize_filename("Müller"), "Muller");
+        assert_eq!(sanitize_filename("Çelik ÄÖÜ"), "Celik_AOU");
+        assert_eq!(sanitize_filename("Müller & Söhne"), "Muller___Sohne");
+        assert_eq!(sanitize_filename("Weiß – 中文"), "Weis_____");
+        // zerlegte Umlaute (NFD), wie sie z.B. macOS liefert
+        assert_eq!(sanitize_filename("Mu\u{0308}ller"), "Muller");
+        assert_eq!(sanitize_filename("İstanbul"), "Istanbul");
+    }
+
+    #[test]
+    fn test_sanitize_policies() {
+        let german = Sanitizer::default().with_german_umlauts(true);
+        assert_eq!(german.sanitize("Jürgen Größe"), "Juergen_Groesse");
+        assert_eq!(german.sanitize("Ju\u{0308}rgen Weiß"), "Juergen_Weiss");
+
+        let unicode = Sanitizer::new(SanitizePolicy::KeepUnicode);
+        assert_eq!(unicode.sanitize("Jürgen/Ζωή 中文.pdf"), "Jürgen_Ζωή_中文_pdf");
+
+        let strict = Sanitizer::new(SanitizePolicy::Strict);
+        assert_eq!(strict.sanitize("Jürgen Çelik"), "J_rgen__elik");
+
+        assert_eq!("keep-unicode".parse::<SanitizePolicy>(), Ok(SanitizePolicy::KeepUnicode));
+        assert!("ascii".parse::<SanitizePolicy>().is_err());
+    }
+
+    #[test]
+    fn test_default_template() {
+        let template = FilenameTemplate::default();
+        assert_eq!(template.render(&replacements()).unwrap(), PathBuf::from("Max_Muller_Rust_Grundlagen"));
+
+        let mut without_title = replacements();
+        without_title.remove("TITLE");
+        assert_eq!(template.render(&without_title).unwrap(), PathBuf::from("Max_Muller_Kurs"));
+    }
+
+    #[test]
+    fn test_template_with_subdirectory_and_filters() {
+        let template = FilenameTemplate::parse(r#"{{TITLE}}/{{DATE|date:"%Y-%m-%d"}}_{{CERT_ID}}_{{NAME|lower}}.pdf"#).unwrap();
+        assert_eq!(
+            template.render(&replacements()).unwrap(),
+            PathBuf::from("Rust_Grundlagen/2024-01-15_CERT-2024-000001_max_muller")
+        );
+
+        let short = FilenameTemplate::parse("{{CERT_ID}}").unwrap().with_max_length(9);
+        assert_eq!(short.render(&replacements()).unwrap(), PathBuf::from("CERT-2024"));
+
+        let missing = FilenameTemplate::parse("{{INSTRUCTOR}}").unwrap().render(&replacements());
+        assert!(matches!(missing, Err(CertgenError::PlaceholderNotFound { name, .. }) if name == "INSTRUCTOR"));
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        for template in ["/abs/{{NAME}}", "../{{NAME}}", "{{TITLE}}//{{NAME}}", "{{NAME}}:x", "{{NAME", ""] {
+            assert!(
+                matches!(FilenameTemplate::parse(template), Err(CertgenError::OutputName(_))),
+                "{}",
+                template
+            );
+        }
+    }
+
+    #[test]
+    fn test_collisions() {
+        let mut names = OutputNames::new(CollisionPolicy::Suffix);
+        assert_eq!(names.claim("out/Max.pdf".into()).unwrap(), PathBuf::from("out/Max.pdf"));
+        assert_eq!(names.claim("out/max.pdf".into()).unwrap(), PathBuf::from("out/max_2.pdf"));
+        assert_eq!(names.claim("out/Max.pdf".into()).unwrap(), PathBuf::from("out/Max_3.pdf"));
+        assert_eq!(names.claim("out/Max_2.pdf".into()).unwrap(), PathBuf::from("out/Max_2_2.pdf"));
+
+        let mut strict = OutputNames::new(CollisionPolicy::Error);
+        strict.claim("out/Max.pdf".into()).unwrap();
+        assert!(matches!(strict.claim("out/Max.pdf".into()), Err(CertgenError::OutputName(_))));
+    }
+}
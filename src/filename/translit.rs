@@ -0,0 +1,177 @@
+//! Umschrift von Buchstaben nach ASCII für Dateinamen: Latein mit diakritischen Zeichen
+//! (Westeuropa, Skandinavien, Polen, Tschechien, Türkei, Rumänien, …), Kyrillisch und
+//! Griechisch. Großbuchstaben werden über ihren Kleinbuchstaben umgeschrieben.
+
+/// ASCII-Umschrift eines Zeichens, `None` wenn es keine gibt. Mit `german` werden
+/// Umlaute als `ae`, `oe`, `ue` und `ß` als `ss` geschrieben statt `a`, `o`, `u` und `s`.
+pub(crate) fn transliterate(c: char, german: bool) -> Option<String> {
+    if c.is_ascii() {
+        return Some(c.to_string());
+    }
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    if lower.is_ascii() {
+        // z.B. İ (türkisch): klein ist das i mit kombinierendem Punkt, Umschrift I
+        return Some(lower.to_ascii_uppercase().to_string());
+    }
+    let text = lowercase(lower, german)?;
+    if lower == c {
+        return Some(text.to_string());
+    }
+    // Großbuchstabe: nur den ersten Buchstaben der Umschrift groß schreiben (Ж → Zh)
+    let mut chars = text.chars();
+    Some(match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    })
+}
+
+/// Kombinierende diakritische Zeichen (z.B. aus zerlegten Umlauten unter macOS)
+pub(crate) fn is_combining_mark(c: char) -> bool {
+    matches!(c, '\u{0300}'..='\u{036F}')
+}
+
+fn lowercase(c: char, german: bool) -> Option<&'static str> {
+    if german {
+        match c {
+            'ä' => return Some("ae"),
+            'ö' => return Some("oe"),
+            'ü' => return Some("ue"),
+            'ß' => return Some("ss"),
+            _ => {}
+        }
+    }
+    Some(match c {
+        // Latein
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'æ' => "ae",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ď' | 'đ' | 'ð' => "d",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => "g",
+        'ĥ' | 'ħ' => "h",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ı' => "i",
+        'ĳ' => "ij",
+        'ĵ' => "j",
+        'ķ' => "k",
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => "l",
+        'ñ' | 'ń' | 'ņ' | 'ň' | 'ŉ' => "n",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'œ' => "oe",
+        'ŕ' | 'ŗ' | 'ř' => "r",
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => "s",
+        'ß' => "s",
+        'ţ' | 'ť' | 'ŧ' | 'ț' => "t",
+        'þ' => "th",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ŵ' => "w",
+        'ý' | 'ÿ' | 'ŷ' => "y",
+        'ź' | 'ż' | 'ž' => "z",
+
+        // Kyrillisch (russisch, ukrainisch, belarussisch, serbisch, makedonisch, bulgarisch)
+        'а' => "a",
+        'б' => "b",
+        'в' => "v",
+        'г' | 'ґ' => "g",
+        'ѓ' => "gj",
+        'д' => "d",
+        'ђ' => "dj",
+        'е' | 'ё' | 'э' => "e",
+        'є' => "ye",
+        'ж' => "zh",
+        'з' => "z",
+        'ѕ' => "dz",
+        'и' | 'і' => "i",
+        'ї' => "yi",
+        'й' | 'ы' => "y",
+        'ј' => "j",
+        'к' => "k",
+        'ќ' => "kj",
+        'л' => "l",
+        'љ' => "lj",
+        'м' => "m",
+        'н' => "n",
+        'њ' => "nj",
+        'о' => "o",
+        'п' => "p",
+        'р' => "r",
+        'с' => "s",
+        'т' => "t",
+        'ћ' => "c",
+        'у' | 'ў' => "u",
+        'ф' => "f",
+        'х' => "kh",
+        'ц' => "ts",
+        'ч' => "ch",
+        'џ' => "dz",
+        'ш' => "sh",
+        'щ' => "shch",
+        'ъ' | 'ь' => "",
+        'ю' => "yu",
+        'я' => "ya",
+
+        // Griechisch
+        'α' | 'ά' => "a",
+        'β' => "v",
+        'γ' => "g",
+        'δ' => "d",
+        'ε' | 'έ' => "e",
+        'ζ' => "z",
+        'η' | 'ή' => "i",
+        'θ' => "th",
+        'ι' | 'ί' | 'ϊ' | 'ΐ' => "i",
+        'κ' => "k",
+        'λ' => "l",
+        'μ' => "m",
+        'ν' => "n",
+        'ξ' => "x",
+        'ο' | 'ό' => "o",
+        'π' => "p",
+        'ρ' => "r",
+        'σ' | 'ς' => "s",
+        'τ' => "t",
+        'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y",
+        'φ' => "f",
+        'χ' => "ch",
+        'ψ' => "ps",
+        'ω' | 'ώ' => "o",
+
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn word(text: &str, german: bool) -> String {
+        text.chars().map(|c| transliterate(c, german).unwrap_or_else(|| "?".into())).collect()
+    }
+
+    #[test]
+    fn test_latin() {
+        assert_eq!(word("Müller", false), "Muller");
+        assert_eq!(word("Müller", true), "Mueller");
+        assert_eq!(word("ÄÖÜ", true), "AeOeUe");
+        assert_eq!(word("Straße", false), "Strase");
+        assert_eq!(word("Straße", true), "Strasse");
+        assert_eq!(word("Çelik Şahin İlhan ığ", false), "Celik Sahin Ilhan ig");
+        assert_eq!(word("Łukasz Żółć", false), "Lukasz Zolc");
+        assert_eq!(word("Søren Ærø Åsa", false), "Soren Aero Asa");
+        assert_eq!(word("Dvořák Ștefan", false), "Dvorak Stefan");
+    }
+
+    #[test]
+    fn test_cyrillic_and_greek() {
+        assert_eq!(word("Жуков Щукин", false), "Zhukov Shchukin");
+        assert_eq!(word("Юлия Їжак", false), "Yuliya Yizhak");
+        assert_eq!(word("Σωκράτης", false), "Sokratis");
+        assert_eq!(word("Ψυχή", false), "Psychi");
+    }
+
+    #[test]
+    fn test_unknown_characters() {
+        assert_eq!(transliterate('€', false), None);
+        assert_eq!(transliterate('中', false), None);
+        assert!(is_combining_mark('\u{0308}'));
+    }
+}
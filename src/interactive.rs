@@ -1,3 +1,4 @@
+use crate::template::dates::{parse_date, storage_text};
 use crate::template::CertificateData;
 use crate::error::Result;
 use std::io::{self, Write};
@@ -53,8 +54,28 @@ pub fn create_json_interactive(output_path: &str) -> Result<()> {
         println!("❌ Datum darf nicht leer sein!");
         return Ok(());
     }
+    let date_from = match parse_date(&date_from) {
+        Ok(date) => date,
+        Err(e) => {
+            println!("❌ Ungültiges Datum: {}", e);
+            return Ok(());
+        }
+    };
     
-    let date_to = read_optional_line("End-Datum (leer lassen für eintägigen Kurs): ")?;
+    let date_to = match read_optional_line("End-Datum (leer lassen für eintägigen Kurs): ")? {
+        None => None,
+        Some(text) => match parse_date(&text) {
+            Ok(date) if date >= date_from => Some(date),
+            Ok(_) => {
+                println!("❌ End-Datum liegt vor dem Start-Datum!");
+                return Ok(());
+            }
+            Err(e) => {
+                println!("❌ Ungültiges Datum: {}", e);
+                return Ok(());
+            }
+        },
+    };
 
     // Agenda abfragen
     println!();
@@ -138,10 +159,7 @@ pub fn create_json_interactive(output_path: &str) -> Result<()> {
         
         let mail = read_line(&format!("  {}. E-Mail: ", participant_number ))?;
         // Bestimme das Haupt-Datum (für eintägig = date_from, für mehrtägig = date_to)
-        let main_date = match &date_to {
-            Some(to) => to.clone(),
-            None => date_from.clone(),
-        };
+        let main_date = date_to.unwrap_or(date_from);
         
         // CertificateData::new erwartet (name, date, agenda)
         let mut cert_data = CertificateData::new(
@@ -154,9 +172,9 @@ pub fn create_json_interactive(output_path: &str) -> Result<()> {
         cert_data.add_field("TITLE".to_string(), title.clone());
 
         // Datumbereich hinzufügen (falls mehrtägig)
-        if let Some(ref to) = date_to {
-            cert_data.date_from = Some(date_from.clone());
-            cert_data.date_to = Some(to.clone());
+        if let Some(to) = date_to {
+            cert_data.date_from = Some(date_from);
+            cert_data.date_to = Some(to);
         }
 
         // custom_fields hinzufügen
@@ -192,9 +210,9 @@ pub fn create_json_interactive(output_path: &str) -> Result<()> {
     println!("📚 Kurstitel: {}", title);
     
     if let Some(to) = date_to {
-        println!("📅 Zeitraum: {} bis {}", date_from, to);
+        println!("📅 Zeitraum: {} bis {}", storage_text(date_from), storage_text(to));
     } else {
-        println!("📅 Datum: {}", date_from);
+        println!("📅 Datum: {}", storage_text(date_from));
     }
     
     println!("📋 Agenda-Punkte: {}", agenda_items.len());
@@ -2,14 +2,25 @@
 //!
 //! A library and CLI tool for generating certificates from ODF templates.
 
+pub mod convert;
 pub mod error;
+pub mod filename;
+pub mod manifest;
 pub mod odf;
+pub mod registry;
+pub mod sign;
+pub mod smtp;
+pub mod source;
 pub mod template;
 pub mod cli;
+pub mod config;
 pub mod interactive;
+pub mod validate;
 
 // Re-exports
+pub use convert::PdfConverter;
 pub use error::{CertgenError, Result};
-pub use odf::{OdfDocument, PlaceholderReplacer};
+pub use odf::{DocumentPart, MissingPolicy, OdfDocument, PlaceholderInfo, PlaceholderReplacer, RenderJob};
 pub use template::CertificateData;
 pub use cli::{Cli, Commands};
+pub use validate::{RecordReport, ValidationReport};
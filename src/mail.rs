@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::fs;
 use std::io::Write;
 use std::path::Path;
@@ -6,19 +7,63 @@ use base64::{engine::general_purpose, Engine as _};
 use chrono::Utc;
 
 use certgen::error::Result;
+use certgen::template::{Locale, Localization, TextTemplate};
+
+/// Absender, weitere Empfänger und Textvorlagen der E-Mails
+#[derive(Debug, Clone, Default)]
+pub struct EmailOptions {
+    /// Absender, z.B. `Kursteam <kurs@example.com>`; ohne ihn fehlt die From-Zeile
+    pub from: Option<String>,
+    pub reply_to: Option<String>,
+    pub cc: Vec<String>,
+    pub bcc: Vec<String>,
+    /// Vorlage für den Betreff aller Sprachen (sonst je Sprache aus der Konfiguration)
+    pub subject: Option<TextTemplate>,
+    /// Vorlage für den Text aller Sprachen (sonst je Sprache aus der Konfiguration)
+    pub body: Option<TextTemplate>,
+}
+
+impl EmailOptions {
+    /// Betreff und Text einer E-Mail in der Sprache des Zertifikats, mit den Feldern
+    /// des Datensatzes
+    pub fn render(
+        &self,
+        localization: &Localization,
+        language: Option<Locale>,
+        fields: &HashMap<String, String>,
+    ) -> Result<(String, String)> {
+        let subject = match &self.subject {
+            Some(template) => template.render(fields)?,
+            None => TextTemplate::parse(localization.email_subject(language))?.render(fields)?,
+        };
+        let body = match &self.body {
+            Some(template) => template.render(fields)?,
+            None => TextTemplate::parse(localization.email_body(language))?.render(fields)?,
+        };
+        Ok((single_line(&subject), body))
+    }
+
+    /// Empfänger, Absender, Kopie-Empfänger sowie Betreff und Text einer E-Mail als Text
+    /// für einen Inhalts-Hash
+    pub fn settings(&self, to: &str, subject: &str, body: &str) -> String {
+        format!(
+            "to={:?} from={:?} reply_to={:?} cc={:?} bcc={:?} subject={:?} body={:?}",
+            to, self.from, self.reply_to, self.cc, self.bcc, subject, body
+        )
+    }
+}
 
 /// Erzeugt eine .eml Datei mit einfachem Textkörper und einem PDF-Anhang.
+/// - `options` liefert Absender, Antwortadresse und Kopie-Empfänger
 /// - `to` ist die Empfänger-E-Mail-Adresse (wird in "To:" geschrieben)
-/// - `subject` ist der Mail-Subject
-/// - `body_template` ist ein String mit Platzhaltern `<name>` und `<cert>`
-/// - `name` wird für `<name>` eingesetzt
+/// - `subject` und `body` sind Betreff und Text mit bereits eingesetzten Feldern
 /// - `attachment_path` ist der Pfad zur PDF-Datei, die angehängt wird
 /// - `output_eml_path` ist der Pfad zur zu erzeugenden .eml-Datei
 pub fn create_eml(
+    options: &EmailOptions,
     to: &str,
     subject: &str,
-    body_template: &str,
-    name: &str,
+    body: &str,
     attachment_path: &Path,
     output_eml_path: &Path,
 ) -> Result<()> {
@@ -29,24 +74,30 @@ pub fn create_eml(
         .and_then(|s| s.to_str())
         .unwrap_or("attachment.pdf");
 
-    // Ersetze Platzhalter im Body
-    let body = body_template
-        .replace("<name>", name)
-        .replace("<cert>", attachment_filename);
-
     // Boundary erzeugen (einfach, eindeutig genug)
     let boundary = format!("----=_CERTGEN_{}",
-        Utc::now().timestamp_nanos());
+        Utc::now().timestamp_nanos_opt().unwrap_or_default());
 
     // Header
     let date = Utc::now().to_rfc2822();
-    // From: kannst du anpassen; hier ein neutraler Default
-    let from = "lindermayr@b1-systems.de";
 
     let mut eml = String::new();
-    eml.push_str(&format!("From: {}\r\n", from));
-    eml.push_str(&format!("To: {}\r\n", to));
-    eml.push_str(&format!("Subject: {}\r\n", subject));
+    if let Some(from) = &options.from {
+        eml.push_str(&format!("From: {}\r\n", encode_mailbox(from)));
+    }
+    eml.push_str(&format!("To: {}\r\n", single_line(to)));
+    if !options.cc.is_empty() {
+        eml.push_str(&format!("Cc: {}\r\n", encode_mailboxes(&options.cc)));
+    }
+    // Bcc bleibt in der Datei, damit ein Mailprogramm die Blindkopie übernimmt;
+    // beim Versand per SMTP wird die Zeile entfernt
+    if !options.bcc.is_empty() {
+        eml.push_str(&format!("Bcc: {}\r\n", encode_mailboxes(&options.bcc)));
+    }
+    if let Some(reply_to) = &options.reply_to {
+        eml.push_str(&format!("Reply-To: {}\r\n", encode_mailbox(reply_to)));
+    }
+    eml.push_str(&format!("Subject: {}\r\n", encode_header(subject)));
     eml.push_str("MIME-Version: 1.0\r\n");
     eml.push_str(&format!(
         "Date: {}\r\n",
@@ -61,29 +112,34 @@ pub fn create_eml(
     // Teil 1: Text-Teil (plain)
     eml.push_str(&format!("--{}\r\n", boundary));
     eml.push_str("Content-Type: text/plain; charset=\"utf-8\"\r\n");
-    eml.push_str("Content-Transfer-Encoding: 7bit\r\n");
-    eml.push_str("\r\n");
-    eml.push_str(&body);
-    eml.push_str("\r\n");
+    // Zeilenenden einheitlich CRLF, auch bei Vorlagen aus Dateien
+    let body = body.replace("\r\n", "\n").replace('\n', "\r\n");
+    if body.is_ascii() {
+        eml.push_str("Content-Transfer-Encoding: 7bit\r\n");
+        eml.push_str("\r\n");
+        eml.push_str(&body);
+        eml.push_str("\r\n");
+    } else {
+        // Umlaute usw. nur base64-kodiert, sonst lehnen SMTP-Server ohne 8BITMIME die Nachricht ab
+        eml.push_str("Content-Transfer-Encoding: base64\r\n");
+        eml.push_str("\r\n");
+        push_base64(&mut eml, body.as_bytes());
+    }
 
     // Teil 2: Attachment (PDF)
     eml.push_str(&format!("--{}\r\n", boundary));
     eml.push_str(&format!(
         "Content-Type: application/pdf; name=\"{}\"\r\n",
-        attachment_filename
+        encode_header(attachment_filename)
     ));
     eml.push_str(&format!(
-        "Content-Disposition: attachment; filename=\"{}\"\r\n",
-        attachment_filename
+        "Content-Disposition: attachment; {}\r\n",
+        filename_parameter(attachment_filename)
     ));
     eml.push_str("Content-Transfer-Encoding: base64\r\n");
     eml.push_str("\r\n");
 
-    // Base64 kodieren, RFC-konforme Zeilenlänge (76 Zeichen)
-    let b64 = general_purpose::STANDARD.encode(&attachment_bytes);
-    for chunk in b64.as_bytes().chunks(76) {
-        eml.push_str(&format!("{}\r\n", std::str::from_utf8(chunk).unwrap()));
-    }
+    push_base64(&mut eml, &attachment_bytes);
 
     // Ende-Marker
     eml.push_str(&format!("--{}--\r\n", boundary));
@@ -96,3 +152,120 @@ pub fn create_eml(
     Ok(())
 }
 
+
+/// Base64 kodieren, RFC-konforme Zeilenlänge (76 Zeichen)
+fn push_base64(eml: &mut String, bytes: &[u8]) {
+    let b64 = general_purpose::STANDARD.encode(bytes);
+    for chunk in b64.as_bytes().chunks(76) {
+        eml.push_str(&format!("{}\r\n", std::str::from_utf8(chunk).unwrap()));
+    }
+}
+
+/// Text für Kopfzeilen: mit Nicht-ASCII-Zeichen als RFC-2047-Wörter (`=?UTF-8?B?…?=`),
+/// die jeweils höchstens 75 Zeichen lang sind
+fn encode_header(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    let mut words = Vec::new();
+    let mut chunk = String::new();
+    for c in text.chars() {
+        if chunk.len() + c.len_utf8() > 45 {
+            words.push(std::mem::take(&mut chunk));
+        }
+        chunk.push(c);
+    }
+    words.push(chunk);
+    words
+        .iter()
+        .map(|word| format!("=?UTF-8?B?{}?=", general_purpose::STANDARD.encode(word)))
+        .collect::<Vec<_>>()
+        .join("\r\n ")
+}
+
+/// `filename`-Parameter für `Content-Disposition`: mit Nicht-ASCII-Zeichen nach RFC 2231
+/// (`filename*=UTF-8''…`, prozentkodiert und bei Bedarf auf mehrere Zeilen verteilt).
+/// RFC-2047-Wörter sind in Parametern nicht erlaubt; `name` im `Content-Type` behält sie
+/// für ältere Programme.
+fn filename_parameter(name: &str) -> String {
+    let name = single_line(name);
+    if name.is_ascii() {
+        return format!("filename=\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+    }
+
+    // Je Zeichen kodiert, damit kein Zeichen auf zwei Abschnitte verteilt wird; unkodiert
+    // bleiben nur die attribute-char aus RFC 2231
+    let encoded: Vec<String> = name
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '!' | '#' | '$' | '&' | '+' | '-' | '.' | '^' | '_' | '`' | '|' | '~' => {
+                c.to_string()
+            }
+            _ => c.to_string().bytes().map(|b| format!("%{:02X}", b)).collect(),
+        })
+        .collect();
+    let mut sections = vec![String::new()];
+    for part in encoded {
+        if sections.last().unwrap().len() + part.len() > 60 {
+            sections.push(String::new());
+        }
+        sections.last_mut().unwrap().push_str(&part);
+    }
+
+    if sections.len() == 1 {
+        return format!("filename*=UTF-8''{}", sections[0]);
+    }
+    sections
+        .iter()
+        .enumerate()
+        .map(|(idx, section)| match idx {
+            0 => format!("filename*0*=UTF-8''{}", section),
+            _ => format!("filename*{}*={}", idx, section),
+        })
+        .collect::<Vec<_>>()
+        .join(";\r\n ")
+}
+
+/// Adresse für Kopfzeilen: ein Anzeigename mit Nicht-ASCII-Zeichen wird kodiert,
+/// z.B. `Jürgen <j@example.com>` → `=?UTF-8?B?SsO8cmdlbg==?= <j@example.com>`
+fn encode_mailbox(mailbox: &str) -> String {
+    let mailbox = single_line(mailbox);
+    match mailbox.rfind('<') {
+        Some(pos) if !mailbox.is_ascii() => {
+            let name = mailbox[..pos].trim().trim_matches('"');
+            format!("{} {}", encode_header(name), &mailbox[pos..])
+        }
+        _ => mailbox,
+    }
+}
+
+fn encode_mailboxes(mailboxes: &[String]) -> String {
+    mailboxes.iter().map(|m| encode_mailbox(m)).collect::<Vec<_>>().join(", ")
+}
+
+/// Zeilenumbrüche aus Werten für Kopfzeilen entfernen, sonst könnten weitere Kopfzeilen
+/// eingeschleust werden
+fn single_line(text: &str) -> String {
+    text.lines().map(str::trim).filter(|l| !l.is_empty()).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filename_parameter() {
+        assert_eq!(filename_parameter("Max_Rust.pdf"), "filename=\"Max_Rust.pdf\"");
+        assert_eq!(filename_parameter("Jürgen Größe.pdf"), "filename*=UTF-8''J%C3%BCrgen%20Gr%C3%B6%C3%9Fe.pdf");
+
+        let long = filename_parameter(&format!("{}.pdf", "ü".repeat(12)));
+        assert_eq!(
+            long,
+            format!(
+                "filename*0*=UTF-8''{};\r\n filename*1*={}.pdf",
+                "%C3%BC".repeat(10),
+                "%C3%BC".repeat(2)
+            )
+        );
+    }
+}
@@ -1,12 +1,31 @@
-use certgen::{Cli, Commands, CertificateData, OdfDocument, Result, CertgenError};
+use certgen::cli::{ConverterArgs, ConverterKind, DateArgs, EmailArgs, FilenameArgs, ImageArgs, LanguageArgs, QrArgs, RegistryArgs, SigningArgs, SmtpArgs, SourceArgs};
+use certgen::config::{Config, ConverterConfig, DateConfig, EmailConfig, InputConfig, OutputConfig, QrConfig, RegistryConfig, SmtpConfig};
+use certgen::filename::{CollisionPolicy, FilenameTemplate, OutputNames, Sanitizer};
+use certgen::odf::PlaceholderInfo;
+use certgen::convert::{CommandConverter, PdfExportOptions, PdfProfile, SofficeConverter, UnoserverConverter};
+use certgen::manifest::{content_hash, millis, Delivery, DeliveryStatus, EntryStatus, Manifest, ManifestEntry, MANIFEST_FILE};
+use certgen::odf::QrOptions;
+use certgen::registry::{sha256_file, RecordKey, Registry, RegistryEntry, DEFAULT_REGISTRY_FILE};
+use certgen::sign::{PdfSigner, SignatureAppearance, SigningKey};
+use certgen::smtp::{parse_mailbox, Mailer, SmtpSettings};
+use certgen::source::{open_source, write_atomic, ColumnMapping, DataSource, SourceOptions};
+use certgen::template::localization::{upgrade_email_placeholders, with_language_suffix};
+use certgen::template::{DateOptions, Locale, Localization, TextTemplate};
+use certgen::{Cli, Commands, CertificateData, PdfConverter, MissingPolicy, OdfDocument, RenderJob, Result, CertgenError, ValidationReport};
+use chrono::NaiveDate;
 use clap::Parser;
-use log::{error, info};
+use log::{debug, error, info, warn};
 use serde_json::Value;
-use std::path::Path;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 mod mail;
 
-const DEFAULT_EMAIL_BODY: &str = "Guten Tag <name>,\n\nanbei Ihr Zertifikat <cert>.\n\nMit freundlichen Grüßen\nDas Team";
+use mail::EmailOptions;
+
+/// Ergebnis eines Batch-Laufs im Ausgabeverzeichnis: die Einträge mit `generated_file` und `cert_id`
+const RESULTS_FILE: &str = "certgen-results.json";
 
 fn main() {
     if let Err(e) = run() {
@@ -25,6 +44,8 @@ fn run() -> Result<()> {
         env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
     }
 
+    let config = Config::load(cli.config.as_deref())?;
+
     match cli.command {
         Commands::Fill {
             template,
@@ -36,32 +57,147 @@ fn run() -> Result<()> {
             date_to,
             agenda,
             custom_fields,
+            strict,
+            on_missing,
+            converter,
+            signing,
+            registry,
+            qr,
+            images,
+            dates,
+            languages,
         } => {
             info!("Filling single certificate");
-            fill_single(
-                &template,
-                &output,
+            let mut data = build_single_data(
                 &name,
                 &title,
-                &date,
-                date_from.as_deref(),
-                date_to.as_deref(),
+                date,
+                date_from,
+                date_to,
                 &agenda,
                 custom_fields,
             )?;
-            println!("✓ Certificate created: {}", output);
+            let localization = build_localization(&dates, &languages, &config);
+            let policy = resolve_missing_policy(strict, on_missing);
+            let templates = open_templates(&template, &languages, &config, |path| {
+                open_document(path, &policy, &qr, &images, &converter, &config)
+            })?;
+            let signer = build_signer(&signing, pdf_profile(&converter, &config.converter))?;
+            let output_dir = Path::new(&output).parent().unwrap_or(Path::new(""));
+            let mut registry = open_registry(&registry, &config.registry, output_dir)?;
+            let cert_id = registry.assign_id(&data, &RecordKey::default());
+            data.cert_id = Some(cert_id.clone());
+            for language in localization.languages_for(&data) {
+                let output = with_language_suffix(Path::new(&output), language);
+                fill_single(
+                    templates.get(language),
+                    &output.to_string_lossy(),
+                    &localization.localize(&data, language),
+                    &localization.date_options(language),
+                    strict,
+                    signer.as_ref(),
+                )?;
+                registry.record(&cert_id, &data, &RecordKey::default(), language, &output)?;
+                println!("✓ Certificate created: {} (ID {})", output.display(), cert_id);
+            }
         }
 
         Commands::Batch {
             template,
             json,
             output_dir,
+            strict,
+            on_missing,
+            jobs,
+            bulk,
+            chunk_size,
+            update_input,
+            keep_going,
+            resume,
+            send,
+            converter,
+            signing,
+            registry,
+            qr,
+            images,
+            dates,
+            languages,
+            source,
+            filenames,
+            email,
+            smtp,
         } => {
             info!("Starting batch processing");
-            let count = fill_batch(&template, &json, &output_dir)?;
+            let (filenames, on_collision) = build_filename_options(&filenames, &config.output)?;
+            let email = build_email_options(&email, &config.email)?;
+            if send && email.from.is_none() {
+                return Err(CertgenError::Smtp(
+                    "--send needs a sender address (--from or email.from in the config file)".to_string(),
+                ));
+            }
+            let options = BatchOptions {
+                strict,
+                missing_policy: resolve_missing_policy(strict, on_missing),
+                jobs: usize::from(jobs),
+                bulk: bulk.then_some(chunk_size),
+                update_input,
+                keep_going,
+                resume,
+                filenames,
+                on_collision,
+                email,
+                send: send.then(|| build_smtp_settings(&smtp, &config.smtp)).transpose()?,
+                signer: build_signer(&signing, pdf_profile(&converter, &config.converter))?,
+                localization: build_localization(&dates, &languages, &config),
+            };
+            let templates = open_templates(&template, &languages, &config, |path| {
+                open_document(path, &options.missing_policy, &qr, &images, &converter, &config)
+                    .map(|doc| {
+                        // Relative Bildpfade in den Daten gelten ab der Datendatei
+                        let data_dir = Path::new(&json).parent().unwrap_or(Path::new(""));
+                        doc.with_keep_going(keep_going).with_image_dir(data_dir)
+                    })
+            })?;
+            let mut registry = open_registry(&registry, &config.registry, Path::new(&output_dir))?;
+            let source = open_source(Path::new(&json), &build_source_options(&source, &config.input)?)?;
+            let count = fill_batch(&templates, source.as_ref(), &output_dir, &options, &mut registry)?;
             println!("✓ Created {} certificates in {}", count, output_dir);
         }
 
+        Commands::Send { output_dir, resend, smtp } => {
+            let settings = build_smtp_settings(&smtp, &config.smtp)?;
+            let dir = Path::new(&output_dir);
+            let mut manifest = Manifest::load(dir)?.ok_or_else(|| {
+                CertgenError::Smtp(format!("no {} in {}, run `certgen batch` first", MANIFEST_FILE, output_dir))
+            })?;
+            let delivered = deliver_emails(&mut manifest, &settings, resend);
+            manifest.write(dir)?;
+            let (sent, failed) = delivered?;
+            if failed > 0 {
+                return Err(CertgenError::DeliveryFailed(failed));
+            }
+            println!("✓ Sent {} emails", sent);
+        }
+
+        Commands::Validate { template, json, source } => {
+            info!("Validating {} against {}", json, template);
+            let source = open_source(Path::new(&json), &build_source_options(&source, &config.input)?)?;
+            let report = validate_batch(&template, source.as_ref())?;
+            print_validation_report(&report, true);
+            if !report.is_ok() {
+                return Err(CertgenError::ValidationFailed(report.failed_count()));
+            }
+            println!("✓ All {} records match the template", report.records.len());
+        }
+
+        Commands::VerifySignature { files, ca, json } => {
+            verify_signatures(&files, ca.as_deref(), json)?;
+        }
+
+        Commands::Inspect { template, json } => {
+            inspect_template(&template, json)?;
+        }
+
         Commands::Example { output, extended } => {
             info!("Generating example JSON");
             generate_example(&output, extended)?;
@@ -76,148 +212,1086 @@ fn run() -> Result<()> {
     Ok(())
 }
 
-fn fill_single(
-    template: &str,
-    output: &str,
+/// Baut die Zertifikatsdaten aus den CLI-Argumenten von `fill`
+fn build_single_data(
     name: &str,
     title: &str,
-    date: &str,
-    date_from: Option<&str>,
-    date_to: Option<&str>,
+    date: NaiveDate,
+    date_from: Option<NaiveDate>,
+    date_to: Option<NaiveDate>,
     agenda: &str,
     custom_fields: Vec<(String, String)>,
-) -> Result<()> {
-    let doc = OdfDocument::open(template)?;
-    
+) -> Result<CertificateData> {
     let mut data = CertificateData::new(
         name.to_string(),
-        date.to_string(),
+        date,
         agenda.to_string(),
     );
 
     data.add_field("TITLE".to_string(), title.to_string());
 
-    if let (Some(from), Some(to)) = (date_from, date_to) {
-        data.date_from = Some(from.to_string());
-        data.date_to = Some(to.to_string());
-    }
+    data.date_from = date_from;
+    data.date_to = date_to;
+    data.check_dates().map_err(CertgenError::Date)?;
 
     for (key, value) in custom_fields {
         info!("Adding custom field: {} = {}", key, value);
         data.add_field(key, value);
     }
 
+    Ok(data)
+}
+
+/// `--strict` ohne explizites `--on-missing` behandelt übrig gebliebene Platzhalter als Fehler
+fn resolve_missing_policy(strict: bool, on_missing: Option<MissingPolicy>) -> MissingPolicy {
+    on_missing.unwrap_or(if strict {
+        MissingPolicy::Error
+    } else {
+        MissingPolicy::Keep
+    })
+}
+
+/// Erzeugt den über die CLI gewählten PDF-Konverter; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_converter(args: &ConverterArgs, config: &ConverterConfig) -> Result<Box<dyn PdfConverter>> {
+    let timeout = args
+        .convert_timeout
+        .or(config.timeout_secs)
+        .map(Duration::from_secs);
+
+    let export = PdfExportOptions {
+        profile: pdf_profile(args, config),
+        embed_fonts: args.embed_fonts || config.embed_fonts,
+        tagged: args.tagged_pdf || config.tagged_pdf,
+        image_quality: args.image_quality.or(config.image_quality),
+        lossless_images: args.lossless_images || config.lossless_images,
+    };
+    if args.converter != ConverterKind::Soffice && export != PdfExportOptions::default() {
+        warn!("PDF export options are only supported by the soffice converter and will be ignored");
+    }
+
+    Ok(match args.converter {
+        ConverterKind::Soffice => {
+            let mut converter = SofficeConverter::new();
+            if let Some(program) = args.soffice.as_ref().or(config.soffice.as_ref()) {
+                converter = converter.with_program(program);
+            }
+            if let Some(dir) = args.profile_dir.as_ref().or(config.profile_dir.as_ref()) {
+                converter = converter.with_profile_dir(dir.clone());
+            }
+            if let Some(options) = args.filter_options.as_ref().or(config.filter_options.as_ref()) {
+                converter = converter.with_filter_options(options);
+            }
+            if let Some(timeout) = timeout {
+                converter = converter.with_timeout(timeout);
+            }
+            Box::new(converter.with_export_options(export))
+        }
+        ConverterKind::Unoserver => {
+            let mut converter = UnoserverConverter::new(&args.unoserver_host, args.unoserver_port);
+            if let Some(timeout) = timeout {
+                converter = converter.with_timeout(timeout);
+            }
+            Box::new(converter)
+        }
+        ConverterKind::Command => {
+            // clap stellt sicher, dass --converter-command gesetzt ist
+            let template = args.converter_command.as_deref().unwrap_or_default();
+            let mut converter = CommandConverter::new(template)?;
+            if let Some(timeout) = timeout {
+                converter = converter.with_timeout(timeout);
+            }
+            Box::new(converter)
+        }
+    })
+}
+
+/// Sprache und Format der Datumsangaben; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_date_options(args: &DateArgs, config: &DateConfig) -> DateOptions {
+    DateOptions {
+        locale: args.locale.or(config.locale).unwrap_or_default(),
+        style: args.date_style.or(config.style).unwrap_or_default(),
+        collapse: args.collapse_dates || config.collapse,
+        single: config.single.clone(),
+        range: config.range.clone(),
+    }
+}
+
+/// Datumsformat, Sprachen und sprachabhängige Texte; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_localization(dates: &DateArgs, languages: &LanguageArgs, config: &Config) -> Localization {
+    let mut settings = config.languages.clone();
+    for (language, setting) in &mut settings {
+        let mut upgraded = false;
+        for text in [&mut setting.email_subject, &mut setting.email_body].into_iter().flatten() {
+            if let Some(new_text) = upgrade_email_placeholders(text) {
+                *text = new_text;
+                upgraded = true;
+            }
+        }
+        if upgraded {
+            warn!(
+                "languages.{}: <name>, <cert> and <title> in email texts are deprecated, use {{{{NAME}}}}, {{{{CERT_FILE}}}} and {{{{TITLE}}}}",
+                language
+            );
+        }
+    }
+    Localization {
+        dates: build_date_options(dates, &config.dates),
+        languages: languages.languages.clone(),
+        settings,
+    }
+}
+
+/// Öffnet eine Vorlage mit den Einstellungen für Platzhalter und Konvertierung
+fn open_document(
+    path: &str,
+    policy: &MissingPolicy,
+    qr: &QrArgs,
+    images: &ImageArgs,
+    converter: &ConverterArgs,
+    config: &Config,
+) -> Result<OdfDocument> {
+    Ok(OdfDocument::open(path)?
+        .with_missing_policy(policy.clone())
+        .with_qr_options(build_qr_options(qr, &config.qr))
+        .with_image_fit(images.image_fit.or(config.images.fit).unwrap_or_default())
+        .with_converter(build_converter(converter, &config.converter)?))
+}
+
+/// Vorlagen je Sprache; Sprachen ohne eigene Vorlage verwenden die Standardvorlage
+struct Templates {
+    default: OdfDocument,
+    by_language: BTreeMap<Locale, OdfDocument>,
+}
+
+impl Templates {
+    /// Sprache, deren eigene Vorlage verwendet wird (`None` = Standardvorlage)
+    fn key(&self, language: Option<Locale>) -> Option<Locale> {
+        language.filter(|l| self.by_language.contains_key(l))
+    }
+
+    fn get(&self, language: Option<Locale>) -> &OdfDocument {
+        match self.key(language) {
+            Some(language) => &self.by_language[&language],
+            None => &self.default,
+        }
+    }
+
+    /// SHA-256 aller Vorlagen, nach `key`
+    fn hashes(&self) -> Result<BTreeMap<Option<Locale>, String>> {
+        let mut hashes = BTreeMap::from([(None, sha256_file(Path::new(self.default.path()))?)]);
+        for (language, doc) in &self.by_language {
+            hashes.insert(Some(*language), sha256_file(Path::new(doc.path()))?);
+        }
+        Ok(hashes)
+    }
+
+    /// Bildplatzhalter aller Vorlagen, nach `key`
+    fn image_placeholders(&self) -> Result<BTreeMap<Option<Locale>, BTreeSet<String>>> {
+        let mut keys = BTreeMap::from([(None, self.default.image_placeholders()?)]);
+        for (language, doc) in &self.by_language {
+            keys.insert(Some(*language), doc.image_placeholders()?);
+        }
+        Ok(keys)
+    }
+
+    /// Platzhalter aller Vorlagen, nach `key`
+    fn placeholders(&self) -> Result<BTreeMap<Option<Locale>, Vec<PlaceholderInfo>>> {
+        let mut placeholders = BTreeMap::from([(None, self.default.placeholders()?)]);
+        for (language, doc) in &self.by_language {
+            placeholders.insert(Some(*language), doc.placeholders()?);
+        }
+        Ok(placeholders)
+    }
+}
+
+/// Öffnet die Standardvorlage und die Vorlagen der Sprachen; `--language-template`
+/// hat Vorrang vor der Konfigurationsdatei
+fn open_templates(
+    template: &str,
+    args: &LanguageArgs,
+    config: &Config,
+    open: impl Fn(&str) -> Result<OdfDocument>,
+) -> Result<Templates> {
+    let mut paths: BTreeMap<Locale, PathBuf> = config
+        .languages
+        .iter()
+        .filter_map(|(language, settings)| settings.template.clone().map(|path| (*language, path)))
+        .collect();
+    paths.extend(args.language_templates.iter().cloned());
+
+    let mut by_language = BTreeMap::new();
+    for (language, path) in paths {
+        info!("Using template {} for language {}", path.display(), language);
+        by_language.insert(language, open(&path.to_string_lossy())?);
+    }
+    Ok(Templates {
+        default: open(template)?,
+        by_language,
+    })
+}
+
+/// Dateinamen-Vorlage, Länge, Bereinigung und Umgang mit doppelten Namen; CLI-Werte haben
+/// Vorrang vor der Konfigurationsdatei
+fn build_filename_options(args: &FilenameArgs, config: &OutputConfig) -> Result<(FilenameTemplate, CollisionPolicy)> {
+    let template = match args.filename_template.as_ref().or(config.filename_template.as_ref()) {
+        Some(template) => FilenameTemplate::parse(template)?,
+        None => FilenameTemplate::default(),
+    };
+    let template = match args.max_filename_length.or(config.max_length) {
+        Some(max_length) => template.with_max_length(max_length),
+        None => template,
+    };
+    let sanitizer = Sanitizer::new(args.sanitize.or(config.sanitize).unwrap_or_default())
+        .with_german_umlauts(args.german_umlauts || config.german_umlauts);
+    let template = template.with_sanitizer(sanitizer);
+    Ok((template, args.on_collision.or(config.on_collision).unwrap_or_default()))
+}
+
+/// Absender, Kopie-Empfänger und Textvorlagen der E-Mails; CLI-Werte haben Vorrang vor der
+/// Konfigurationsdatei
+fn build_email_options(args: &EmailArgs, config: &EmailConfig) -> Result<EmailOptions> {
+    // Adressen aus der Konfigurationsdatei prüfen (die der CLI prüft clap)
+    let mailbox = |value: &String| parse_mailbox(value);
+    let pick = |args: &Vec<String>, config: &Vec<String>| -> Result<Vec<String>> {
+        if args.is_empty() {
+            config.iter().map(mailbox).collect()
+        } else {
+            Ok(args.clone())
+        }
+    };
+    let template = |path: Option<&PathBuf>| path.map(|path| TextTemplate::from_file(path)).transpose();
+
+    Ok(EmailOptions {
+        from: args.from.clone().map(Ok).or_else(|| config.from.as_ref().map(mailbox)).transpose()?,
+        reply_to: args.reply_to.clone().map(Ok).or_else(|| config.reply_to.as_ref().map(mailbox)).transpose()?,
+        cc: pick(&args.cc, &config.cc)?,
+        bcc: pick(&args.bcc, &config.bcc)?,
+        subject: template(args.subject_template.as_ref().or(config.subject_template.as_ref()))?,
+        body: template(args.body_template.as_ref().or(config.body_template.as_ref()))?,
+    })
+}
+
+/// SMTP-Server, Anmeldung und Versandverhalten; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_smtp_settings(args: &SmtpArgs, config: &SmtpConfig) -> Result<SmtpSettings> {
+    let host = args.smtp_host.as_ref().or(config.host.as_ref()).ok_or_else(|| {
+        CertgenError::Smtp("no SMTP server configured (--smtp-host or smtp.host in the config file)".to_string())
+    })?;
+    let defaults = SmtpSettings::new(host);
+    Ok(SmtpSettings {
+        port: args.smtp_port.or(config.port),
+        security: args.smtp_security.or(config.security).unwrap_or_default(),
+        username: args.smtp_user.clone().or_else(|| config.username.clone()),
+        password: args.smtp_password.clone(),
+        auth: if args.smtp_auth.is_empty() {
+            config.auth.clone()
+        } else {
+            args.smtp_auth.clone()
+        },
+        rate_limit: args.rate_limit.or(config.rate_limit),
+        retries: args.smtp_retries.or(config.retries).unwrap_or(defaults.retries),
+        retry_delay: config.retry_delay_secs.map(Duration::from_secs).unwrap_or(defaults.retry_delay),
+        timeout: config.timeout_secs.map(Duration::from_secs).unwrap_or(defaults.timeout),
+        ..defaults
+    })
+}
+
+/// Trennzeichen, Kodierung und Spaltenzuordnung für Tabellen; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_source_options(args: &SourceArgs, config: &InputConfig) -> Result<SourceOptions> {
+    let delimiter = match args.delimiter.or(config.delimiter) {
+        Some(delimiter) if delimiter.is_ascii() => Some(delimiter as u8),
+        Some(delimiter) => {
+            return Err(CertgenError::DataSource(format!("delimiter `{}` must be an ASCII character", delimiter)))
+        }
+        None => None,
+    };
+
+    let mut columns = ColumnMapping::new();
+    for (field, column) in config.columns.iter().chain(args.columns.iter().map(|(f, c)| (f, c))) {
+        columns = columns.map(field, column);
+    }
+
+    Ok(SourceOptions {
+        delimiter,
+        encoding: args.encoding.clone().or_else(|| config.encoding.clone()),
+        sheet: args.sheet.clone().or_else(|| config.sheet.clone()),
+        columns,
+    })
+}
+
+/// Inhalt und Größe der QR-Codes; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn build_qr_options(args: &QrArgs, config: &QrConfig) -> QrOptions {
+    let defaults = QrOptions::default();
+    QrOptions {
+        default_payload: args
+            .qr_payload
+            .clone()
+            .or_else(|| config.payload.clone())
+            .unwrap_or(defaults.default_payload),
+        size: args.qr_size.clone().or_else(|| config.size.clone()).unwrap_or(defaults.size),
+    }
+}
+
+/// Öffnet das Zertifikatsregister; CLI-Werte haben Vorrang vor der Konfigurationsdatei,
+/// ohne Angabe liegt es im Ausgabeverzeichnis `output_dir`
+fn open_registry(args: &RegistryArgs, config: &RegistryConfig, output_dir: &Path) -> Result<Registry> {
+    let path = args
+        .registry
+        .clone()
+        .or_else(|| config.path.clone())
+        .unwrap_or_else(|| output_dir.join(DEFAULT_REGISTRY_FILE));
+    let format = args
+        .id_format
+        .clone()
+        .or_else(|| config.id_format.clone())
+        .unwrap_or_default();
+    info!("Using certificate registry {}", path.display());
+    Registry::open(path, format)
+}
+
+/// PDF-Profil der Ausgabe; CLI-Werte haben Vorrang vor der Konfigurationsdatei
+fn pdf_profile(args: &ConverterArgs, config: &ConverterConfig) -> PdfProfile {
+    args.pdf_profile.or(config.pdf_profile).unwrap_or_default()
+}
+
+/// Lädt den Signaturschlüssel, falls `--sign-p12` oder `--sign-key`/`--sign-cert` angegeben sind.
+/// Der sichtbare Signaturkasten verwendet eine nicht eingebettete Schrift und ist daher
+/// mit einem PDF/A-Profil nicht möglich
+fn build_signer(args: &SigningArgs, profile: PdfProfile) -> Result<Option<PdfSigner>> {
+    let password = args.sign_password.as_deref();
+    let key = match (&args.sign_p12, &args.sign_key, &args.sign_cert) {
+        (Some(p12), _, _) => SigningKey::from_pkcs12(p12, password.unwrap_or_default())?,
+        (None, Some(key), Some(cert)) => SigningKey::from_pem(key, cert, password)?,
+        _ => return Ok(None),
+    };
+    if args.sign_visible && profile.is_pdfa() {
+        return Err(CertgenError::Signature(format!(
+            "--sign-visible cannot be combined with --pdf-profile {}: the signature box uses a font that is not embedded, so the file would no longer be PDF/A",
+            profile
+        )));
+    }
+    info!("Signing PDFs as {}", key.signer_name());
+
+    let mut signer = PdfSigner::new(key);
+    if let Some(reason) = &args.sign_reason {
+        signer = signer.with_reason(reason);
+    }
+    if let Some(location) = &args.sign_location {
+        signer = signer.with_location(location);
+    }
+    if args.sign_visible {
+        signer = signer.with_appearance(SignatureAppearance {
+            page: args.sign_page,
+            rect: args.sign_rect,
+        });
+    }
+    Ok(Some(signer))
+}
+
+fn fill_single(
+    doc: &OdfDocument,
+    output: &str,
+    data: &CertificateData,
+    dates: &DateOptions,
+    strict: bool,
+    signer: Option<&PdfSigner>,
+) -> Result<()> {
+    let replacements = data.to_replacements_with(dates);
+
+    if strict {
+        let report = certgen::validate::validate_records(std::slice::from_ref(data), &doc.placeholders()?);
+        ensure_valid(&report)?;
+    }
+
     // Wenn .pdf als Ausgabe gewünscht ist, benutze die neue PDF-Kette
     if output.to_lowercase().ends_with(".pdf") {
-        doc.fill_and_save_pdf(output, &data.to_replacements())?;
+        doc.fill_and_save_pdf(output, &replacements)?;
+        if let Some(signer) = signer {
+            signer.sign_file(Path::new(output))?;
+        }
     } else {
-        doc.fill_and_save(output, &data.to_replacements())?;
+        if signer.is_some() {
+            return Err(CertgenError::Signature("only PDF output can be signed".to_string()));
+        }
+        doc.fill_and_save(output, &replacements)?;
     }
     Ok(())
 }
 
 
-fn fill_batch(template: &str, json_path: &str, output_dir: &str) -> Result<usize> {
+/// Deserialisiert alle Einträge der Batch-Datei in CertificateData
+fn parse_batch_records(items: &[Value]) -> Result<Vec<CertificateData>> {
+    items.iter().map(CertificateData::from_batch_item).collect()
+}
+
+fn validate_batch(template: &str, source: &dyn DataSource) -> Result<ValidationReport> {
     let doc = OdfDocument::open(template)?;
+    let records = parse_batch_records(&source.read_items()?)?;
 
-    // Lies die ganze JSON-Datei als Value, damit wir später die erzeugten Dateinamen zurückschreiben können
-    let content = std::fs::read_to_string(json_path)?;
-    let mut v: Value = serde_json::from_str(&content)?;
+    Ok(certgen::validate::validate_records(&records, &doc.placeholders()?))
+}
 
-    // Erwartet ein top-level Array
-    let arr = match v.as_array_mut() {
-        Some(a) => a,
-        None => {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Expected top-level JSON array in batch file",
-            )
-            .into())
+/// Gibt den Prüfbericht aus; ohne `verbose` nur die fehlerhaften Datensätze
+fn print_validation_report(report: &ValidationReport, verbose: bool) {
+    for record in &report.records {
+        if record.is_ok() && record.unused.is_empty() {
+            if verbose {
+                println!("✓ [{}] {}", record.index, record.name);
+            }
+            continue;
         }
-    };
+
+        let marker = if record.is_ok() { "!" } else { "✗" };
+        println!("{} [{}] {}", marker, record.index, record.name);
+        if !record.unfilled.is_empty() {
+            println!("    unfilled placeholders: {}", record.unfilled.join(", "));
+        }
+        if !record.unused.is_empty() {
+            println!("    unused data keys:      {}", record.unused.join(", "));
+        }
+    }
+}
+
+/// Bricht mit `ValidationFailed` ab, wenn Platzhalter unbefüllt bleiben würden
+fn ensure_valid(report: &ValidationReport) -> Result<()> {
+    if report.is_ok() {
+        return Ok(());
+    }
+    print_validation_report(report, false);
+    Err(CertgenError::ValidationFailed(report.failed_count()))
+}
+
+/// Optionen für den Batch-Modus
+struct BatchOptions {
+    strict: bool,
+    missing_policy: MissingPolicy,
+    jobs: usize,
+    /// Bulk-Konvertierung mit der angegebenen Chunk-Größe (0 = alle auf einmal)
+    bulk: Option<usize>,
+    /// Erzeugte Dateinamen und IDs in die Eingabedatei zurückschreiben
+    update_input: bool,
+    /// Fehlerhafte Datensätze überspringen und am Ende melden statt abzubrechen
+    keep_going: bool,
+    /// Dateien überspringen, die laut Manifest des letzten Laufs aktuell sind
+    resume: bool,
+    /// Vorlage für die Dateinamen (relativ zum Ausgabeverzeichnis, ohne `.pdf`)
+    filenames: FilenameTemplate,
+    /// Umgang mit doppelten Dateinamen
+    on_collision: CollisionPolicy,
+    /// Absender, Kopie-Empfänger und Textvorlagen der E-Mails
+    email: EmailOptions,
+    /// E-Mails nach dem Lauf über diesen SMTP-Server verschicken
+    send: Option<SmtpSettings>,
+    /// Signiert jedes erzeugte PDF vor dem Erstellen der E-Mail
+    signer: Option<PdfSigner>,
+    /// Sprachen, Datumsformat und sprachabhängige Texte
+    localization: Localization,
+}
+
+/// Ein zu erzeugendes Zertifikat im Batch: Datensatz, Sprache und Ausgabe
+struct BatchOutput {
+    record: usize,
+    language: Option<Locale>,
+    /// Inhalts-Hash für `--resume`, siehe `content_hash`
+    input_hash: String,
+    /// Eintrag des letzten Laufs, wenn die Datei noch aktuell ist (wird nicht neu erzeugt)
+    up_to_date: Option<ManifestEntry>,
+    job: RenderJob,
+}
+
+fn fill_batch(
+    templates: &Templates,
+    source: &dyn DataSource,
+    output_dir: &str,
+    options: &BatchOptions,
+    registry: &mut Registry,
+) -> Result<usize> {
+
+    let mut items = source.read_items()?;
+    let arr = items.as_mut_slice();
+
+    // Ohne Absender hätten die .eml-Dateien keine From-Zeile; vor dem Rendern abbrechen
+    if options.email.from.is_none() && arr.iter().any(|item| item.get("email").and_then(Value::as_str).is_some()) {
+        return Err(CertgenError::Email(
+            "records with an email address need a sender (--from or email.from in the config file)".to_string(),
+        ));
+    }
+    let localization = &options.localization;
+    let mut manifest = Manifest::start();
+
+    // Alle Datensätze vorab einlesen, damit fehlerhafte Einträge vor dem Rendern auffallen;
+    // mit --keep-going werden sie übersprungen und am Ende gemeldet
+    let mut rejected: BTreeMap<usize, String> = BTreeMap::new();
+    let mut records = Vec::with_capacity(arr.len());
+    for (idx, item) in arr.iter().enumerate() {
+        match CertificateData::from_batch_item(item) {
+            Ok(cert) => records.push(Some(cert)),
+            Err(e) if options.keep_going => {
+                rejected.insert(idx, e.to_string());
+                records.push(None);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    // IDs vergeben: vorhandene cert_id bzw. frühere Ausstellung desselben Datensatzes wiederverwenden;
+    // Einträge mit cert_id zuerst, damit ihre IDs nicht an einen gleichen Datensatz gehen
+    for with_id in [true, false] {
+        for (idx, cert) in records.iter_mut().enumerate() {
+            if let Some(cert) = cert.as_mut().filter(|cert| cert.cert_id.is_some() == with_id) {
+                cert.cert_id = Some(registry.assign_id(cert, &record_key(idx, &arr[idx])));
+            }
+        }
+    }
+
+    if options.strict {
+        // Jede Sprache gegen ihre eigene Vorlage prüfen, mit den festen Texten der Sprache
+        let placeholders = templates.placeholders()?;
+        let mut report = ValidationReport::default();
+        for (idx, cert) in records.iter().enumerate() {
+            let Some(cert) = cert else { continue };
+            for language in localization.languages_for(cert) {
+                let localized = localization.localize(cert, language);
+                let expected = &placeholders[&templates.key(language)];
+                report.records.push(certgen::validate::validate_record(idx, &localized, expected));
+            }
+        }
+        if !options.keep_going {
+            ensure_valid(&report)?;
+        } else if !report.is_ok() {
+            print_validation_report(&report, false);
+            for record in report.records.iter().filter(|r| !r.is_ok()) {
+                records[record.index] = None;
+                rejected
+                    .entry(record.index)
+                    .or_insert_with(|| format!("unfilled placeholders: {}", record.unfilled.join(", ")));
+            }
+        }
+    }
 
     std::fs::create_dir_all(output_dir)?;
 
+    let previous = if options.resume {
+        let previous = Manifest::load(Path::new(output_dir))?;
+        if previous.is_none() {
+            info!("No manifest from an earlier run in {}, creating all certificates", output_dir);
+        }
+        previous
+    } else {
+        None
+    };
+    let template_hashes = templates.hashes()?;
+    let image_keys = templates.image_placeholders()?;
+    let signing = match &options.signer {
+        Some(signer) => signer.settings()?,
+        None => "unsigned".to_string(),
+    };
+
+    // Dateinamen aus der Vorlage (Standard: <name>_<title>.pdf, sanitisiert), bei mehreren
+    // Sprachen mit Sprachendung: <name>_<title>_<lang>.pdf; doppelte Namen je nach --on-collision
+    let mut names = OutputNames::new(options.on_collision);
+    let mut unnamed = Vec::new();
+    let mut outputs = Vec::with_capacity(records.len());
+    for (idx, cert) in records.iter().enumerate() {
+        let Some(cert) = cert else { continue };
+        let email = arr[idx].get("email").and_then(Value::as_str);
+
+        for language in localization.languages_for(cert) {
+            let replacements = localization.replacements(cert, language);
+            let output = match output_path(output_dir, &options.filenames, &mut names, cert, &replacements, language) {
+                Ok(output) => output,
+                Err(e) if options.keep_going => {
+                    outputs.retain(|output: &BatchOutput| output.record != idx);
+                    unnamed.push((idx, e.with_record(idx).to_string()));
+                    break;
+                }
+                Err(e) => return Err(e.with_record(idx)),
+            };
+            let key = templates.key(language);
+            let doc = templates.get(language);
+            let mut inputs = vec![template_hashes[&key].clone(), doc.render_settings(), signing.clone()];
+            // Eingesetzte Bilder mit ihrem Inhalt; eine fehlende Datei scheitert erst beim Rendern
+            for value in image_keys[&key].iter().filter_map(|k| replacements.get(k)) {
+                inputs.push(sha256_file(&doc.image_path(value)).unwrap_or_default());
+            }
+            // Mit einer geänderten Adresse oder geänderten Texten wird die E-Mail neu erstellt
+            // und verschickt; Fehler in den Textvorlagen meldet erst `finish_output`
+            if let Some(email) = email {
+                let fields = email_fields(&replacements, &output, email);
+                let (subject, body) = options.email.render(localization, language, &fields).unwrap_or_default();
+                inputs.push(options.email.settings(email, &subject, &body));
+            }
+            let inputs: Vec<&str> = inputs.iter().map(String::as_str).collect();
+            let input_hash = content_hash(&inputs, &replacements);
+            let up_to_date = previous
+                .as_ref()
+                .and_then(|previous| previous.up_to_date(&output, &input_hash))
+                .cloned();
+            outputs.push(BatchOutput {
+                record: idx,
+                language,
+                input_hash,
+                up_to_date,
+                job: RenderJob { output, replacements },
+            });
+        }
+    }
+    for (idx, error) in unnamed {
+        records[idx] = None;
+        rejected.insert(idx, error);
+    }
+
+    // Erstelle die PDFs (bei --jobs > 1 parallel, Reihenfolge bleibt erhalten)
+    let results = render_outputs(templates, &outputs, options);
+    let mut rendered = outputs.iter().zip(results).peekable();
+
     let mut created = 0usize;
+    let mut failure = None;
 
     for (idx, item) in arr.iter_mut().enumerate() {
-        // bestimme das CertificateData-Objekt:
-        // - falls wrapper { "email": "...", "certificate": { ... } } -> benutze das innere .certificate
-        // - sonst: item selbst sollte ein CertificateData-Objekt sein
-        let cert_value = if item.get("certificate").is_some() {
-            item.get("certificate").unwrap().clone()
-        } else {
-            item.clone()
+        let Some(cert) = &records[idx] else {
+            manifest.push(ManifestEntry {
+                status: EntryStatus::Failed,
+                error: rejected.get(&idx).cloned(),
+                ..ManifestEntry::new(idx, record_name(item))
+            });
+            continue;
         };
+        let cert_id = cert.cert_id.as_deref().unwrap_or_default();
+        let email = item.get("email").and_then(Value::as_str);
 
-        // Deserialisiere in CertificateData (explizit CertgenError verwenden, um Ambiguität zu vermeiden)
-        let cert: CertificateData = serde_json::from_value(cert_value)
-            .map_err(CertgenError::from)?;
+        let mut files = Vec::new();
+        let mut emls = Vec::new();
+        while let Some((output, result)) = rendered.next_if(|(output, _)| output.record == idx) {
+            let output_path = &output.job.output;
+            let mut entry = ManifestEntry {
+                language: output.language,
+                email: email.map(String::from),
+                output: Some(output_path.clone()),
+                cert_id: Some(cert_id.to_string()),
+                input_hash: Some(output.input_hash.clone()),
+                ..ManifestEntry::new(idx, &cert.name)
+            };
 
-        // Erzeuge Dateinamen wie zuvor: <name>_<title>.pdf (sanitisiert)
-        let cleaned_name = sanitize_filename(&cert.name);
-        let title = cert.custom_fields
-            .get("TITLE")
-            .map(|t| sanitize_filename(t))
-            .unwrap_or_else(|| "Kurs".to_string());
+            // Unveränderte Dateien aus dem letzten Lauf (--resume) werden übernommen
+            if let Some(previous) = &output.up_to_date {
+                entry.size = previous.size;
+                entry.sha256 = previous.sha256.clone();
+                files.push((output.language, output_path.to_string_lossy().to_string()));
+                let eml_path = eml_path(output_dir, output_path);
+                if email.is_some() && eml_path.exists() {
+                    emls.push((output.language, eml_path.to_string_lossy().to_string()));
+                    // Bereits verschickte E-Mails werden nicht erneut gesendet
+                    entry.delivery = previous.delivery.clone().filter(|_| previous.eml.as_ref() == Some(&eml_path));
+                    entry.eml = Some(eml_path);
+                }
+                info!("Skipped [{}] {}: {} is up to date", idx, cert_id, output_path.display());
+                manifest.push(entry);
+                continue;
+            }
 
-        let filename = format!("{}_{}.pdf", cleaned_name, title);
-        let output_path = Path::new(output_dir).join(&filename);
-        let output_str = output_path.to_str().unwrap();
+            // Ohne --keep-going startet nach dem ersten Fehler kein neues Rendern mehr; bereits
+            // gerenderte PDFs werden trotzdem fertiggestellt, damit ihr Zustand stimmt
+            let Some(result) = result else {
+                entry.error = Some("not rendered after an earlier error".to_string());
+                manifest.push(entry);
+                continue;
+            };
 
-        // Erstelle das PDF (fill_and_save_pdf löscht die temporäre .odt selbst)
-        doc.fill_and_save_pdf(output_str, &cert.to_replacements())?;
+            let started = Instant::now();
+            let finished = result.and_then(|render_time| {
+                let (registered, eml) =
+                    finish_output(output, cert, email, output_dir, options, registry)?;
+                Ok((render_time, registered, eml))
+            });
+            match finished {
+                Ok((render_time, registered, eml)) => {
+                    entry.status = EntryStatus::Ok;
+                    entry.size = Some(std::fs::metadata(output_path)?.len());
+                    entry.sha256 = Some(registered.sha256);
+                    entry.duration_ms = millis(render_time + started.elapsed());
 
-        // Schreibe den generierten Dateinamen zurück in das JSON-Objekt
-        // Hier schreibe ich den Pfad mit Ordnernamen: "<output_dir>/<filename>"
-        let stored_path_string = Path::new(output_dir)
-            .join(&filename)
-            .to_string_lossy()
-            .to_string();
+                    // Hier schreibe ich den Pfad mit Ordnernamen: "<output_dir>/<filename>"
+                    files.push((output.language, output_path.to_string_lossy().to_string()));
+                    if let Some(eml_path) = eml {
+                        emls.push((output.language, eml_path.to_string_lossy().to_string()));
+                        entry.eml = Some(eml_path);
+                    }
 
+                    created += 1;
+                    info!("Created [{}] {} -> {}", idx, cert_id, output_path.display());
+                }
+                Err(e) => {
+                    let e = e.with_record(idx);
+                    entry.status = EntryStatus::Failed;
+                    entry.error = Some(e.to_string());
+                    failure.get_or_insert(e);
+                }
+            }
+            manifest.push(entry);
+        }
+
+        // Schreibe die generierten Dateinamen zurück in das JSON-Objekt; bei mehreren
+        // Sprachen zusätzlich alle Dateien nach Sprache
         if let Value::Object(map) = item {
-            map.insert("generated_file".to_string(), Value::String(stored_path_string));
-        }
-
-        // Wenn eine E-Mail im Eintrag vorhanden ist, erzeuge automatisch eine .eml-Datei mit Anhang
-        if let Some(Value::String(email_addr)) = item.get("email") {
-            // Erzeuge ein emails-Unterverzeichnis unter output_dir
-            let eml_dir = Path::new(output_dir).join("emails");
-            std::fs::create_dir_all(&eml_dir)?;
-
-            // EML-Dateiname basiert auf dem PDF-Dateinamen
-            let eml_filename = format!("{}.eml", filename.trim_end_matches(".pdf"));
-            let eml_path = eml_dir.join(&eml_filename);
-
-            // Subject und Body (kann später CLI-konfigurierbar gemacht werden)
-            let subject = format!("Ihr Zertifikat: {}", title);
-            let body_template = DEFAULT_EMAIL_BODY;
-
-            // create_eml erwartet Pfad zur erzeugten PDF als Path
-            mail::create_eml(
-                email_addr,
-                &subject,
-                body_template,
-                &cert.name,
-                Path::new(output_str),
-                &eml_path,
-            )?;
+            write_generated(map, "generated_file", "generated_files", &files);
+            map.insert("cert_id".to_string(), Value::String(cert_id.to_string()));
+            write_generated(map, "generated_eml", "generated_emls", &emls);
+        }
+    }
+
+    // Versand erst, wenn alle Dateien erzeugt sind; ohne --keep-going nur bei fehlerfreiem Lauf
+    let delivered = match &options.send {
+        Some(settings) if failure.is_none() || options.keep_going => Some(deliver_emails(&mut manifest, settings, false)),
+        _ => None,
+    };
+
+    // Manifest, Zusammenfassung und Ergebnisse auch bei Fehlern schreiben, damit die
+    // erfolgreich erzeugten Dateien festgehalten sind
+    manifest.finish();
+    let (manifest_path, summary_path) = manifest.write(Path::new(output_dir))?;
+    info!("Manifest written to {} (summary: {})", manifest_path.display(), summary_path.display());
+
+    // Die Eingabedatei wird nur mit --update-input geändert (nur JSON); die Ergebnisse
+    // stehen immer zusätzlich im Ausgabeverzeichnis
+    let pretty = serde_json::to_string_pretty(&items)?;
+    let results_path = Path::new(output_dir).join(RESULTS_FILE);
+    write_atomic(&results_path, pretty.as_bytes())?;
+    info!("Results written to {}", results_path.display());
+
+    if options.update_input && !source.write_back(&items)? {
+        warn!("--update-input is only supported for JSON input; the input file was not changed");
+    }
+
+    if let Some(e) = failure.filter(|_| !options.keep_going) {
+        return Err(e);
+    }
+
+    let skipped = outputs.iter().filter(|output| output.up_to_date.is_some()).count();
+    if skipped > 0 {
+        println!("↷ Skipped {} up-to-date certificates", skipped);
+    }
+
+    // Mit --keep-going alle Fehler gesammelt ausgeben
+    let failed: Vec<&ManifestEntry> =
+        manifest.entries.iter().filter(|e| e.status == EntryStatus::Failed).collect();
+    if !failed.is_empty() {
+        for entry in &failed {
+            println!("✗ [{}] {}: {}", entry.index, entry.name, entry.error.as_deref().unwrap_or_default());
+        }
+        println!("Created {} certificates in {}, see {}", created, output_dir, summary_path.display());
+        return Err(CertgenError::BatchFailed(failed.len()));
+    }
+
+    if let Some(delivered) = delivered {
+        let (sent, failed) = delivered?;
+        println!("✓ Sent {} emails", sent);
+        if failed > 0 {
+            return Err(CertgenError::DeliveryFailed(failed));
+        }
+    }
+
+    Ok(created)
+}
 
-            // schreibe generated_eml ins JSON (voller Pfad relativ wie to_string_lossy liefert)
-            if let Value::Object(map) = item {
-                map.insert("generated_eml".to_string(), Value::String(eml_path.to_string_lossy().to_string()));
+/// Verschickt die E-Mails aller Einträge mit .eml-Datei, die noch nicht zugestellt wurden
+/// (mit `resend` alle), und trägt das Ergebnis je Eintrag ins Manifest ein.
+/// Gibt die Anzahl der verschickten und der gescheiterten E-Mails zurück.
+fn deliver_emails(manifest: &mut Manifest, settings: &SmtpSettings, resend: bool) -> Result<(usize, usize)> {
+    let pending: Vec<usize> = manifest
+        .entries
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| e.eml.is_some() && e.status != EntryStatus::Failed && (resend || !e.is_delivered()))
+        .map(|(idx, _)| idx)
+        .collect();
+    if pending.is_empty() {
+        info!("No emails to send");
+        return Ok((0, 0));
+    }
+
+    info!("Sending {} emails via {}:{}", pending.len(), settings.host, settings.port());
+    let mut mailer = Mailer::connect(settings)?;
+    let (mut sent, mut failed) = (0, 0);
+    for idx in pending {
+        let entry = &mut manifest.entries[idx];
+        let eml = entry.eml.as_deref().unwrap_or(Path::new(""));
+        let delivery = match std::fs::read(eml) {
+            Ok(message) => mailer.send(&message),
+            Err(e) => Delivery::failed(format!("{}: {}", eml.display(), e)),
+        };
+        match delivery.status {
+            DeliveryStatus::Sent => {
+                sent += 1;
+                info!("Sent [{}] {} to {}", entry.index, entry.name, delivery.recipients.join(", "));
+            }
+            DeliveryStatus::Failed => {
+                failed += 1;
+                println!(
+                    "✗ [{}] {}: email not delivered: {}",
+                    entry.index,
+                    entry.name,
+                    delivery.error.as_deref().unwrap_or_default()
+                );
             }
         }
+        entry.delivery = Some(delivery);
+    }
+    Ok((sent, failed))
+}
 
-        created += 1;
-        info!("Created [{}] -> {}", idx, output_str);
+/// Pfad eines Zertifikats aus der Dateinamen-Vorlage; legt Unterverzeichnisse an.
+/// `{{DATE}}` steht hier für das sortierbare Datum `JJJJ-MM-TT` statt des Datumstexts.
+fn output_path(
+    output_dir: &str,
+    filenames: &FilenameTemplate,
+    names: &mut OutputNames,
+    cert: &CertificateData,
+    replacements: &HashMap<String, String>,
+    language: Option<Locale>,
+) -> Result<PathBuf> {
+    let mut fields = replacements.clone();
+    fields.insert("DATE".to_string(), cert.date.format("%Y-%m-%d").to_string());
+    let mut filename = filenames.render(&fields)?.into_os_string();
+    filename.push(".pdf");
+    let output = names.claim(with_language_suffix(&Path::new(output_dir).join(filename), language))?;
+    if let Some(parent) = output.parent() {
+        std::fs::create_dir_all(parent)?;
     }
+    Ok(output)
+}
 
-    // Schreibe die aktualisierte JSON-Datei zurück (überschreibt input file)
-    let pretty = serde_json::to_string_pretty(&v)?;
-    std::fs::write(json_path, pretty)?;
+/// Zeile und E-Mail-Adresse eines Eintrags der Eingabedatei für das Register
+fn record_key(idx: usize, item: &Value) -> RecordKey {
+    RecordKey {
+        row: Some(idx),
+        email: item.get("email").and_then(Value::as_str).map(String::from),
+    }
+}
 
-    Ok(created)
+/// Name eines Eintrags der Eingabedatei für Meldungen, auch wenn er nicht gelesen werden kann
+fn record_name(item: &Value) -> &str {
+    item.get("certificate")
+        .unwrap_or(item)
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+}
+
+/// Nachbearbeitung einer erzeugten Datei: signieren, im Register eintragen und bei einer
+/// E-Mail-Adresse eine .eml-Datei mit Anhang erzeugen
+fn finish_output(
+    output: &BatchOutput,
+    cert: &CertificateData,
+    email: Option<&str>,
+    output_dir: &str,
+    options: &BatchOptions,
+    registry: &mut Registry,
+) -> Result<(RegistryEntry, Option<PathBuf>)> {
+    let output_path = &output.job.output;
+
+    // Betreff und Text vor dem Signieren einsetzen, damit ein fehlendes Feld nichts ins Register schreibt
+    let message = match email {
+        Some(email) => {
+            let fields = email_fields(&output.job.replacements, output_path, email);
+            Some(options.email.render(&options.localization, output.language, &fields)?)
+        }
+        None => None,
+    };
+
+    if let Some(signer) = &options.signer {
+        signer.sign_file(output_path)?;
+    }
+    let key = RecordKey {
+        row: Some(output.record),
+        email: email.map(String::from),
+    };
+    let registered = registry.record(
+        cert.cert_id.as_deref().unwrap_or_default(),
+        cert,
+        &key,
+        output.language,
+        output_path,
+    )?;
+
+    // Wenn eine E-Mail im Eintrag vorhanden ist, erzeuge automatisch eine .eml-Datei mit Anhang
+    let (Some(email_addr), Some((subject, body))) = (email, message) else {
+        return Ok((registered, None));
+    };
+
+    // Erzeuge ein emails-Unterverzeichnis unter output_dir
+    let eml_path = eml_path(output_dir, output_path);
+    std::fs::create_dir_all(eml_path.parent().unwrap())?;
+
+    // create_eml erwartet Pfad zur erzeugten PDF als Path
+    mail::create_eml(&options.email, email_addr, &subject, &body, output_path, &eml_path)?;
+
+    Ok((registered, Some(eml_path)))
+}
+
+/// Felder für Betreff und Text der E-Mail: die Ersetzungen des Zertifikats sowie
+/// `CERT_FILE` (Dateiname der PDF) und `EMAIL`
+fn email_fields(replacements: &HashMap<String, String>, output_path: &Path, email: &str) -> HashMap<String, String> {
+    let mut fields = replacements.clone();
+    let file_name = output_path.file_name().unwrap_or_default().to_string_lossy();
+    fields.insert("CERT_FILE".to_string(), file_name.into_owned());
+    fields.insert("EMAIL".to_string(), email.to_string());
+    fields
+}
+
+/// Pfad der .eml-Datei zu einem Zertifikat: `<output_dir>/emails/<Pfad der PDF>.eml`,
+/// Unterverzeichnisse der PDFs werden übernommen
+fn eml_path(output_dir: &str, output_path: &Path) -> PathBuf {
+    let relative = output_path
+        .strip_prefix(output_dir)
+        .unwrap_or_else(|_| Path::new(output_path.file_name().unwrap()));
+    Path::new(output_dir).join("emails").join(relative).with_extension("eml")
+}
+
+/// Rendert die Ausgaben gruppiert nach Vorlage; die Ergebnisse stehen in der Reihenfolge von
+/// `outputs`. Aktuelle Dateien (`up_to_date`) und nach einem Fehler nicht mehr gestartete
+/// Ausgaben sind `None`
+fn render_outputs(
+    templates: &Templates,
+    outputs: &[BatchOutput],
+    options: &BatchOptions,
+) -> Vec<Option<Result<Duration>>> {
+    let mut results: Vec<Option<Result<Duration>>> = outputs.iter().map(|_| None).collect();
+
+    let mut groups: BTreeMap<Option<Locale>, Vec<usize>> = BTreeMap::new();
+    for (idx, output) in outputs.iter().enumerate().filter(|(_, output)| output.up_to_date.is_none()) {
+        groups.entry(templates.key(output.language)).or_default().push(idx);
+    }
+
+    for (key, indices) in groups {
+        let doc = templates.get(key);
+        let jobs: Vec<RenderJob> = indices.iter().map(|&idx| outputs[idx].job.clone()).collect();
+        let group_results = match options.bulk {
+            Some(chunk_size) => doc.render_pdfs_bulk(&jobs, chunk_size, options.jobs),
+            None => doc.render_pdfs(&jobs, options.jobs),
+        };
+        for (idx, result) in indices.into_iter().zip(group_results) {
+            results[idx] = Some(result);
+        }
+    }
+
+    results
+}
+
+/// Schreibt die erste erzeugte Datei unter `key` und bei mehreren Sprachen alle unter `all_key`
+fn write_generated(
+    map: &mut serde_json::Map<String, Value>,
+    key: &str,
+    all_key: &str,
+    files: &[(Option<Locale>, String)],
+) {
+    let Some((_, first)) = files.first() else {
+        return;
+    };
+    map.insert(key.to_string(), Value::String(first.clone()));
+    if files.len() > 1 {
+        let all = files
+            .iter()
+            .map(|(language, file)| {
+                let language = language.map(|l| l.to_string()).unwrap_or_default();
+                (language, Value::String(file.clone()))
+            })
+            .collect();
+        map.insert(all_key.to_string(), Value::Object(all));
+    }
+}
+
+/// Prüft die Signaturen der angegebenen PDFs; schlägt fehl, wenn eine Datei
+/// unsigniert ist oder eine Signatur nicht gültig ist
+fn verify_signatures(files: &[PathBuf], ca: Option<&Path>, json: bool) -> Result<()> {
+    let store = ca.map(certgen::sign::load_trust_store).transpose()?;
+
+    let mut results = Vec::with_capacity(files.len());
+    for file in files {
+        let signatures = certgen::sign::verify_pdf(&std::fs::read(file)?, store.as_ref())?;
+        results.push((file, signatures));
+    }
+
+    let failed = results
+        .iter()
+        .filter(|(_, sigs)| sigs.is_empty() || !sigs.iter().all(|s| s.is_valid()))
+        .count();
+
+    if json {
+        let report: Vec<Value> = results
+            .iter()
+            .map(|(file, sigs)| serde_json::json!({ "file": file, "signatures": sigs }))
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        for (file, signatures) in &results {
+            if signatures.is_empty() {
+                println!("✗ {}: not signed", file.display());
+            }
+            for sig in signatures {
+                let marker = if sig.is_valid() { "✓" } else { "✗" };
+                println!(
+                    "{} {}: signed by {} ({})",
+                    marker,
+                    file.display(),
+                    sig.signer.as_deref().unwrap_or("unknown"),
+                    sig.signing_time.as_deref().unwrap_or("no signing time")
+                );
+                if !sig.intact {
+                    println!("    signed content has been modified");
+                } else if !sig.covers_document {
+                    println!("    document was changed after signing");
+                }
+                match sig.trusted {
+                    Some(true) => println!("    certificate trusted"),
+                    Some(false) => println!("    certificate not trusted"),
+                    None => println!("    certificate not checked (use --ca)"),
+                }
+                if let Some(error) = &sig.error {
+                    debug!("{}: {}", file.display(), error);
+                }
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(CertgenError::Signature(format!("{} of {} file(s) failed verification", failed, files.len())));
+    }
+    Ok(())
+}
+
+fn inspect_template(template: &str, json: bool) -> Result<()> {
+    let doc = OdfDocument::open(template)?;
+    let placeholders = doc.placeholders()?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&placeholders)?);
+        return Ok(());
+    }
+
+    if placeholders.is_empty() {
+        println!("No placeholders found in {}", template);
+        return Ok(());
+    }
+
+    println!("Placeholders in {}:", template);
+    let width = placeholders.iter().map(|p| p.name.len()).max().unwrap_or(0);
+    for placeholder in &placeholders {
+        let parts: Vec<String> = placeholder
+            .parts
+            .iter()
+            .map(|(part, count)| format!("{} ×{}", part.label(), count))
+            .collect();
+        println!(
+            "  {:width$}  {:>3}  ({}){}",
+            placeholder.name,
+            placeholder.count,
+            parts.join(", "),
+            if placeholder.optional { "  optional" } else { "" },
+            width = width
+        );
+    }
+
+    Ok(())
 }
 
 fn generate_example(output: &str, extended: bool) -> Result<()> {
@@ -265,28 +1339,223 @@ fn generate_example(output: &str, extended: bool) -> Result<()> {
     Ok(())
 }
 
-fn sanitize_filename(s: &str) -> String {
-    s.chars()
-        .map(|c| match c {
-            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
-            ' ' => '_',
-            'ä' => 'a',
-            'ö' => 'o',
-            'ü' => 'u',
-            'ß' => 's',
-            _ => '_',
-        })
-        .collect()
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Write;
+
+    /// Minimale Vorlage mit `{{NAME}}` und `{{AGENDA}}`
+    fn write_template(path: &Path) {
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(path).unwrap());
+        let stored = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("mimetype", stored).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.text").unwrap();
+        zip.start_file("META-INF/manifest.xml", stored).unwrap();
+        zip.write_all(br#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0"/>"#)
+            .unwrap();
+        zip.start_file("content.xml", stored).unwrap();
+        zip.write_all(b"<text:p>{{NAME}}: {{AGENDA}}</text:p>").unwrap();
+        zip.start_file("styles.xml", stored).unwrap();
+        zip.write_all(b"<office:styles/>").unwrap();
+        zip.finish().unwrap();
+    }
+
+    /// Der Konverter kopiert nur, damit die Tests ohne LibreOffice laufen
+    fn templates(template: &Path) -> Templates {
+        let doc = OdfDocument::open(template.to_str().unwrap())
+            .unwrap()
+            .with_converter(Box::new(CommandConverter::new("cp {input} {output}").unwrap()))
+            .with_keep_going(true);
+        Templates {
+            default: doc,
+            by_language: BTreeMap::new(),
+        }
+    }
+
+    fn batch_options() -> BatchOptions {
+        BatchOptions {
+            strict: false,
+            missing_policy: MissingPolicy::Error,
+            jobs: 2,
+            bulk: None,
+            update_input: false,
+            keep_going: true,
+            resume: false,
+            filenames: FilenameTemplate::default(),
+            on_collision: CollisionPolicy::default(),
+            email: EmailOptions::default(),
+            send: None,
+            signer: None,
+            localization: Localization::default(),
+        }
+    }
+
+    fn statuses(output_dir: &Path) -> Vec<(usize, EntryStatus)> {
+        let manifest = Manifest::load(output_dir).unwrap().unwrap();
+        manifest.entries.iter().map(|entry| (entry.index, entry.status)).collect()
+    }
+
+    #[test]
+    fn test_batch_skips_failed_records_and_resumes() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        write_template(&template);
+        let input = dir.path().join("teilnehmer.json");
+        let write_input = |agenda: &str| {
+            let items = serde_json::json!([
+                { "name": "Max", "date": "15.01.2024", "agenda": "Rust" },
+                { "name": "Kaputt", "date": "31.02.2024", "agenda": "Rust" },
+                { "name": "Erika", "date": "15.01.2024", "agenda": agenda },
+            ]);
+            std::fs::write(&input, items.to_string()).unwrap();
+        };
+        write_input("Go");
+
+        let templates = templates(&template);
+        let options = BatchOptions {
+            resume: true,
+            ..batch_options()
+        };
+        let output_dir = dir.path().join("out");
+        let out = output_dir.to_str().unwrap();
+        let run = || {
+            let mut registry = Registry::open(output_dir.join(DEFAULT_REGISTRY_FILE), Default::default()).unwrap();
+            let source = open_source(&input, &SourceOptions::default()).unwrap();
+            fill_batch(&templates, source.as_ref(), out, &options, &mut registry)
+        };
+
+        // Der Datensatz mit ungültigem Datum wird übersprungen, die anderen werden erzeugt
+        assert!(matches!(run(), Err(CertgenError::BatchFailed(1))));
+        assert_eq!(
+            statuses(&output_dir),
+            vec![(0, EntryStatus::Ok), (1, EntryStatus::Failed), (2, EntryStatus::Ok)]
+        );
+
+        // Ein zweiter Lauf erzeugt unveränderte Dateien nicht neu
+        assert!(matches!(run(), Err(CertgenError::BatchFailed(1))));
+        assert_eq!(
+            statuses(&output_dir),
+            vec![(0, EntryStatus::Skipped), (1, EntryStatus::Failed), (2, EntryStatus::Skipped)]
+        );
+
+        // Nur der geänderte Datensatz wird neu erzeugt
+        write_input("Python");
+        assert!(matches!(run(), Err(CertgenError::BatchFailed(1))));
+        assert_eq!(
+            statuses(&output_dir),
+            vec![(0, EntryStatus::Skipped), (1, EntryStatus::Failed), (2, EntryStatus::Ok)]
+        );
+    }
+
+    #[test]
+    fn test_update_input_with_several_languages() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        write_template(&template);
+        let input = dir.path().join("teilnehmer.json");
+        let items = serde_json::json!([{ "name": "Max", "date": "15.01.2024", "agenda": "Rust", "TITLE": "Kurs" }]);
+        std::fs::write(&input, items.to_string()).unwrap();
+
+        let templates = templates(&template);
+        let options = BatchOptions {
+            update_input: true,
+            keep_going: false,
+            localization: Localization {
+                languages: vec![Locale::De, Locale::En],
+                ..Localization::default()
+            },
+            ..batch_options()
+        };
+        let output_dir = dir.path().join("out");
+        let run = || {
+            let mut registry = Registry::open(output_dir.join(DEFAULT_REGISTRY_FILE), Default::default()).unwrap();
+            let source = open_source(&input, &SourceOptions::default()).unwrap();
+            fill_batch(&templates, source.as_ref(), output_dir.to_str().unwrap(), &options, &mut registry)
+        };
+
+        assert_eq!(run().unwrap(), 2);
+        let written: Value = serde_json::from_str(&std::fs::read_to_string(&input).unwrap()).unwrap();
+        assert!(written[0]["generated_files"].is_object());
+        let cert_id = written[0]["cert_id"].clone();
+
+        // Die zurückgeschriebenen Dateien sind beim nächsten Lauf keine Felder des Datensatzes
+        assert_eq!(run().unwrap(), 2);
+        let rewritten: Value = serde_json::from_str(&std::fs::read_to_string(&input).unwrap()).unwrap();
+        assert_eq!(rewritten, written);
+        assert_eq!(rewritten[0]["cert_id"], cert_id);
+    }
 
     #[test]
-    fn test_sanitize_filename() {
-        assert_eq!(sanitize_filename("Max Mustermann"), "Max_Mustermann");
-        assert_eq!(sanitize_filename("Test/File"), "Test_File");
-        assert_eq!(sanitize_filename("Müller"), "Muller");
+    fn test_resume_recreates_email_for_new_address() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        write_template(&template);
+        let input = dir.path().join("teilnehmer.json");
+        let write_input = |email: &str| {
+            let items = serde_json::json!([
+                { "email": email, "certificate": { "name": "Max", "date": "15.01.2024", "agenda": "Rust" } }
+            ]);
+            std::fs::write(&input, items.to_string()).unwrap();
+        };
+        write_input("max@example.com");
+
+        let templates = templates(&template);
+        let options = BatchOptions {
+            resume: true,
+            email: EmailOptions {
+                from: Some("kurs@example.com".to_string()),
+                ..EmailOptions::default()
+            },
+            ..batch_options()
+        };
+        let output_dir = dir.path().join("out");
+        let run = || {
+            let mut registry = Registry::open(output_dir.join(DEFAULT_REGISTRY_FILE), Default::default()).unwrap();
+            let source = open_source(&input, &SourceOptions::default()).unwrap();
+            fill_batch(&templates, source.as_ref(), output_dir.to_str().unwrap(), &options, &mut registry)
+        };
+
+        assert_eq!(run().unwrap(), 1);
+        assert_eq!(run().unwrap(), 0);
+        assert_eq!(statuses(&output_dir), vec![(0, EntryStatus::Skipped)]);
+
+        // Nur die Adresse ändert sich: PDF und E-Mail werden neu erstellt
+        write_input("max.mustermann@example.com");
+        assert_eq!(run().unwrap(), 1);
+        assert_eq!(statuses(&output_dir), vec![(0, EntryStatus::Ok)]);
+        let eml = std::fs::read_to_string(output_dir.join("emails/Max_Kurs.eml")).unwrap();
+        assert!(eml.contains("To: max.mustermann@example.com\r\n"));
+    }
+
+    #[test]
+    fn test_emails_need_a_sender() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        write_template(&template);
+        let input = dir.path().join("teilnehmer.json");
+        let item = serde_json::json!([{ "email": "max@example.com", "certificate": { "name": "Max", "date": "15.01.2024", "agenda": "Rust" } }]);
+        std::fs::write(&input, item.to_string()).unwrap();
+
+        let templates = templates(&template);
+        let output_dir = dir.path().join("out");
+        let run = |options: &BatchOptions| {
+            let mut registry = Registry::open(output_dir.join(DEFAULT_REGISTRY_FILE), Default::default()).unwrap();
+            let source = open_source(&input, &SourceOptions::default()).unwrap();
+            fill_batch(&templates, source.as_ref(), output_dir.to_str().unwrap(), options, &mut registry)
+        };
+
+        assert!(matches!(run(&batch_options()), Err(CertgenError::Email(_))));
+        assert!(!output_dir.exists());
+
+        let options = BatchOptions {
+            email: EmailOptions {
+                from: Some("Kursteam <kurs@example.com>".to_string()),
+                ..EmailOptions::default()
+            },
+            ..batch_options()
+        };
+        assert_eq!(run(&options).unwrap(), 1);
+        let eml = std::fs::read_to_string(output_dir.join("emails/Max_Kurs.eml")).unwrap();
+        assert!(eml.starts_with("From: Kursteam <kurs@example.com>\r\n"));
     }
 }
@@ -0,0 +1,454 @@
+//! Protokoll eines Batch-Laufs: `manifest.json` mit einem Eintrag je Ausgabedatei
+//! (Status, Prüfsumme, Dauer, Fehler, E-Mail-Versand) und eine lesbare Zusammenfassung `summary.md`.
+//! Mit dem Inhalts-Hash eines Eintrags erkennt `--resume`, welche Dateien aktuell sind.
+
+use crate::error::Result;
+use crate::registry::sha256_file;
+use crate::source::write_atomic;
+use crate::template::Locale;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Dateiname des Manifests im Ausgabeverzeichnis
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Dateiname der Zusammenfassung im Ausgabeverzeichnis
+pub const SUMMARY_FILE: &str = "summary.md";
+
+/// Ergebnis einer Ausgabedatei
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryStatus {
+    Ok,
+    Skipped,
+    Failed,
+}
+
+/// Eine Ausgabedatei des Laufs; bei mehreren Sprachen gibt es je Datensatz mehrere Einträge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    /// Index des Datensatzes in der Eingabedatei
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Locale>,
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    /// Fehlt bei Datensätzen, die schon beim Einlesen oder Prüfen scheitern
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    /// SHA-256 der erzeugten Datei (hex)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cert_id: Option<String>,
+    /// Hash über Vorlage, Bilder, Einstellungen und Ersetzungen, siehe `content_hash`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_hash: Option<String>,
+    pub status: EntryStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Dauer der Erzeugung in Millisekunden
+    #[serde(default)]
+    pub duration_ms: u64,
+    /// Erzeugte E-Mail (.eml) mit dem Zertifikat als Anhang
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eml: Option<PathBuf>,
+    /// Ergebnis des Versands per SMTP (`--send` bzw. `certgen send`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delivery: Option<Delivery>,
+}
+
+impl ManifestEntry {
+    /// Ein noch nicht verarbeiteter Eintrag (Status `skipped`)
+    pub fn new(index: usize, name: &str) -> Self {
+        Self {
+            index,
+            language: None,
+            name: name.to_string(),
+            email: None,
+            output: None,
+            size: None,
+            sha256: None,
+            cert_id: None,
+            input_hash: None,
+            status: EntryStatus::Skipped,
+            error: None,
+            duration_ms: 0,
+            eml: None,
+            delivery: None,
+        }
+    }
+
+    /// Die E-Mail wurde bereits erfolgreich verschickt
+    pub fn is_delivered(&self) -> bool {
+        self.delivery.as_ref().is_some_and(|d| d.status == DeliveryStatus::Sent)
+    }
+}
+
+/// Ergebnis des Versands einer E-Mail
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Sent,
+    Failed,
+}
+
+/// Versand einer E-Mail an ihre Empfänger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Delivery {
+    pub status: DeliveryStatus,
+    /// Empfänger laut Umschlag (To, Cc und Bcc)
+    pub recipients: Vec<String>,
+    /// Anzahl der Versuche einschließlich Wiederholungen
+    pub attempts: u32,
+    /// Zeitpunkt des letzten Versuchs (RFC 3339)
+    pub at: String,
+    /// Antwort des Servers, z.B. `250 2.0.0 Ok: queued as 4F2A1`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Delivery {
+    /// Ein Versand, der ohne Versuch gescheitert ist (z.B. fehlende .eml-Datei)
+    pub fn failed(error: String) -> Self {
+        Self {
+            status: DeliveryStatus::Failed,
+            recipients: Vec::new(),
+            attempts: 0,
+            at: chrono::Local::now().to_rfc3339(),
+            response: None,
+            error: Some(error),
+        }
+    }
+}
+
+/// Manifest eines Batch-Laufs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    /// Beginn des Laufs (RFC 3339)
+    pub started_at: String,
+    /// Ende des Laufs (RFC 3339)
+    #[serde(default)]
+    pub finished_at: String,
+    #[serde(default)]
+    pub duration_ms: u64,
+    pub entries: Vec<ManifestEntry>,
+    #[serde(skip)]
+    started: Option<Instant>,
+}
+
+impl Default for Manifest {
+    fn default() -> Self {
+        Self::start()
+    }
+}
+
+impl Manifest {
+    /// Beginnt ein neues Manifest zum aktuellen Zeitpunkt
+    pub fn start() -> Self {
+        Self {
+            started_at: chrono::Local::now().to_rfc3339(),
+            finished_at: String::new(),
+            duration_ms: 0,
+            entries: Vec::new(),
+            started: Some(Instant::now()),
+        }
+    }
+
+    /// Liest das Manifest eines früheren Laufs aus `dir`, `None` wenn es keines gibt
+    pub fn load(dir: &Path) -> Result<Option<Self>> {
+        let path = dir.join(MANIFEST_FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+    }
+
+    /// Der Eintrag eines früheren Laufs für `output`, wenn die Datei mit demselben
+    /// Inhalts-Hash erzeugt wurde und seitdem unverändert ist
+    pub fn up_to_date(&self, output: &Path, input_hash: &str) -> Option<&ManifestEntry> {
+        let entry = self.entries.iter().rev().find(|e| e.output.as_deref() == Some(output))?;
+        if entry.input_hash.as_deref() != Some(input_hash) {
+            return None;
+        }
+        let sha256 = entry.sha256.as_deref()?;
+        (sha256_file(output).ok()?.as_str() == sha256).then_some(entry)
+    }
+
+    pub fn push(&mut self, entry: ManifestEntry) {
+        self.entries.push(entry);
+    }
+
+    /// Anzahl der Einträge mit dem Status
+    pub fn count(&self, status: EntryStatus) -> usize {
+        self.entries.iter().filter(|e| e.status == status).count()
+    }
+
+    /// Anzahl der E-Mails mit dem Versandstatus
+    pub fn count_delivered(&self, status: DeliveryStatus) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.delivery.as_ref().is_some_and(|d| d.status == status))
+            .count()
+    }
+
+    /// Schließt den Lauf ab (Ende und Gesamtdauer)
+    pub fn finish(&mut self) {
+        self.finished_at = chrono::Local::now().to_rfc3339();
+        if let Some(started) = self.started {
+            self.duration_ms = millis(started.elapsed());
+        }
+    }
+
+    /// Schreibt `manifest.json` und `summary.md` nach `dir` und gibt die Pfade zurück
+    pub fn write(&self, dir: &Path) -> Result<(PathBuf, PathBuf)> {
+        let manifest_path = dir.join(MANIFEST_FILE);
+        write_atomic(&manifest_path, serde_json::to_string_pretty(self)?.as_bytes())?;
+        let summary_path = dir.join(SUMMARY_FILE);
+        write_atomic(&summary_path, self.summary().as_bytes())?;
+        Ok((manifest_path, summary_path))
+    }
+
+    /// Zusammenfassung als Markdown
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# Certificate batch summary\n");
+        let _ = writeln!(out, "- Started: {}", self.started_at);
+        let _ = writeln!(out, "- Finished: {}", self.finished_at);
+        let _ = writeln!(out, "- Duration: {:.1} s", self.duration_ms as f64 / 1000.0);
+        let _ = writeln!(
+            out,
+            "- Files: {} ok, {} skipped, {} failed\n",
+            self.count(EntryStatus::Ok),
+            self.count(EntryStatus::Skipped),
+            self.count(EntryStatus::Failed)
+        );
+
+        let _ = writeln!(out, "| # | Name | Language | Certificate ID | File | Size | Status |");
+        let _ = writeln!(out, "|---|------|----------|----------------|------|------|--------|");
+        for entry in &self.entries {
+            let _ = writeln!(
+                out,
+                "| {} | {} | {} | {} | {} | {} | {} |",
+                entry.index,
+                cell(&entry.name),
+                entry.language.map(|l| l.to_string()).unwrap_or_default(),
+                cell(entry.cert_id.as_deref().unwrap_or_default()),
+                entry.output.as_ref().map(|p| cell(&p.to_string_lossy())).unwrap_or_default(),
+                entry.size.map(format_size).unwrap_or_default(),
+                status_text(entry.status)
+            );
+        }
+
+        let deliveries: Vec<(&ManifestEntry, &Delivery)> =
+            self.entries.iter().filter_map(|e| Some((e, e.delivery.as_ref()?))).collect();
+        if !deliveries.is_empty() {
+            let _ = writeln!(out, "\n## Email delivery\n");
+            let _ = writeln!(
+                out,
+                "- Emails: {} sent, {} failed\n",
+                self.count_delivered(DeliveryStatus::Sent),
+                self.count_delivered(DeliveryStatus::Failed)
+            );
+            let _ = writeln!(out, "| # | Name | Recipients | Attempts | Status | Response |");
+            let _ = writeln!(out, "|---|------|------------|----------|--------|----------|");
+            for (entry, delivery) in deliveries {
+                let _ = writeln!(
+                    out,
+                    "| {} | {} | {} | {} | {} | {} |",
+                    entry.index,
+                    cell(&entry.name),
+                    cell(&delivery.recipients.join(", ")),
+                    delivery.attempts,
+                    match delivery.status {
+                        DeliveryStatus::Sent => "✓ sent",
+                        DeliveryStatus::Failed => "✗ failed",
+                    },
+                    cell(delivery.response.as_deref().or(delivery.error.as_deref()).unwrap_or_default())
+                );
+            }
+        }
+
+        let errors: Vec<&ManifestEntry> = self.entries.iter().filter(|e| e.error.is_some()).collect();
+        if !errors.is_empty() {
+            let _ = writeln!(out, "\n## Errors\n");
+            for entry in errors {
+                let _ = writeln!(
+                    out,
+                    "- [{}] {}: {}",
+                    entry.index,
+                    entry.name,
+                    entry.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        out
+    }
+}
+
+/// Hash über alles, was eine Datei bestimmt: `inputs` (z.B. SHA-256 der Vorlage und der
+/// eingesetzten Bilder, QR- und Signatureinstellungen) und alle Ersetzungen eines
+/// Zertifikats; ändert sich einer der Werte, muss die Datei neu erzeugt werden
+pub fn content_hash(inputs: &[&str], replacements: &HashMap<String, String>) -> String {
+    let mut hasher = openssl::sha::Sha256::new();
+    for input in inputs {
+        hasher.update(input.as_bytes());
+        hasher.update(b"\0");
+    }
+    let sorted: BTreeMap<&String, &String> = replacements.iter().collect();
+    for (key, value) in sorted {
+        hasher.update(b"\0");
+        hasher.update(key.as_bytes());
+        hasher.update(b"=");
+        hasher.update(value.as_bytes());
+    }
+    hasher.finish().iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Millisekunden einer Dauer für das Manifest
+pub fn millis(duration: Duration) -> u64 {
+    duration.as_millis().try_into().unwrap_or(u64::MAX)
+}
+
+fn status_text(status: EntryStatus) -> &'static str {
+    match status {
+        EntryStatus::Ok => "✓ ok",
+        EntryStatus::Skipped => "– skipped",
+        EntryStatus::Failed => "✗ failed",
+    }
+}
+
+/// Markdown-Tabellenzelle: `|` und Zeilenumbrüche würden die Tabelle zerstören
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(index: usize, name: &str, status: EntryStatus) -> ManifestEntry {
+        ManifestEntry {
+            output: Some(PathBuf::from(format!("out/{}.pdf", index))),
+            size: (status == EntryStatus::Ok).then_some(2048),
+            sha256: (status == EntryStatus::Ok).then(|| "ab".repeat(32)),
+            cert_id: Some(format!("CERT-{}", index)),
+            status,
+            error: (status == EntryStatus::Failed).then(|| "conversion failed".to_string()),
+            duration_ms: 120,
+            ..ManifestEntry::new(index, name)
+        }
+    }
+
+    #[test]
+    fn test_manifest_roundtrip_and_summary() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut manifest = Manifest::start();
+        manifest.push(entry(0, "Max | Muster", EntryStatus::Ok));
+        manifest.push(entry(1, "Erika", EntryStatus::Failed));
+        manifest.push(entry(2, "Hans", EntryStatus::Skipped));
+        manifest.finish();
+
+        let (manifest_path, summary_path) = manifest.write(dir.path()).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&manifest_path).unwrap()).unwrap();
+        assert_eq!(json["entries"][0]["status"], "ok");
+        assert_eq!(json["entries"][0]["size"], 2048);
+        assert_eq!(json["entries"][1]["error"], "conversion failed");
+        assert!(json["entries"][2].get("sha256").is_none());
+
+        let loaded: Manifest = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.entries, manifest.entries);
+
+        let summary = std::fs::read_to_string(summary_path).unwrap();
+        assert!(summary.contains("- Files: 1 ok, 1 skipped, 1 failed"));
+        assert!(summary.contains("| 0 | Max \\| Muster |  | CERT-0 | out/0.pdf | 2.0 KiB | ✓ ok |"));
+        assert!(summary.contains("## Errors\n\n- [1] Erika: conversion failed"));
+        assert!(!summary.contains("## Email delivery"));
+    }
+
+    #[test]
+    fn test_delivery_summary() {
+        let mut manifest = Manifest::start();
+        let mut sent = entry(0, "Max", EntryStatus::Ok);
+        sent.delivery = Some(Delivery {
+            status: DeliveryStatus::Sent,
+            recipients: vec!["max@example.com".to_string()],
+            attempts: 2,
+            at: "2024-01-15T10:00:00+01:00".to_string(),
+            response: Some("250 Ok".to_string()),
+            error: None,
+        });
+        let mut failed = entry(1, "Erika", EntryStatus::Ok);
+        failed.delivery = Some(Delivery {
+            status: DeliveryStatus::Failed,
+            recipients: vec!["erika@example.com".to_string()],
+            attempts: 1,
+            at: "2024-01-15T10:00:01+01:00".to_string(),
+            response: None,
+            error: Some("550 mailbox unavailable".to_string()),
+        });
+        manifest.push(sent);
+        manifest.push(failed);
+        manifest.push(entry(2, "Hans", EntryStatus::Ok));
+
+        assert!(manifest.entries[0].is_delivered());
+        assert!(!manifest.entries[1].is_delivered());
+        assert!(!manifest.entries[2].is_delivered());
+
+        let summary = manifest.summary();
+        assert!(summary.contains("- Emails: 1 sent, 1 failed"));
+        assert!(summary.contains("| 0 | Max | max@example.com | 2 | ✓ sent | 250 Ok |"));
+        assert!(summary.contains("| 1 | Erika | erika@example.com | 1 | ✗ failed | 550 mailbox unavailable |"));
+    }
+
+    #[test]
+    fn test_up_to_date() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("Max.pdf");
+        std::fs::write(&output, b"%PDF-1.4").unwrap();
+
+        let replacements = HashMap::from([("NAME".to_string(), "Max".to_string())]);
+        let hash = content_hash(&["template", "qr=3cm"], &replacements);
+        assert_ne!(hash, content_hash(&["other template", "qr=3cm"], &replacements));
+        assert_ne!(hash, content_hash(&["template", "qr=4cm"], &replacements));
+        assert_ne!(hash, content_hash(&["template", "qr=3cm"], &HashMap::from([("NAME".to_string(), "Erika".to_string())])));
+
+        let mut manifest = Manifest::start();
+        manifest.push(ManifestEntry {
+            output: Some(output.clone()),
+            sha256: Some(sha256_file(&output).unwrap()),
+            input_hash: Some(hash.clone()),
+            status: EntryStatus::Ok,
+            ..ManifestEntry::new(0, "Max")
+        });
+        manifest.write(dir.path()).unwrap();
+
+        let loaded = Manifest::load(dir.path()).unwrap().unwrap();
+        assert!(loaded.up_to_date(&output, &hash).is_some());
+        assert!(loaded.up_to_date(&output, "changed").is_none());
+        assert!(loaded.up_to_date(&dir.path().join("Erika.pdf"), &hash).is_none());
+
+        // Nachträglich veränderte Dateien werden neu erzeugt
+        std::fs::write(&output, b"%PDF-1.7").unwrap();
+        assert!(loaded.up_to_date(&output, &hash).is_none());
+        assert!(Manifest::load(&dir.path().join("missing")).unwrap().is_none());
+    }
+}
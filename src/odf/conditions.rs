@@ -0,0 +1,300 @@
+//! Bedingte Abschnitte: `{{#if KEY}} ... {{/if}}` und `{{#unless KEY}} ... {{/unless}}`,
+//! jeweils optional mit `{{else}}`.
+//!
+//! Steht eine Markierung allein in einem Absatz, wird der ganze Absatz entfernt; ist
+//! dieser Absatz der einzige Inhalt eines Listeneintrags oder einer Tabellenzeile, wird
+//! der Listeneintrag bzw. die ganze Zeile entfernt. So bleiben weder leere `<text:p>`
+//! noch leere Zeilen zurück. Markierungen mitten im Text wirken nur innerhalb des Absatzes.
+
+use crate::error::{CertgenError, Result};
+use log::debug;
+use regex::Regex;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Werte, die (unabhängig von Groß-/Kleinschreibung) als „falsch“ gelten
+const FALSY_VALUES: [&str; 4] = ["false", "0", "no", "nein"];
+
+/// Findet `{{#if KEY}}`, `{{#unless KEY}}`, `{{else}}`, `{{/if}}` und `{{/unless}}`
+pub(crate) fn condition_regex() -> Regex {
+    Regex::new(r"\{\{\s*(?:#(if|unless)\s+([^{}<>\s]+)|(else)|/(if|unless))\s*\}\}").unwrap()
+}
+
+/// Ein Feld gilt als wahr, wenn es einen nicht leeren Wert hat, der nicht
+/// `false`, `0`, `no` oder `nein` ist
+pub(crate) fn is_truthy(value: Option<&String>) -> bool {
+    value.is_some_and(|value| {
+        let value = value.trim();
+        !value.is_empty() && !FALSY_VALUES.iter().any(|falsy| value.eq_ignore_ascii_case(falsy))
+    })
+}
+
+#[derive(Debug)]
+enum MarkerKind {
+    Open { unless: bool, key: String },
+    Else,
+    Close { unless: bool },
+}
+
+#[derive(Debug)]
+struct Marker {
+    kind: MarkerKind,
+    /// Der Platzhalter selbst, z.B. `{{#if EXAM_PASSED}}`
+    text: String,
+    /// Bereich, der beim Auflösen entfernt wird (Markierung bzw. umgebender Absatz/Zeile)
+    unit: Range<usize>,
+}
+
+struct Block {
+    open: Marker,
+    else_marker: Option<Marker>,
+}
+
+/// Wertet alle bedingten Abschnitte in `content` aus
+pub(crate) fn apply_conditions(content: &str, replacements: &HashMap<String, String>) -> Result<String> {
+    let re = condition_regex();
+    let mut stack: Vec<Block> = Vec::new();
+    let mut removals: Vec<Range<usize>> = Vec::new();
+
+    for caps in re.captures_iter(content) {
+        let whole = caps.get(0).unwrap();
+        let kind = if let (Some(kind), Some(key)) = (caps.get(1), caps.get(2)) {
+            MarkerKind::Open {
+                unless: kind.as_str() == "unless",
+                key: key.as_str().to_string(),
+            }
+        } else if caps.get(3).is_some() {
+            MarkerKind::Else
+        } else {
+            MarkerKind::Close {
+                unless: &caps[4] == "unless",
+            }
+        };
+        let marker = Marker {
+            kind,
+            text: whole.as_str().to_string(),
+            unit: marker_unit(content, whole.range()),
+        };
+
+        match marker.kind {
+            MarkerKind::Open { .. } => stack.push(Block {
+                open: marker,
+                else_marker: None,
+            }),
+            MarkerKind::Else => match stack.last_mut() {
+                Some(block) if block.else_marker.is_none() => block.else_marker = Some(marker),
+                Some(block) => {
+                    return Err(CertgenError::TemplateSyntax(format!("second {{{{else}}}} in {}", block.open.text)))
+                }
+                None => return Err(CertgenError::TemplateSyntax("{{else}} outside of {{#if}}/{{#unless}}".into())),
+            },
+            MarkerKind::Close { unless } => {
+                let block = stack
+                    .pop()
+                    .ok_or_else(|| CertgenError::TemplateSyntax(format!("{} without opening block", marker.text)))?;
+                let MarkerKind::Open { unless: open_unless, ref key } = block.open.kind else {
+                    unreachable!("only opening markers are pushed");
+                };
+                if open_unless != unless {
+                    return Err(CertgenError::TemplateSyntax(format!(
+                        "{} is closed by {}",
+                        block.open.text, marker.text
+                    )));
+                }
+
+                // Beide Zweige prüfen, damit eine fehlerhafte Vorlage nicht nur bei manchen Datensätzen auffällt
+                let crosses_elements = [true, false]
+                    .into_iter()
+                    .flat_map(|show| block_removals(&block, &marker, show))
+                    .any(|range| !is_balanced(&content[range]));
+                if crosses_elements {
+                    return Err(CertgenError::TemplateSyntax(format!(
+                        "{} ... {} spans several paragraphs: put each marker in a paragraph of its own \
+                         or keep both in the same paragraph",
+                        block.open.text, marker.text
+                    )));
+                }
+
+                let show = is_truthy(replacements.get(key)) != unless;
+                debug!("Condition {} evaluates to {}", block.open.text, show);
+                removals.extend(block_removals(&block, &marker, show));
+            }
+        }
+    }
+
+    if let Some(block) = stack.pop() {
+        return Err(CertgenError::TemplateSyntax(format!("{} is never closed", block.open.text)));
+    }
+
+    Ok(remove_ranges(content, removals))
+}
+
+/// Zu entfernende Bereiche eines Blocks: die Markierungen und der nicht angezeigte Zweig
+fn block_removals(block: &Block, close: &Marker, show: bool) -> Vec<Range<usize>> {
+    let open = &block.open.unit;
+    match (&block.else_marker, show) {
+        (None, true) => vec![open.clone(), close.unit.clone()],
+        (None, false) => vec![Range {
+            start: open.start,
+            end: close.unit.end,
+        }],
+        (Some(else_marker), true) => vec![open.clone(), else_marker.unit.start..close.unit.end],
+        (Some(else_marker), false) => vec![open.start..else_marker.unit.end, close.unit.clone()],
+    }
+}
+
+/// Bereich, der für eine Markierung entfernt wird: der Absatz (bzw. Listeneintrag
+/// oder Tabellenzeile), wenn die Markierung dessen einziger Text ist, sonst nur die Markierung
+fn marker_unit(content: &str, marker: Range<usize>) -> Range<usize> {
+    let text = &content[marker.clone()];
+    let mut unit = marker.clone();
+
+    for tags in [&["text:p", "text:h"][..], &["text:list-item"], &["table:table-row"]] {
+        let Some(element) = tags.iter().find_map(|tag| enclosing_element(content, &unit, tag)) else {
+            continue;
+        };
+        if strip_tags(&content[element.clone()]).trim() != text {
+            break;
+        }
+        unit = element;
+    }
+    unit
+}
+
+/// Innerstes Element `<tag ...>...</tag>`, das `range` umschließt
+pub(crate) fn enclosing_element(content: &str, range: &Range<usize>, tag: &str) -> Option<Range<usize>> {
+    let open = format!("<{}", tag);
+    let close = format!("</{}>", tag);
+    let before = &content[..range.start];
+
+    let start = before
+        .rmatch_indices(open.as_str())
+        .map(|(pos, _)| pos)
+        .find(|&pos| {
+            let next = before[pos + open.len()..].chars().next();
+            matches!(next, Some(' ' | '>'))
+        })?;
+    if before[start..].contains(&close) {
+        return None;
+    }
+    let end = content[range.end..].find(&close)? + range.end + close.len();
+    Some(start..end)
+}
+
+fn strip_tags(xml: &str) -> String {
+    Regex::new(r"<[^>]*>").unwrap().replace_all(xml, "").into_owned()
+}
+
+/// Prüft, ob alle in `xml` geöffneten Elemente darin auch wieder geschlossen werden
+fn is_balanced(xml: &str) -> bool {
+    let tag = Regex::new(r"<(/?)([A-Za-z][\w:.-]*)[^>]*?(/?)>").unwrap();
+    let mut open: Vec<&str> = Vec::new();
+    for caps in tag.captures_iter(xml) {
+        let name = caps.get(2).unwrap().as_str();
+        if !caps[3].is_empty() {
+            continue;
+        }
+        if caps[1].is_empty() {
+            open.push(name);
+        } else if open.pop() != Some(name) {
+            return false;
+        }
+    }
+    open.is_empty()
+}
+
+/// Entfernt die (ggf. verschachtelten oder überlappenden) Bereiche aus `content`
+fn remove_ranges(content: &str, mut ranges: Vec<Range<usize>>) -> String {
+    ranges.sort_by_key(|range| range.start);
+    let mut result = String::with_capacity(content.len());
+    let mut pos = 0;
+    for range in ranges {
+        if range.start > pos {
+            result.push_str(&content[pos..range.start]);
+        }
+        pos = pos.max(range.end);
+    }
+    result.push_str(&content[pos..]);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(exam: &str) -> HashMap<String, String> {
+        HashMap::from([("EXAM_PASSED".to_string(), exam.to_string())])
+    }
+
+    const PARAGRAPHS: &str = concat!(
+        "<text:p>Teilnahme</text:p>",
+        r#"<text:p text:style-name="P1">{{#if EXAM_PASSED}}</text:p>"#,
+        "<text:p>mit Prüfung</text:p>",
+        "<text:p>{{else}}</text:p>",
+        "<text:p>ohne Prüfung</text:p>",
+        "<text:p><text:span>{{/if}}</text:span></text:p>",
+        "<text:p>Ende</text:p>"
+    );
+
+    #[test]
+    fn test_paragraph_blocks() {
+        assert_eq!(
+            apply_conditions(PARAGRAPHS, &data("ja")).unwrap(),
+            "<text:p>Teilnahme</text:p><text:p>mit Prüfung</text:p><text:p>Ende</text:p>"
+        );
+        assert_eq!(
+            apply_conditions(PARAGRAPHS, &data("nein")).unwrap(),
+            "<text:p>Teilnahme</text:p><text:p>ohne Prüfung</text:p><text:p>Ende</text:p>"
+        );
+        assert_eq!(
+            apply_conditions(PARAGRAPHS, &HashMap::new()).unwrap(),
+            "<text:p>Teilnahme</text:p><text:p>ohne Prüfung</text:p><text:p>Ende</text:p>"
+        );
+    }
+
+    #[test]
+    fn test_table_rows_and_inline() {
+        let content = concat!(
+            "<table:table>",
+            "<table:table-row><table:table-cell><text:p>{{#unless EXAM_PASSED}}</text:p></table:table-cell>",
+            "<table:table-cell/></table:table-row>",
+            "<table:table-row><table:table-cell><text:p>Nachprüfung</text:p></table:table-cell></table:table-row>",
+            "<table:table-row><table:table-cell><text:p>{{/unless}}</text:p></table:table-cell></table:table-row>",
+            "</table:table>",
+            "<text:p>Kurs{{#if EXAM_PASSED}} mit Prüfung{{/if}}.</text:p>"
+        );
+
+        assert_eq!(
+            apply_conditions(content, &data("true")).unwrap(),
+            "<table:table></table:table><text:p>Kurs mit Prüfung.</text:p>"
+        );
+        assert_eq!(
+            apply_conditions(content, &data("")).unwrap(),
+            concat!(
+                "<table:table><table:table-row><table:table-cell><text:p>Nachprüfung</text:p>",
+                "</table:table-cell></table:table-row></table:table><text:p>Kurs.</text:p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_nested_blocks() {
+        let content = "<text:p>{{#if A}}</text:p><text:p>a</text:p><text:p>{{#if B}}</text:p><text:p>b</text:p><text:p>{{/if}}</text:p><text:p>{{/if}}</text:p>";
+        let both = HashMap::from([("A".to_string(), "1".to_string()), ("B".to_string(), "1".to_string())]);
+        assert_eq!(apply_conditions(content, &both).unwrap(), "<text:p>a</text:p><text:p>b</text:p>");
+        let only_b = HashMap::from([("B".to_string(), "1".to_string())]);
+        assert_eq!(apply_conditions(content, &only_b).unwrap(), "");
+    }
+
+    #[test]
+    fn test_invalid_blocks() {
+        let crossing = "<text:p>A {{#if X}}</text:p><text:p>B {{/if}}</text:p>";
+        assert!(matches!(
+            apply_conditions(crossing, &HashMap::new()),
+            Err(CertgenError::TemplateSyntax(_))
+        ));
+        assert!(apply_conditions("<text:p>{{#if X}}</text:p>", &HashMap::new()).is_err());
+        assert!(apply_conditions("<text:p>{{#if X}}</text:p><text:p>{{/unless}}</text:p>", &HashMap::new()).is_err());
+        assert!(apply_conditions("<text:p>{{/if}}</text:p>", &HashMap::new()).is_err());
+    }
+}
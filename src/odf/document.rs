@@ -1,17 +1,81 @@
+use crate::convert::{pdf_output_path, PdfConverter, SofficeConverter};
 use crate::error::{CertgenError, Result};
-use crate::odf::replacer::PlaceholderReplacer;
-use std::collections::HashMap;
+use crate::odf::conditions::{apply_conditions, condition_regex};
+use crate::odf::loops::{expand_loops, loop_element, loop_regex};
+use crate::odf::filters::{placeholder_key, FilterExpression, FilterRegistry};
+use crate::odf::images::{image_frame_keys, register_in_manifest, replace_image_frames, EmbeddedImage, ImageFit};
+use crate::odf::qr::{qr_regex, replace_qr_codes, QrOptions, QR_PLACEHOLDER};
+use crate::odf::replacer::{MissingPolicy, PlaceholderReplacer};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::{self, File};
 use std::io::{Read, Write};
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use zip::{ZipArchive, ZipWriter, write::FileOptions, CompressionMethod};
 use log::{debug, info};
-use which::which; // Laufzeit-Check ob 'soffice' vorhanden
+
+/// Teil des ODF-Pakets, in dem ein Platzhalter vorkommt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocumentPart {
+    /// Dokumentkörper (content.xml)
+    Body,
+    /// Kopf-/Fußzeilen und Seitenvorlagen (styles.xml)
+    Styles,
+}
+
+impl DocumentPart {
+    /// Alle Teile, die Platzhalter enthalten können
+    pub const ALL: [DocumentPart; 2] = [DocumentPart::Body, DocumentPart::Styles];
+
+    /// Dateiname des Teils im ZIP-Archiv
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            DocumentPart::Body => "content.xml",
+            DocumentPart::Styles => "styles.xml",
+        }
+    }
+
+    /// Lesbare Bezeichnung für die Ausgabe
+    pub fn label(&self) -> &'static str {
+        match self {
+            DocumentPart::Body => "body",
+            DocumentPart::Styles => "header/footer",
+        }
+    }
+}
+
+/// Ein in der Vorlage gefundener Platzhalter samt Vorkommen pro Teil
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlaceholderInfo {
+    pub name: String,
+    pub count: usize,
+    pub parts: BTreeMap<DocumentPart, usize>,
+    /// Darf ohne Wert bleiben: Feld einer Bedingung (`{{#if KEY}}`) oder nur
+    /// innerhalb bedingter Abschnitte verwendet
+    pub optional: bool,
+}
+
+/// Ein einzelnes zu erzeugendes PDF für `OdfDocument::render_pdfs`
+#[derive(Debug, Clone)]
+pub struct RenderJob {
+    pub output: PathBuf,
+    pub replacements: HashMap<String, String>,
+}
 
 /// Repräsentiert ein ODF-Dokument
 pub struct OdfDocument {
     path: String,
+    missing_policy: MissingPolicy,
+    qr: QrOptions,
+    image_fit: ImageFit,
+    image_dir: PathBuf,
+    filters: FilterRegistry,
+    converter: Box<dyn PdfConverter>,
+    keep_going: bool,
 }
 
 impl OdfDocument {
@@ -23,9 +87,184 @@ impl OdfDocument {
         
         Ok(Self {
             path: path.to_string(),
+            missing_policy: MissingPolicy::default(),
+            qr: QrOptions::default(),
+            image_fit: ImageFit::default(),
+            image_dir: PathBuf::new(),
+            filters: FilterRegistry::new(),
+            converter: Box::new(SofficeConverter::new()),
+            keep_going: false,
         })
     }
 
+    /// Legt das Backend für die PDF-Konvertierung fest (Standard: lokales soffice)
+    pub fn with_converter(mut self, converter: Box<dyn PdfConverter>) -> Self {
+        self.converter = converter;
+        self
+    }
+
+    /// Legt fest, wie mit Platzhaltern ohne Wert umgegangen wird
+    pub fn with_missing_policy(mut self, policy: MissingPolicy) -> Self {
+        self.missing_policy = policy;
+        self
+    }
+
+    /// Legt Standardinhalt und Größe der QR-Code-Platzhalter fest
+    pub fn with_qr_options(mut self, options: QrOptions) -> Self {
+        self.qr = options;
+        self
+    }
+
+    /// Legt fest, wie Bilder in die Rahmen der Bildplatzhalter eingepasst werden
+    pub fn with_image_fit(mut self, fit: ImageFit) -> Self {
+        self.image_fit = fit;
+        self
+    }
+
+    /// Legt das Verzeichnis fest, ab dem relative Bildpfade aus den Daten gelten
+    /// (Standard: das aktuelle Verzeichnis)
+    pub fn with_image_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.image_dir = dir.into();
+        self
+    }
+
+    /// Legt die Filter für `{{KEY|filter}}` fest, z.B. die eingebauten plus eigene
+    pub fn with_filters(mut self, filters: FilterRegistry) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Legt fest, ob `render_pdfs` nach einem Fehler die übrigen Dokumente noch erzeugt
+    /// (Standard: nach dem ersten Fehler werden keine weiteren gestartet)
+    pub fn with_keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Pfad der Vorlage
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Einstellungen, die neben Vorlage und Daten das Ergebnis bestimmen (QR-Codes,
+    /// Bildeinpassung), als Text für einen Inhalts-Hash
+    pub fn render_settings(&self) -> String {
+        format!("qr={}|{} fit={}", self.qr.default_payload, self.qr.size, self.image_fit)
+    }
+
+    /// Schlüssel aller Bildplatzhalter der Vorlage
+    pub fn image_placeholders(&self) -> Result<BTreeSet<String>> {
+        let mut keys = BTreeSet::new();
+        for part in DocumentPart::ALL {
+            if let Some(content) = self.read_part(part)? {
+                keys.extend(image_frame_keys(&Self::clean_split_placeholders(&content)));
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Bilddatei zum Wert eines Bildplatzhalters (relative Pfade ab `with_image_dir`)
+    pub fn image_path(&self, value: &str) -> PathBuf {
+        self.image_dir.join(value)
+    }
+
+    /// Liest einen Teil (z.B. content.xml) aus dem Archiv, `None` wenn er fehlt
+    fn read_part(&self, part: DocumentPart) -> Result<Option<String>> {
+        let file = File::open(&self.path)?;
+        let mut archive = ZipArchive::new(file)?;
+
+        let mut entry = match archive.by_name(part.file_name()) {
+            Ok(entry) => entry,
+            Err(zip::result::ZipError::FileNotFound) => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut content = String::new();
+        entry.read_to_string(&mut content)?;
+        Ok(Some(content))
+    }
+
+    /// Listet alle Platzhalter der Vorlage (content.xml und styles.xml) mit Anzahl und Fundort auf.
+    /// Es wird dieselbe Bereinigung wie beim Befüllen angewendet, sodass die Namen
+    /// exakt den Keys entsprechen, die `CertificateData::to_replacements()` liefern muss.
+    pub fn placeholders(&self) -> Result<Vec<PlaceholderInfo>> {
+        use regex::Regex;
+
+        let re = Regex::new(r"\{\{([^{}]+)\}\}").unwrap();
+        let qr = qr_regex();
+        let conditions = condition_regex();
+        let loops = loop_regex();
+        let mut found: BTreeMap<String, PlaceholderInfo> = BTreeMap::new();
+
+        for part in DocumentPart::ALL {
+            let content = match self.read_part(part)? {
+                Some(content) => content,
+                None => continue,
+            };
+            let cleaned = Self::clean_split_placeholders(&content);
+
+            // Wiederholte Zeilen erscheinen unter dem Namen der Liste; ihre Felder gehören zum Listeneintrag
+            let mut occurrences: Vec<(String, bool)> = Vec::new();
+            let mut loop_elements = Vec::new();
+            for caps in loops.captures_iter(&cleaned) {
+                let marker = caps.get(0).unwrap();
+                occurrences.push((caps[1].to_string(), false));
+                loop_elements.extend(loop_element(&cleaned, &marker.range()));
+            }
+            let in_loop = |pos: usize| loop_elements.iter().any(|element| element.contains(&pos));
+
+            // Bedingungsfelder ({{#if KEY}}) und Platzhalter in bedingten Abschnitten dürfen fehlen
+            let mut markers: Vec<(usize, i32)> = Vec::new();
+            for caps in conditions.captures_iter(&cleaned) {
+                let start = caps.get(0).unwrap().start();
+                if in_loop(start) {
+                    continue;
+                }
+                match (caps.get(2), caps.get(4)) {
+                    (Some(key), _) => {
+                        markers.push((start, 1));
+                        occurrences.push((key.as_str().to_string(), true));
+                    }
+                    (None, Some(_)) => markers.push((start, -1)),
+                    (None, None) => markers.push((start, 0)),
+                }
+            }
+            let conditional = |pos: usize| {
+                let depth: i32 = markers.iter().filter(|(start, _)| *start < pos).map(|(_, delta)| delta).sum();
+                depth > 0
+            };
+
+            // QR-Codes (auch mit Feldreferenzen im Inhalt) werden unter einem Namen zusammengefasst
+            let qr_starts: Vec<usize> = qr.find_iter(&cleaned).map(|m| m.start()).collect();
+            occurrences.extend(qr_starts.iter().map(|&pos| (QR_PLACEHOLDER.to_string(), conditional(pos))));
+            for caps in re.captures_iter(&cleaned) {
+                let start = caps.get(0).unwrap().start();
+                if !in_loop(start) && !qr_starts.contains(&start) && !markers.iter().any(|(marker, _)| *marker == start) {
+                    // Bei Filtern zählt der Feldname; mit `default` darf der Wert fehlen
+                    let name = placeholder_key(&caps[1]).to_string();
+                    let has_default = caps[1].contains('|')
+                        && FilterExpression::parse(&caps[1]).is_ok_and(|expression| expression.has_default());
+                    occurrences.push((name, has_default || conditional(start)));
+                }
+            }
+
+            for (name, optional) in occurrences {
+                let info = found.entry(name.clone()).or_insert_with(|| PlaceholderInfo {
+                    name,
+                    count: 0,
+                    parts: BTreeMap::new(),
+                    optional: true,
+                });
+                info.count += 1;
+                info.optional &= optional;
+                *info.parts.entry(part).or_insert(0) += 1;
+            }
+        }
+
+        debug!("Found {} distinct placeholders in {}", found.len(), self.path);
+        Ok(found.into_values().collect())
+    }
+
     /// Entfernt XML-Tags aus Platzhaltern
     /// Wandelt: von {{</text:span><text:span>INSTRUCTOR</text:span><text:span>}}
     /// In: von {{INSTRUCTOR}}
@@ -48,8 +287,10 @@ impl OdfDocument {
             
             format!("{{{{{}}}}}", trimmed)
         });
-        
-        result.to_string()
+
+        // Übrig gebliebene Spans ohne Formatierung, die nur noch den Platzhalter umschließen, auflösen
+        let bare_span = Regex::new(r"<text:span>(\{\{[^{}<>]*\}\})</text:span>").unwrap();
+        bare_span.replace_all(&result, "$1").to_string()
     }
 
     /// Füllt das Dokument mit Daten und speichert es
@@ -60,14 +301,56 @@ impl OdfDocument {
     ) -> Result<()> {
         info!("Processing template: {}", self.path);
         info!("Output will be written to: {}", output_path);
-        
+
+        // Keine halb geschriebenen Dateien zurücklassen (z.B. bei MissingPolicy::Error)
+        if let Err(e) = self.write_filled(output_path, replacements) {
+            let _ = fs::remove_file(output_path);
+            return Err(e);
+        }
+
+        info!("Successfully created: {}", output_path);
+        Ok(())
+    }
+
+    fn write_filled(
+        &self,
+        output_path: &str,
+        replacements: &HashMap<String, String>,
+    ) -> Result<()> {
         let file = File::open(&self.path)?;
         let mut archive = ZipArchive::new(file)?;
         
         let output_file = File::create(output_path)?;
         let mut output_archive = ZipWriter::new(output_file);
         
-        let replacer = PlaceholderReplacer::new();
+        let replacer = PlaceholderReplacer::new()
+            .with_missing_policy(self.missing_policy.clone())
+            .with_filters(self.filters.clone());
+
+        // Zuerst die Textteile befüllen; dabei entstehen ggf. Bilder (Bildplatzhalter,
+        // QR-Codes), die unter Pictures/ abgelegt und im Manifest eingetragen werden müssen
+        let mut images: Vec<EmbeddedImage> = Vec::new();
+        let mut filled_parts: HashMap<&str, String> = HashMap::new();
+        for part in DocumentPart::ALL {
+            let mut content = String::new();
+            match archive.by_name(part.file_name()) {
+                Ok(mut entry) => entry.read_to_string(&mut content)?,
+                Err(zip::result::ZipError::FileNotFound) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            // ERST: XML-Tags aus Platzhaltern entfernen
+            debug!("Cleaning split placeholders in {}...", part.file_name());
+            let cleaned = Self::clean_split_placeholders(&content);
+
+            // DANN: Listen wiederholen, bedingte Abschnitte auswerten, Bilder einsetzen, QR-Codes erzeugen und Replacements durchführen (mit XML-Escaping)
+            let expanded = expand_loops(&cleaned, replacements, &replacer)?;
+            let visible = apply_conditions(&expanded, replacements)?;
+            let with_images =
+                replace_image_frames(&visible, replacements, self.image_fit, &self.image_dir, &replacer, &mut images)?;
+            let with_qr = replace_qr_codes(&with_images, replacements, &self.qr, &self.missing_policy, &mut images)?;
+            filled_parts.insert(part.file_name(), replacer.replace_all(&with_qr, replacements)?);
+        }
         
         // WICHTIG: mimetype MUSS als erstes kommen und UNKOMPRIMIERT sein!
         if let Ok(mut mimetype_file) = archive.by_name("mimetype") {
@@ -93,23 +376,22 @@ impl OdfDocument {
             
             debug!("Processing file: {}", filename);
             
-            // content.xml und styles.xml können Text enthalten
-            if filename == "content.xml" || filename == "styles.xml" {
-                let mut content = String::new();
-                file.read_to_string(&mut content)?;
-                
-                // ERST: XML-Tags aus Platzhaltern entfernen
-                debug!("Cleaning split placeholders...");
-                let cleaned = Self::clean_split_placeholders(&content);
-                
-                // DANN: Replacements durchführen (mit XML-Escaping)
-                let replaced = replacer.replace_all(&cleaned, replacements);
-                
+            // content.xml und styles.xml wurden oben befüllt
+            if let Some(filled) = filled_parts.get(filename.as_str()) {
                 let options = FileOptions::default()
                     .compression_method(CompressionMethod::Deflated);
                 
                 output_archive.start_file(&filename, options)?;
-                output_archive.write_all(replaced.as_bytes())?;
+                output_archive.write_all(filled.as_bytes())?;
+            } else if filename == "META-INF/manifest.xml" && !images.is_empty() {
+                let mut manifest = String::new();
+                file.read_to_string(&mut manifest)?;
+
+                let options = FileOptions::default()
+                    .compression_method(CompressionMethod::Deflated);
+
+                output_archive.start_file(&filename, options)?;
+                output_archive.write_all(register_in_manifest(&manifest, &images).as_bytes())?;
             } else {
                 // Andere Dateien 1:1 kopieren mit Original-Kompression
                 let compression = file.compression();
@@ -123,68 +405,178 @@ impl OdfDocument {
                 output_archive.write_all(&buffer)?;
             }
         }
+
+        for image in &images {
+            debug!("Embedding image: {}", image.path);
+            let options = FileOptions::default()
+                .compression_method(CompressionMethod::Deflated);
+
+            output_archive.start_file(&image.path, options)?;
+            output_archive.write_all(&image.data)?;
+        }
         
         output_archive.finish()?;
-        info!("Successfully created: {}", output_path);
-        
         Ok(())
     }
 
-    /// Füllt das Dokument, speichert zunächst als .odt, konvertiert per LibreOffice (soffice) nach PDF und löscht die .odt
+    /// Füllt das Dokument, speichert zunächst als .odt, konvertiert per `PdfConverter` (Standard: soffice) nach PDF und löscht die .odt
     pub fn fill_and_save_pdf(
         &self,
         output_pdf_path: &str,
         replacements: &HashMap<String, String>,
     ) -> Result<()> {
         let pdf_path = Path::new(output_pdf_path);
-        let odt_path = pdf_path.with_extension("odt");
+        self.fill_and_convert(&pdf_path.with_extension("odt"), pdf_path, replacements, None)
+    }
 
+    /// Befüllt `odt_path`, konvertiert sie im selben Verzeichnis mit einem privaten
+    /// Arbeitsverzeichnis des Workers (z.B. eigenes LibreOffice-Profil bei parallelem
+    /// Rendern), verschiebt die PDF nach `pdf_path` und löscht die .odt
+    fn fill_and_convert(
+        &self,
+        odt_path: &Path,
+        pdf_path: &Path,
+        replacements: &HashMap<String, String>,
+        workdir: Option<&Path>,
+    ) -> Result<()> {
         // 1) Erzeuge .odt
-        self.fill_and_save(odt_path.to_str().unwrap(), replacements)?;
-
-        // 2) Prüfe ob soffice verfügbar ist
-        if which("soffice").is_err() {
-            // Statt eines nicht-existierenden CertgenError::Generic verwenden wir ein std::io::Error
-            // und konvertieren dieses in CertgenError via bestehende From-Implementierung.
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "LibreOffice (soffice) nicht im PATH gefunden. Bitte installiere LibreOffice oder sorge dafür, dass 'soffice' im PATH liegt."
-            ).into());
-        }
-
-        // 3) Konvertiere .odt -> .pdf via soffice (LibreOffice)
-        let outdir = pdf_path.parent().unwrap_or_else(|| Path::new("."));
-        let status = Command::new("soffice")
-            .arg("--headless")
-            .arg("--convert-to")
-            .arg("pdf")
-            .arg("--outdir")
-            .arg(outdir)
-            .arg(&odt_path)
-            .status()?;
-
-        if !status.success() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("LibreOffice-Konvertierung schlug fehl (exit: {:?}).", status.code())
-            ).into());
-        }
-
-        // LibreOffice schreibt <basename>.pdf in outdir
-        let generated_pdf = outdir.join(odt_path.file_stem().unwrap()).with_extension("pdf");
-        if generated_pdf != pdf_path {
-            fs::rename(&generated_pdf, &pdf_path)?;
-        }
+        self.fill_and_save(&odt_path.to_string_lossy(), replacements)?;
+
+        // 2) Konvertiere .odt -> .pdf
+        let outdir = odt_path.parent().unwrap_or_else(|| Path::new("."));
+        let converted = self.converter.convert(&[odt_path], outdir, workdir);
 
-        // 4) entferne temporäre .odt
+        // 3) entferne temporäre .odt
         if odt_path.exists() {
-            fs::remove_file(&odt_path)?;
+            fs::remove_file(odt_path)?;
         }
+        converted?;
 
-        info!("Successfully created PDF: {}", output_pdf_path);
+        // Der Konverter schreibt <basename>.pdf in outdir
+        let generated_pdf = pdf_output_path(odt_path, outdir);
+        if generated_pdf != pdf_path {
+            move_file(&generated_pdf, pdf_path)?;
+        }
+
+        info!("Successfully created PDF: {}", pdf_path.display());
         Ok(())
     }
 
+    /// Erzeugt mehrere PDFs mit `workers` parallelen Konvertierungen. Jeder Worker bekommt
+    /// ein eigenes Arbeitsverzeichnis (bei soffice: eigenes Profil); die .odt-Dateien liegen
+    /// nach Job-Index benannt in einem Staging-Verzeichnis, sodass sich Jobs mit gleichem
+    /// Ausgabenamen nicht gegenseitig überschreiben. Das Ergebnis enthält
+    /// pro Job ein `Result` mit der Dauer der Erzeugung in derselben Reihenfolge wie `jobs`. Nach dem ersten
+    /// Fehler werden ohne `with_keep_going` keine weiteren Jobs mehr gestartet; nicht
+    /// gestartete Jobs fehlen im Ergebnis (es ist dann kürzer als `jobs`).
+    pub fn render_pdfs(&self, jobs: &[RenderJob], workers: usize) -> Vec<Result<Duration>> {
+        if workers > 1 {
+            info!("Rendering {} documents with {} parallel workers", jobs.len(), workers);
+        }
+
+        let staging = match create_staging_dir() {
+            Ok(staging) => staging,
+            Err(e) => return vec![Err(e)],
+        };
+
+        let results = run_pool(jobs.len(), workers, self.keep_going, |idx, workdir| {
+            let job = &jobs[idx];
+            let started = Instant::now();
+            let odt_path = staging.join(format!("certgen-{:06}.odt", idx));
+            self.fill_and_convert(&odt_path, &job.output, &job.replacements, workdir)
+                .map(|()| started.elapsed())
+        });
+
+        let _ = fs::remove_dir_all(&staging);
+
+        results.into_iter().map_while(|result| result).collect()
+    }
+
+    /// Wie `render_pdfs`, aber alle .odt-Dateien werden zuerst in ein Staging-Verzeichnis
+    /// geschrieben und dann mit einem einzigen Konverter-Aufruf konvertiert (bzw. einem Aufruf
+    /// pro `chunk_size` Dokumente, 0 = alle auf einmal). Die Chunks werden auf `workers`
+    /// parallele Worker verteilt. Anschließend werden die PDFs an ihr Ziel verschoben.
+    /// Die Dauer je Dokument enthält das Befüllen und den Anteil an der Konvertierung des Chunks.
+    pub fn render_pdfs_bulk(
+        &self,
+        jobs: &[RenderJob],
+        chunk_size: usize,
+        workers: usize,
+    ) -> Vec<Result<Duration>> {
+        let staging = match create_staging_dir() {
+            Ok(staging) => staging,
+            Err(e) => return vec![Err(e)],
+        };
+
+        let staged_odt = |idx: usize| staging.join(format!("certgen-{:06}.odt", idx));
+
+        // 1) Alle .odt-Dateien befüllen; beim ersten Fehler nur die bisherigen konvertieren
+        let mut results: Vec<Result<Duration>> = Vec::with_capacity(jobs.len());
+        for (idx, job) in jobs.iter().enumerate() {
+            let started = Instant::now();
+            let result = self
+                .fill_and_save(&staged_odt(idx).to_string_lossy(), &job.replacements)
+                .map(|()| started.elapsed());
+            let failed = result.is_err();
+            results.push(result);
+            if failed && !self.keep_going {
+                break;
+            }
+        }
+
+        let filled: Vec<usize> = results
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.is_ok())
+            .map(|(idx, _)| idx)
+            .collect();
+        let chunk_size = if chunk_size == 0 { filled.len().max(1) } else { chunk_size };
+        let chunks: Vec<&[usize]> = filled.chunks(chunk_size).collect();
+
+        info!(
+            "Converting {} documents in {} {} invocation(s)",
+            filled.len(),
+            chunks.len(),
+            self.converter.name()
+        );
+
+        // 2) Konvertierung pro Chunk
+        let conversions = run_pool(chunks.len(), workers, self.keep_going, |chunk_idx, workdir| {
+            let files: Vec<PathBuf> = chunks[chunk_idx].iter().map(|&idx| staged_odt(idx)).collect();
+            let paths: Vec<&Path> = files.iter().map(PathBuf::as_path).collect();
+            let started = Instant::now();
+            self.converter.convert(&paths, &staging, workdir)?;
+            Ok(started.elapsed() / files.len() as u32)
+        });
+
+        // 3) Ergebnisse den Jobs zuordnen und PDFs an ihr Ziel verschieben
+        let mut converted: Vec<Option<Result<Duration>>> = results.into_iter().map(Some).collect();
+        for (chunk, conversion) in chunks.iter().zip(conversions) {
+            for &idx in chunk.iter() {
+                let filled = converted[idx].take().and_then(|r| r.ok()).unwrap_or_default();
+                converted[idx] = match &conversion {
+                    None => None,
+                    Some(Err(e)) => Some(Err(std::io::Error::other(format!(
+                        "Bulk-Konvertierung schlug fehl: {}",
+                        e
+                    ))
+                    .into())),
+                    Some(Ok(share)) => {
+                        let pdf = pdf_output_path(&staged_odt(idx), &staging);
+                        Some(move_file(&pdf, &jobs[idx].output).map(|()| {
+                            info!("Successfully created PDF: {}", jobs[idx].output.display());
+                            filled + *share
+                        }))
+                    }
+                };
+            }
+        }
+
+        let _ = fs::remove_dir_all(&staging);
+
+        converted.into_iter().map_while(|result| result).collect()
+    }
+
     /// Batch-Verarbeitung: Mehrere Dokumente aus einer Liste erstellen (ODT)
     pub fn batch_fill(
         &self,
@@ -210,31 +602,467 @@ impl OdfDocument {
         output_dir: &str,
         batch_data: Vec<(String, HashMap<String, String>)>,
     ) -> Result<Vec<String>> {
+        let jobs = Self::pdf_jobs(output_dir, batch_data)?;
+        Self::collect_created(&jobs, self.render_pdfs(&jobs, 1))
+    }
+
+    /// Wie `batch_fill_pdf`, konvertiert aber alle Dokumente mit einem Konverter-Aufruf
+    /// (bzw. einem pro `chunk_size` Dokumente, 0 = alle auf einmal)
+    pub fn batch_fill_pdf_bulk(
+        &self,
+        output_dir: &str,
+        batch_data: Vec<(String, HashMap<String, String>)>,
+        chunk_size: usize,
+    ) -> Result<Vec<String>> {
+        let jobs = Self::pdf_jobs(output_dir, batch_data)?;
+        Self::collect_created(&jobs, self.render_pdfs_bulk(&jobs, chunk_size, 1))
+    }
+
+    fn pdf_jobs(
+        output_dir: &str,
+        batch_data: Vec<(String, HashMap<String, String>)>,
+    ) -> Result<Vec<RenderJob>> {
         std::fs::create_dir_all(output_dir)?;
-        
+
+        Ok(batch_data
+            .into_iter()
+            .map(|(filename, replacements)| RenderJob {
+                // Stelle sicher, dass die Dateiendung .pdf ist
+                output: Path::new(output_dir).join(filename).with_extension("pdf"),
+                replacements,
+            })
+            .collect())
+    }
+
+    fn collect_created(jobs: &[RenderJob], results: Vec<Result<Duration>>) -> Result<Vec<String>> {
         let mut created_files = Vec::new();
-        
-        for (filename, replacements) in batch_data {
-            let output_path = Path::new(output_dir).join(filename);
-            // Stelle sicher, dass die Dateiendung .pdf ist
-            let pdf_path = if output_path.extension().is_some() {
-                output_path.with_extension("pdf")
-            } else {
-                output_path.with_extension("pdf")
-            };
-            let pdf_str = pdf_path.to_str().unwrap().to_string();
-            self.fill_and_save_pdf(&pdf_str, &replacements)?;
-            created_files.push(pdf_str);
+        for (job, result) in jobs.iter().zip(results) {
+            result?;
+            created_files.push(job.output.to_str().unwrap().to_string());
         }
-        
         Ok(created_files)
     }
 }
 
+/// Zähler für eindeutige Staging-Verzeichnisse innerhalb eines Prozesses
+static STAGING_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Legt ein neues, leeres Staging-Verzeichnis für die .odt-Dateien eines Laufs an
+fn create_staging_dir() -> Result<PathBuf> {
+    let staging = std::env::temp_dir().join(format!(
+        "certgen-staging-{}-{}",
+        std::process::id(),
+        STAGING_COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    fs::create_dir_all(&staging)?;
+    Ok(staging)
+}
+
+/// Verteilt `count` Aufgaben auf `workers` Threads, jeder mit eigenem Arbeitsverzeichnis
+/// (z.B. für ein LibreOffice-Profil). Mit nur einem Worker läuft alles sequentiell ohne.
+/// Nach dem ersten Fehler werden ohne `keep_going` keine neuen Aufgaben gestartet; nicht
+/// gestartete Aufgaben bleiben `None`.
+fn run_pool<T, F>(count: usize, workers: usize, keep_going: bool, task: F) -> Vec<Option<Result<T>>>
+where
+    T: Send,
+    F: Fn(usize, Option<&Path>) -> Result<T> + Sync,
+{
+    let workers = workers.clamp(1, count.max(1));
+    let results: Mutex<Vec<Option<Result<T>>>> = Mutex::new((0..count).map(|_| None).collect());
+
+    if workers == 1 {
+        let mut results = results.into_inner().unwrap();
+        for (idx, slot) in results.iter_mut().enumerate() {
+            let result = task(idx, None);
+            let failed = result.is_err();
+            *slot = Some(result);
+            if failed && !keep_going {
+                break;
+            }
+        }
+        return results;
+    }
+
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+
+    std::thread::scope(|scope| {
+        for worker in 0..workers {
+            let (next, failed, results, task) = (&next, &failed, &results, &task);
+            scope.spawn(move || {
+                let workdir = std::env::temp_dir()
+                    .join(format!("certgen-worker-{}-{}", std::process::id(), worker));
+
+                loop {
+                    if failed.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let idx = next.fetch_add(1, Ordering::SeqCst);
+                    if idx >= count {
+                        break;
+                    }
+
+                    debug!("Worker {} runs task {}", worker, idx);
+                    let result = task(idx, Some(&workdir));
+                    if result.is_err() && !keep_going {
+                        failed.store(true, Ordering::SeqCst);
+                    }
+                    results.lock().unwrap()[idx] = Some(result);
+                }
+
+                let _ = fs::remove_dir_all(&workdir);
+            });
+        }
+    });
+
+    results.into_inner().unwrap()
+}
+
+/// Verschiebt eine Datei; fällt auf Kopieren + Löschen zurück, wenn `rename`
+/// scheitert (z.B. weil Quelle und Ziel auf verschiedenen Dateisystemen liegen)
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("Konverter hat keine PDF erzeugt: {}", from.display()),
+        )
+        .into());
+    }
+    if fs::rename(from, to).is_err() {
+        fs::copy(from, to)?;
+        fs::remove_file(from)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Schreibt ein minimales ODT-Paket mit den angegebenen XML-Teilen
+    pub(crate) fn write_test_odt(path: &Path, content_xml: &str, styles_xml: &str) {
+        let file = File::create(path).unwrap();
+        let mut zip = ZipWriter::new(file);
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        zip.start_file("mimetype", stored).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.text").unwrap();
+        zip.start_file("META-INF/manifest.xml", FileOptions::default()).unwrap();
+        zip.write_all(
+            concat!(
+                r#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0">"#,
+                r#"<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>"#,
+                "</manifest:manifest>"
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        zip.start_file("content.xml", FileOptions::default()).unwrap();
+        zip.write_all(content_xml.as_bytes()).unwrap();
+        zip.start_file("styles.xml", FileOptions::default()).unwrap();
+        zip.write_all(styles_xml.as_bytes()).unwrap();
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_placeholders_counts_per_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vorlage.odt");
+        write_test_odt(
+            &path,
+            r#"<text:p>{{NAME}} von {{</text:span><text:span>INSTRUCTOR</text:span>}} - {{NAME}}</text:p>"#,
+            r#"<style:footer><text:p>{{TITLE}} {{NAME}}</text:p></style:footer>"#,
+        );
+
+        let doc = OdfDocument::open(path.to_str().unwrap()).unwrap();
+        let placeholders = doc.placeholders().unwrap();
+        let names: Vec<&str> = placeholders.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["INSTRUCTOR", "NAME", "TITLE"]);
+
+        let name = &placeholders[1];
+        assert_eq!(name.count, 3);
+        assert_eq!(name.parts.get(&DocumentPart::Body), Some(&2));
+        assert_eq!(name.parts.get(&DocumentPart::Styles), Some(&1));
+
+        let title = &placeholders[2];
+        assert_eq!(title.parts.get(&DocumentPart::Body), None);
+    }
+
+    #[test]
+    fn test_filtered_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vorlage.odt");
+        write_test_odt(
+            &path,
+            r#"<text:p>{{NAME|upper}} {{NAME}} {{HOURS|default:&quot;8&quot;}}</text:p>"#,
+            "<office:styles/>",
+        );
+
+        let doc = OdfDocument::open(path.to_str().unwrap()).unwrap();
+        let placeholders = doc.placeholders().unwrap();
+        let names: Vec<(&str, usize, bool)> = placeholders
+            .iter()
+            .map(|p| (p.name.as_str(), p.count, p.optional))
+            .collect();
+        assert_eq!(names, vec![("HOURS", 1, true), ("NAME", 2, false)]);
+    }
+
+    #[test]
+    fn test_date_filter_on_raw_dates() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        let output = dir.path().join("ausgabe.odt");
+        write_test_odt(
+            &template,
+            r#"<text:p>{{DATE_FROM|date:&quot;%d. %B&quot;}} - {{DATE_TO|date:&quot;%d.%m.%Y&quot;}}</text:p>"#,
+            "<office:styles/>",
+        );
+
+        let mut data = crate::template::CertificateData::new(
+            "Max".to_string(),
+            chrono::NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+            String::new(),
+        );
+        data.date_from = chrono::NaiveDate::from_ymd_opt(2024, 1, 10);
+        data.date_to = chrono::NaiveDate::from_ymd_opt(2024, 1, 15);
+
+        let doc = OdfDocument::open(template.to_str().unwrap())
+            .unwrap()
+            .with_missing_policy(MissingPolicy::Error);
+        doc.fill_and_save(output.to_str().unwrap(), &data.to_replacements()).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut content = String::new();
+        archive.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "<text:p>10. Januar - 15.01.2024</text:p>");
+    }
+
+    #[test]
+    fn test_qr_code_is_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        let output = dir.path().join("ausgabe.odt");
+        write_test_odt(
+            &template,
+            r#"<text:p>{{NAME}}</text:p><text:p>{{QR:https://example.com/v/{CERT_ID}}}</text:p>"#,
+            r#"<style:footer><text:p>{{QR}}</text:p></style:footer>"#,
+        );
+
+        let doc = OdfDocument::open(template.to_str().unwrap()).unwrap();
+        let names: Vec<String> = doc.placeholders().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["NAME", "QR"]);
+
+        let replacements = HashMap::from([
+            ("NAME".to_string(), "Max".to_string()),
+            ("CERT_ID".to_string(), "B1-2024-000001".to_string()),
+        ]);
+        doc.fill_and_save(output.to_str().unwrap(), &replacements).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+        assert!(read("content.xml").contains(r#"xlink:href="Pictures/certgen-qr-1.svg""#));
+        assert!(read("styles.xml").contains(r#"xlink:href="Pictures/certgen-qr-2.svg""#));
+        let manifest = read("META-INF/manifest.xml");
+        assert!(manifest.contains(r#"manifest:full-path="Pictures/certgen-qr-1.svg""#));
+        assert!(manifest.contains(r#"manifest:full-path="Pictures/certgen-qr-2.svg""#));
+        assert!(read("Pictures/certgen-qr-2.svg").contains("<svg"));
+    }
+
+    #[test]
+    fn test_image_placeholder_is_embedded() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        let output = dir.path().join("ausgabe.odt");
+        let signature = dir.path().join("unterschrift.svg");
+        fs::write(&signature, r#"<svg xmlns="http://www.w3.org/2000/svg" width="200" height="50"/>"#).unwrap();
+        write_test_odt(
+            &template,
+            concat!(
+                r#"<text:p>{{NAME}}<draw:frame draw:name="Bild1" svg:width="5cm" svg:height="5cm">"#,
+                r#"<draw:image xlink:href="Pictures/platzhalter.png"/><svg:title>{{SIGNATURE}}</svg:title></draw:frame></text:p>"#
+            ),
+            "<office:styles/>",
+        );
+
+        let doc = OdfDocument::open(template.to_str().unwrap())
+            .unwrap()
+            .with_image_fit(ImageFit::Contain);
+        let names: Vec<String> = doc.placeholders().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["NAME", "SIGNATURE"]);
+        assert_eq!(doc.image_placeholders().unwrap(), BTreeSet::from(["SIGNATURE".to_string()]));
+
+        let replacements = HashMap::from([
+            ("NAME".to_string(), "Max".to_string()),
+            ("SIGNATURE".to_string(), signature.to_string_lossy().to_string()),
+        ]);
+        doc.fill_and_save(output.to_str().unwrap(), &replacements).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut read = |name: &str| {
+            let mut content = String::new();
+            archive.by_name(name).unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+        let content = read("content.xml");
+        assert!(content.contains(r#"xlink:href="Pictures/certgen-img-1.svg""#));
+        assert!(content.contains(r#"svg:width="5.0000cm" svg:height="1.2500cm""#));
+        assert!(content.contains("<svg:title>SIGNATURE</svg:title>"));
+        assert!(read("META-INF/manifest.xml").contains(r#"manifest:full-path="Pictures/certgen-img-1.svg""#));
+        assert!(read("Pictures/certgen-img-1.svg").contains("<svg"));
+    }
+
+    #[test]
+    fn test_conditional_sections() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        let output = dir.path().join("ausgabe.odt");
+        write_test_odt(
+            &template,
+            concat!(
+                "<text:p>{{NAME}}</text:p>",
+                "<text:p>{{#if </text:span><text:span>EXAM_PASSED}}</text:p>",
+                "<text:p>Prüfung bestanden am {{EXAM_DATE}}</text:p>",
+                "<text:p>{{/if}}</text:p>"
+            ),
+            "<office:styles/>",
+        );
+
+        let doc = OdfDocument::open(template.to_str().unwrap()).unwrap();
+        let placeholders = doc.placeholders().unwrap();
+        let names: Vec<(&str, bool)> = placeholders.iter().map(|p| (p.name.as_str(), p.optional)).collect();
+        assert_eq!(names, vec![("EXAM_DATE", true), ("EXAM_PASSED", true), ("NAME", false)]);
+
+        let read_content = |path: &Path| {
+            let mut archive = ZipArchive::new(File::open(path).unwrap()).unwrap();
+            let mut content = String::new();
+            archive.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+            content
+        };
+
+        let passed = HashMap::from([
+            ("NAME".to_string(), "Max".to_string()),
+            ("EXAM_PASSED".to_string(), "true".to_string()),
+            ("EXAM_DATE".to_string(), "15.01.2024".to_string()),
+        ]);
+        doc.fill_and_save(output.to_str().unwrap(), &passed).unwrap();
+        assert_eq!(
+            read_content(&output),
+            "<text:p>Max</text:p><text:p>Prüfung bestanden am 15.01.2024</text:p>"
+        );
+
+        // Ohne EXAM_PASSED verschwindet der Abschnitt samt seiner Platzhalter, auch bei MissingPolicy::Error
+        let doc = doc.with_missing_policy(MissingPolicy::Error);
+        let attended = HashMap::from([("NAME".to_string(), "Erika".to_string())]);
+        doc.fill_and_save(output.to_str().unwrap(), &attended).unwrap();
+        assert_eq!(read_content(&output), "<text:p>Erika</text:p>");
+    }
+
+    #[test]
+    fn test_agenda_table_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        let output = dir.path().join("ausgabe.odt");
+        write_test_odt(
+            &template,
+            concat!(
+                "<table:table><table:table-row>",
+                "<table:table-cell><text:p>{{#each AGENDA}}{{module}}</text:p></table:table-cell>",
+                "<table:table-cell><text:p>{{hours}}</text:p></table:table-cell>",
+                "</table:table-row></table:table><text:p>{{NAME}}</text:p>"
+            ),
+            "<office:styles/>",
+        );
+
+        let doc = OdfDocument::open(template.to_str().unwrap())
+            .unwrap()
+            .with_missing_policy(MissingPolicy::Error);
+        let names: Vec<String> = doc.placeholders().unwrap().into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["AGENDA", "NAME"]);
+
+        let replacements = HashMap::from([
+            ("NAME".to_string(), "Max".to_string()),
+            (
+                crate::odf::list_key("AGENDA"),
+                r#"[{"module":"Grundlagen","hours":8},{"module":"Praxis","hours":16}]"#.to_string(),
+            ),
+        ]);
+        doc.fill_and_save(output.to_str().unwrap(), &replacements).unwrap();
+
+        let mut archive = ZipArchive::new(File::open(&output).unwrap()).unwrap();
+        let mut content = String::new();
+        archive.by_name("content.xml").unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!(content.matches("<table:table-row>").count(), 2);
+        assert!(content.contains("<text:p>Praxis</text:p></table:table-cell><table:table-cell><text:p>16</text:p>"));
+    }
+
+    #[test]
+    fn test_render_pdfs_with_same_output_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("vorlage.odt");
+        write_test_odt(&template, "<text:p>{{NAME}}</text:p>", "<office:document-styles/>");
+
+        let converter = crate::convert::CommandConverter::new("cp {input} {output}").unwrap();
+        let doc = OdfDocument::open(template.to_str().unwrap())
+            .unwrap()
+            .with_converter(Box::new(converter));
+        let output = dir.path().join("Max.pdf");
+        let jobs: Vec<RenderJob> = (0..4)
+            .map(|idx| RenderJob {
+                output: output.clone(),
+                replacements: HashMap::from([("NAME".to_string(), format!("Max {}", idx))]),
+            })
+            .collect();
+
+        let results = doc.render_pdfs(&jobs, 4);
+        assert_eq!(results.len(), 4);
+        assert!(results.iter().all(Result::is_ok));
+        assert!(output.exists());
+        assert!(!dir.path().join("Max.odt").exists());
+    }
+
+    #[test]
+    fn test_run_pool_keeps_order() {
+        let results = run_pool(20, 4, false, |idx, workdir| {
+            assert!(workdir.is_some());
+            Ok(idx * 2)
+        });
+        let values: Vec<usize> = results.into_iter().map(|r| r.unwrap().unwrap()).collect();
+        assert_eq!(values, (0..20).map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_run_pool_stops_after_error() {
+        let results = run_pool(5, 1, false, |idx, _| {
+            if idx == 2 {
+                Err(CertgenError::InvalidTemplate)
+            } else {
+                Ok(idx)
+            }
+        });
+        assert!(results[1].as_ref().unwrap().is_ok());
+        assert!(results[2].as_ref().unwrap().is_err());
+        assert!(results[3].is_none());
+    }
+
+    #[test]
+    fn test_run_pool_keep_going() {
+        for workers in [1, 3] {
+            let results = run_pool(6, workers, true, |idx, _| {
+                if idx % 2 == 0 {
+                    Err(CertgenError::InvalidTemplate)
+                } else {
+                    Ok(idx)
+                }
+            });
+            let failed = results.iter().filter(|r| matches!(r, Some(Err(_)))).count();
+            let succeeded = results.iter().filter(|r| matches!(r, Some(Ok(_)))).count();
+            assert_eq!((failed, succeeded), (3, 3));
+        }
+    }
+
     #[test]
     fn test_clean_split_placeholders() {
         let input = r#"text <text:span>{{</text:span><text:span>NAME</text:span><text:span>}}</text:span> more"#;
@@ -0,0 +1,389 @@
+//! Filter für Platzhalter: `{{NAME|upper}}`, `{{DATE|date:"%d. %B %Y"}}`,
+//! `{{HOURS|default:"8"}}`, `{{TITLE|truncate:60}}`, `{{SCORE|number:"0.0"}}`.
+//!
+//! Filter werden von links nach rechts angewendet. Argumente folgen nach `:` und
+//! werden durch `,` getrennt; Text mit Leerzeichen oder Kommas steht in Anführungszeichen.
+//! Typografische Anführungszeichen, die LibreOffice beim Tippen einsetzt, sind erlaubt.
+
+use crate::error::{CertgenError, Result};
+use crate::odf::replacer::PlaceholderReplacer;
+use crate::template::dates::{format_localized, parse_date, Locale};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// Ein Filter erhält den bisherigen Wert (`None`, wenn das Feld fehlt) und seine
+/// Argumente und liefert den neuen Wert oder eine Fehlermeldung
+pub type FilterFn = dyn Fn(Option<&str>, &[String]) -> std::result::Result<Option<String>, String> + Send + Sync;
+
+/// Verfügbare Filter, nach Namen
+#[derive(Clone)]
+pub struct FilterRegistry {
+    filters: HashMap<String, Arc<FilterFn>>,
+}
+
+impl FilterRegistry {
+    /// Registry mit den eingebauten Filtern `upper`, `lower`, `trim`, `default`,
+    /// `truncate`, `date` und `number`
+    pub fn new() -> Self {
+        let mut registry = Self::empty();
+        registry.register("upper", |value, _| Ok(value.map(str::to_uppercase)));
+        registry.register("lower", |value, _| Ok(value.map(str::to_lowercase)));
+        registry.register("trim", |value, _| Ok(value.map(|v| v.trim().to_string())));
+        registry.register("default", default_filter);
+        registry.register("truncate", truncate_filter);
+        registry.register("date", date_filter);
+        registry.register("number", number_filter);
+        registry
+    }
+
+    /// Registry ohne Filter
+    pub fn empty() -> Self {
+        Self {
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Registriert einen Filter; ein vorhandener Filter gleichen Namens wird ersetzt
+    pub fn register<F>(&mut self, name: &str, filter: F) -> &mut Self
+    where
+        F: Fn(Option<&str>, &[String]) -> std::result::Result<Option<String>, String> + Send + Sync + 'static,
+    {
+        self.filters.insert(name.to_string(), Arc::new(filter));
+        self
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    /// Prüft, dass alle Filter eines Platzhalters registriert sind
+    pub fn check(&self, expression: &FilterExpression) -> Result<()> {
+        match expression.filters.iter().find(|call| !self.contains(&call.name)) {
+            Some(call) => Err(unknown_filter(&call.name, expression)),
+            None => Ok(()),
+        }
+    }
+
+    /// Wendet die Filterkette eines Platzhalters auf `value` an
+    pub fn apply(&self, expression: &FilterExpression, value: Option<&str>) -> Result<Option<String>> {
+        let mut current = value.map(str::to_string);
+        for call in &expression.filters {
+            let filter = self
+                .filters
+                .get(&call.name)
+                .ok_or_else(|| unknown_filter(&call.name, expression))?;
+            current = filter(current.as_deref(), &call.args)
+                .map_err(|e| CertgenError::Filter(format!("{{{{{}}}}}: {}", expression, e)))?;
+        }
+        Ok(current)
+    }
+}
+
+fn unknown_filter(name: &str, expression: &FilterExpression) -> CertgenError {
+    CertgenError::Filter(format!("unknown filter `{}` in {{{{{}}}}}", name, expression))
+}
+
+impl Default for FilterRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FilterRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.filters.keys().collect();
+        names.sort();
+        f.debug_struct("FilterRegistry").field("filters", &names).finish()
+    }
+}
+
+/// Ein Filteraufruf, z.B. `truncate:60`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterCall {
+    pub name: String,
+    pub args: Vec<String>,
+}
+
+/// Inhalt eines Platzhalters mit Filtern, z.B. `TITLE|truncate:60|upper`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilterExpression {
+    pub key: String,
+    pub filters: Vec<FilterCall>,
+    /// Ursprünglicher Text zwischen `{{` und `}}` (für Meldungen)
+    source: String,
+}
+
+impl FilterExpression {
+    /// Zerlegt den Inhalt eines Platzhalters; XML-Entities werden dabei aufgelöst
+    pub fn parse(inner: &str) -> Result<Self> {
+        let source = inner.trim().to_string();
+        let text = PlaceholderReplacer::unescape_xml(&source);
+        let mut parts = split_outside_quotes(&text, '|').into_iter();
+        let key = parts.next().unwrap_or_default().trim().to_string();
+        if key.is_empty() {
+            return Err(CertgenError::Filter(format!("missing field name in {{{{{}}}}}", source)));
+        }
+
+        let filters = parts
+            .map(|part| {
+                let (name, args) = match part.split_once(':') {
+                    Some((name, args)) => (name, split_outside_quotes(args, ',')),
+                    None => (part.as_str(), Vec::new()),
+                };
+                let name = name.trim().to_string();
+                if name.is_empty() {
+                    return Err(CertgenError::Filter(format!("empty filter in {{{{{}}}}}", source)));
+                }
+                Ok(FilterCall {
+                    name,
+                    args: args.iter().map(|arg| unquote(arg.trim())).collect(),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self { key, filters, source })
+    }
+
+    /// Ein Platzhalter mit `default` darf ohne Wert bleiben
+    pub fn has_default(&self) -> bool {
+        self.filters.iter().any(|call| call.name == "default")
+    }
+}
+
+impl fmt::Display for FilterExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+/// Feldname eines Platzhalters (ohne Filter)
+pub(crate) fn placeholder_key(inner: &str) -> &str {
+    inner.split('|').next().unwrap_or(inner).trim()
+}
+
+/// Anführungszeichen, auch die typografischen aus der Autokorrektur (`„…“`, `“…”`, `»…«`)
+const OPENING_QUOTES: [char; 5] = ['"', '„', '“', '»', '\''];
+const CLOSING_QUOTES: [char; 5] = ['"', '“', '”', '«', '\''];
+
+fn split_outside_quotes(text: &str, separator: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut quoted = false;
+    for c in text.chars() {
+        if quoted {
+            quoted = !CLOSING_QUOTES.contains(&c);
+        } else if OPENING_QUOTES.contains(&c) {
+            quoted = true;
+        } else if c == separator {
+            parts.push(String::new());
+            continue;
+        }
+        parts.last_mut().unwrap().push(c);
+    }
+    parts
+}
+
+fn unquote(arg: &str) -> String {
+    let mut chars = arg.chars();
+    match (chars.next(), chars.next_back()) {
+        (Some(first), Some(last)) if OPENING_QUOTES.contains(&first) && CLOSING_QUOTES.contains(&last) => {
+            chars.collect()
+        }
+        _ => arg.to_string(),
+    }
+}
+
+fn argument<'a>(args: &'a [String], index: usize, filter: &str) -> std::result::Result<&'a str, String> {
+    args.get(index)
+        .map(String::as_str)
+        .ok_or_else(|| format!("filter `{}` needs an argument", filter))
+}
+
+fn default_filter(value: Option<&str>, args: &[String]) -> std::result::Result<Option<String>, String> {
+    let fallback = argument(args, 0, "default")?;
+    Ok(Some(match value {
+        Some(value) if !value.trim().is_empty() => value.to_string(),
+        _ => fallback.to_string(),
+    }))
+}
+
+/// `truncate:N` bzw. `truncate:N,"Suffix"`: höchstens N Zeichen inklusive Suffix (Standard `…`)
+fn truncate_filter(value: Option<&str>, args: &[String]) -> std::result::Result<Option<String>, String> {
+    let max: usize = argument(args, 0, "truncate")?
+        .parse()
+        .map_err(|_| format!("invalid length `{}` for truncate", args[0]))?;
+    let suffix = args.get(1).map_or("…", String::as_str);
+
+    Ok(value.map(|value| {
+        if value.chars().count() <= max {
+            return value.to_string();
+        }
+        let keep = max.saturating_sub(suffix.chars().count());
+        let cut: String = value.chars().take(keep).collect();
+        format!("{}{}", cut.trim_end(), suffix)
+    }))
+}
+
+/// `date:"FORMAT"[,SPRACHE]` mit chrono-Formatangaben, z.B. `%d.%m.%Y`; Monats- und
+/// Wochentagsnamen (`%B`, `%A`) in der angegebenen Sprache, Standard `de`
+fn date_filter(value: Option<&str>, args: &[String]) -> std::result::Result<Option<String>, String> {
+    let format = argument(args, 0, "date")?;
+    let locale: Locale = match args.get(1) {
+        Some(locale) => locale.parse()?,
+        None => Locale::default(),
+    };
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let date = parse_date(value)?;
+    format_localized(date, format, locale).map(Some)
+}
+
+/// `number:"MUSTER"`: Nachkommastellen und Trennzeichen nach Muster, z.B. `0.0`,
+/// `0,00` (Dezimalkomma) oder `#.##0,00` (mit Tausenderpunkten)
+fn number_filter(value: Option<&str>, args: &[String]) -> std::result::Result<Option<String>, String> {
+    let pattern = argument(args, 0, "number")?;
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let number = parse_number(value).ok_or_else(|| format!("`{}` is not a number", value))?;
+
+    let (integer_pattern, decimal_sep, fraction_pattern) = split_number_pattern(pattern);
+    let decimals = fraction_pattern.len();
+    let min_decimals = fraction_pattern.chars().filter(|&c| c == '0').count();
+    let grouping = [',', '.', ' ', '\''].into_iter().find(|&sep| integer_pattern.contains(sep));
+
+    let formatted = format!("{:.*}", decimals, number.abs());
+    let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+    let fraction = fraction.trim_end_matches('0');
+    let fraction = format!("{:0<width$}", fraction, width = min_decimals);
+
+    let mut text = String::new();
+    if number < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        text.push('-');
+    }
+    match grouping {
+        Some(sep) => text.push_str(&group_thousands(integer, sep)),
+        None => text.push_str(integer),
+    }
+    if !fraction.is_empty() {
+        text.push(decimal_sep.unwrap_or('.'));
+        text.push_str(&fraction);
+    }
+    Ok(Some(text))
+}
+
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim();
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.replace('.', "").replace(',', ".").parse().ok())
+}
+
+/// Zerlegt ein Zahlenmuster in Ganzzahlteil, Dezimaltrennzeichen und Nachkommateil
+fn split_number_pattern(pattern: &str) -> (&str, Option<char>, &str) {
+    let Some(pos) = pattern.rfind(['.', ',']) else {
+        return (pattern, None, "");
+    };
+    let separator = pattern[pos..].chars().next().unwrap();
+    let (integer, fraction) = (&pattern[..pos], &pattern[pos + 1..]);
+    // `#,##0` bzw. `#.##0`: einziges Trennzeichen vor drei Stellen ist ein Tausendertrenner
+    let other_separator = integer.contains(['.', ',']);
+    if !other_separator && integer.contains('#') && fraction.len() == 3 {
+        return (pattern, None, "");
+    }
+    (integer, Some(separator), fraction)
+}
+
+fn group_thousands(digits: &str, separator: char) -> String {
+    let mut grouped = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(expression: &str, value: Option<&str>) -> Result<Option<String>> {
+        FilterRegistry::new().apply(&FilterExpression::parse(expression)?, value)
+    }
+
+    #[test]
+    fn test_parse_expression() {
+        let expression = FilterExpression::parse(r#" TITLE | truncate:60,"…" | default:„ohne Titel, leer“ "#).unwrap();
+        assert_eq!(expression.key, "TITLE");
+        assert_eq!(
+            expression.filters,
+            vec![
+                FilterCall {
+                    name: "truncate".into(),
+                    args: vec!["60".into(), "…".into()]
+                },
+                FilterCall {
+                    name: "default".into(),
+                    args: vec!["ohne Titel, leer".into()]
+                },
+            ]
+        );
+        assert!(expression.has_default());
+        assert_eq!(
+            FilterExpression::parse("DATE|date:&quot;%d.%m.&quot;").unwrap().filters[0].args,
+            vec!["%d.%m."]
+        );
+    }
+
+    #[test]
+    fn test_builtin_filters() {
+        assert_eq!(apply("NAME|upper", Some("Jürgen")).unwrap().as_deref(), Some("JÜRGEN"));
+        assert_eq!(apply(r#"HOURS|default:"8""#, None).unwrap().as_deref(), Some("8"));
+        assert_eq!(apply(r#"HOURS|default:"8""#, Some("")).unwrap().as_deref(), Some("8"));
+        assert_eq!(apply("NAME|upper", None).unwrap(), None);
+        assert_eq!(
+            apply("TITLE|truncate:12", Some("Rust Programmierung")).unwrap().as_deref(),
+            Some("Rust Progra…")
+        );
+        assert_eq!(
+            apply(r#"DATE|date:"%Y-%m-%d""#, Some("15.01.2024")).unwrap().as_deref(),
+            Some("2024-01-15")
+        );
+        assert_eq!(
+            apply(r#"DATE|date:"%d. %B %Y""#, Some("2024-03-01")).unwrap().as_deref(),
+            Some("01. März 2024")
+        );
+        assert_eq!(
+            apply(r#"DATE|date:"%B %-d, %Y",en"#, Some("2024-03-01")).unwrap().as_deref(),
+            Some("March 1, 2024")
+        );
+        assert!(matches!(apply(r#"DATE|date:"%d""#, Some("morgen")), Err(CertgenError::Filter(_))));
+        assert!(matches!(apply("NAME|shout", Some("x")), Err(CertgenError::Filter(_))));
+    }
+
+    #[test]
+    fn test_number_filter() {
+        let number = |pattern: &str, value: &str| apply(&format!(r#"X|number:"{}""#, pattern), Some(value)).unwrap().unwrap();
+        assert_eq!(number("0.0", "87.26"), "87.3");
+        assert_eq!(number("0,00", "87.5"), "87,50");
+        assert_eq!(number("0", "87,6"), "88");
+        assert_eq!(number("#,##0.00", "1234567.891"), "1,234,567.89");
+        assert_eq!(number("#.##0,#", "1234,5"), "1.234,5");
+        assert_eq!(number("#,##0", "-1234"), "-1,234");
+        assert_eq!(number("0.##", "2.50"), "2.5");
+    }
+
+    #[test]
+    fn test_custom_filter() {
+        let mut registry = FilterRegistry::new();
+        registry.register("initials", |value, _| {
+            Ok(value.map(|v| v.split_whitespace().filter_map(|w| w.chars().next()).collect()))
+        });
+        let expression = FilterExpression::parse("NAME|initials|lower").unwrap();
+        assert_eq!(registry.apply(&expression, Some("Max Mustermann")).unwrap().as_deref(), Some("mm"));
+    }
+}
@@ -0,0 +1,485 @@
+//! Bilder, die beim Befüllen in das ODF-Paket eingebettet werden: erzeugte
+//! QR-Codes und Bildplatzhalter.
+//!
+//! Ein Bildplatzhalter ist ein Bildrahmen (`draw:frame`), dessen Name, Titel oder
+//! Beschreibung (Alternativtext) `{{KEY}}` lautet. Der Wert von `KEY` im Datensatz
+//! ist der Pfad zur Bilddatei, deren Bytes ins Paket kopiert werden.
+
+use crate::error::{CertgenError, Result};
+use crate::odf::replacer::PlaceholderReplacer;
+use log::{debug, warn};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Wie ein eingesetztes Bild in den Rahmen der Vorlage eingepasst wird
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImageFit {
+    /// Rahmengröße der Vorlage beibehalten, das Bild wird ggf. verzerrt
+    #[default]
+    Stretch,
+    /// Seitenverhältnis des Bildes beibehalten und in den Rahmen einpassen
+    Contain,
+}
+
+impl FromStr for ImageFit {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "stretch" => Ok(ImageFit::Stretch),
+            "contain" => Ok(ImageFit::Contain),
+            _ => Err(format!("invalid image fit `{}`: expected stretch or contain", s)),
+        }
+    }
+}
+
+impl fmt::Display for ImageFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImageFit::Stretch => "stretch",
+            ImageFit::Contain => "contain",
+        })
+    }
+}
+
+/// Ein Bild unter `Pictures/`, das zusätzlich in `META-INF/manifest.xml` eingetragen wird
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct EmbeddedImage {
+    /// Pfad im Paket, z.B. `Pictures/certgen-qr-1.svg`
+    pub path: String,
+    pub media_type: String,
+    pub data: Vec<u8>,
+}
+
+/// Ausgabegröße eines Bildes im Dokument als ODF-Längen, z.B. `3cm`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FrameSize {
+    pub width: String,
+    pub height: String,
+}
+
+/// Zeichengebundener Rahmen, der ein eingebettetes Bild im Fließtext anzeigt
+pub(crate) fn inline_frame(name: &str, image: &EmbeddedImage, size: &FrameSize) -> String {
+    format!(
+        concat!(
+            r#"<draw:frame draw:name="{}" text:anchor-type="as-char" svg:width="{}" svg:height="{}" draw:z-index="0">"#,
+            r#"<draw:image xlink:href="{}" xlink:type="simple" xlink:show="embed" xlink:actuate="onLoad" draw:mime-type="{}"/>"#,
+            "</draw:frame>"
+        ),
+        name, size.width, size.height, image.path, image.media_type
+    )
+}
+
+/// Setzt in allen Bildrahmen mit `{{KEY}}` als Name, Titel oder Beschreibung das
+/// Bild aus dem Datensatz ein; relative Pfade gelten ab `base_dir`. Rahmen ohne Wert
+/// bleiben unverändert, damit die `MissingPolicy` den übrig gebliebenen Platzhalter wie
+/// jeden anderen behandelt; bei einem leeren Wert wendet `replacer` sie gleich an.
+pub(crate) fn replace_image_frames(
+    content: &str,
+    replacements: &HashMap<String, String>,
+    fit: ImageFit,
+    base_dir: &Path,
+    replacer: &PlaceholderReplacer,
+    images: &mut Vec<EmbeddedImage>,
+) -> Result<String> {
+    let key_re = Regex::new(r"\{\{([^{}<>]+)\}\}").unwrap();
+    let mut result = String::with_capacity(content.len());
+    let mut rest = content;
+
+    while let Some(start) = rest.find("<draw:frame") {
+        let Some(end) = rest[start..].find("</draw:frame>").map(|e| start + e + "</draw:frame>".len()) else {
+            break;
+        };
+        let frame = &rest[start..end];
+
+        // Rahmen, die weitere Rahmen enthalten (z.B. Textrahmen), werden nur durchlaufen
+        if frame[1..].contains("<draw:frame") {
+            let inner = start + 1 + frame[1..].find("<draw:frame").unwrap();
+            result.push_str(&rest[..inner]);
+            rest = &rest[inner..];
+            continue;
+        }
+
+        result.push_str(&rest[..start]);
+        rest = &rest[end..];
+
+        let key = frame_key(frame, &key_re);
+        match key.as_deref().and_then(|key| replacements.get(key).map(|value| (key, value))) {
+            Some((key, value)) if value.trim().is_empty() => {
+                let placeholder = format!("{{{{{}}}}}", key);
+                result.push_str(&frame.replace(&placeholder, &replacer.handle_missing(&placeholder)?));
+            }
+            Some((key, value)) => {
+                let image = load_image(&base_dir.join(value), images.len() + 1)?;
+                debug!("Placing image {} in frame {{{{{}}}}}", value, key);
+                result.push_str(&rewrite_frame(frame, key, &image, fit));
+                images.push(image);
+            }
+            None => result.push_str(frame),
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Schlüssel aller Bildplatzhalter in `content`
+pub(crate) fn image_frame_keys(content: &str) -> Vec<String> {
+    let key_re = Regex::new(r"\{\{([^{}<>]+)\}\}").unwrap();
+    content
+        .match_indices("<draw:frame")
+        .filter_map(|(start, _)| {
+            let end = content[start..].find("</draw:frame>")? + start;
+            let frame = &content[start..end];
+            // Wie in `replace_image_frames` zählen nur innerste Rahmen
+            (!frame[1..].contains("<draw:frame")).then(|| frame_key(frame, &key_re))?
+        })
+        .collect()
+}
+
+/// `{{KEY}}` aus `draw:name`, `svg:title` oder `svg:desc` des Rahmens
+fn frame_key(frame: &str, key_re: &Regex) -> Option<String> {
+    let open_tag = &frame[..frame.find('>')?];
+    let name = attribute(open_tag, "draw:name");
+    let title = element_text(frame, "svg:title");
+    let desc = element_text(frame, "svg:desc");
+
+    [name, title, desc]
+        .into_iter()
+        .flatten()
+        .find_map(|text| key_re.captures(text.trim()).map(|caps| caps[1].trim().to_string()))
+}
+
+fn rewrite_frame(frame: &str, key: &str, image: &EmbeddedImage, fit: ImageFit) -> String {
+    let placeholder = format!("{{{{{}}}}}", key);
+    let image_re = Regex::new(r"(?s)<draw:image\b[^>]*?(?:/>|>.*?</draw:image>)").unwrap();
+
+    // Alle bisherigen Bilder (inkl. Ersatzdarstellungen) durch das neue ersetzen
+    let new_image = format!(
+        r#"<draw:image xlink:href="{}" xlink:type="simple" xlink:show="embed" xlink:actuate="onLoad" draw:mime-type="{}"/>"#,
+        image.path, image.media_type
+    );
+    let mut first = true;
+    let mut frame = image_re
+        .replace_all(frame, |_: &regex::Captures| {
+            if std::mem::take(&mut first) {
+                new_image.clone()
+            } else {
+                String::new()
+            }
+        })
+        .replace(&placeholder, key);
+
+    if fit == ImageFit::Contain {
+        match image_size(&image.data) {
+            Some((width, height)) => frame = contain(&frame, width / height),
+            None => warn!("Cannot determine the size of {}, image is stretched", image.path),
+        }
+    }
+    frame
+}
+
+/// Verkleinert Breite oder Höhe des Rahmens auf das Seitenverhältnis des Bildes
+fn contain(frame: &str, aspect: f64) -> String {
+    let open_end = frame.find('>').unwrap_or(frame.len());
+    let open_tag = &frame[..open_end];
+    let (Some(width), Some(height)) = (
+        attribute(open_tag, "svg:width").and_then(parse_length),
+        attribute(open_tag, "svg:height").and_then(parse_length),
+    ) else {
+        return frame.to_string();
+    };
+
+    let (width_cm, height_cm) = (width.0 * width.1, height.0 * height.1);
+    let (new_width, new_height) = if width_cm / height_cm > aspect {
+        (height_cm * aspect, height_cm)
+    } else {
+        (width_cm, width_cm / aspect)
+    };
+
+    let tag = set_attribute(open_tag, "svg:width", &format!("{:.4}cm", new_width));
+    let tag = set_attribute(&tag, "svg:height", &format!("{:.4}cm", new_height));
+    format!("{}{}", tag, &frame[open_end..])
+}
+
+/// Länge als (Wert, Faktor nach cm)
+fn parse_length(value: &str) -> Option<(f64, f64)> {
+    let split = value.find(|c: char| c.is_ascii_alphabetic())?;
+    let number: f64 = value[..split].parse().ok()?;
+    let factor = match &value[split..] {
+        "cm" => 1.0,
+        "mm" => 0.1,
+        "in" => 2.54,
+        "pt" => 2.54 / 72.0,
+        "pc" => 2.54 / 6.0,
+        "px" => 2.54 / 96.0,
+        _ => return None,
+    };
+    Some((number, factor))
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let marker = format!(" {}=\"", name);
+    let start = tag.find(&marker)? + marker.len();
+    let end = tag[start..].find('"')? + start;
+    Some(&tag[start..end])
+}
+
+fn set_attribute(tag: &str, name: &str, value: &str) -> String {
+    let marker = format!(" {}=\"", name);
+    match tag.find(&marker) {
+        Some(pos) => {
+            let start = pos + marker.len();
+            let end = tag[start..].find('"').map_or(tag.len(), |e| start + e);
+            format!("{}{}{}", &tag[..start], value, &tag[end..])
+        }
+        None => format!("{}{}{}\"", tag, marker, value),
+    }
+}
+
+fn element_text<'a>(xml: &'a str, name: &str) -> Option<&'a str> {
+    let open = format!("<{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&format!("</{}>", name))? + start;
+    Some(&xml[start..end])
+}
+
+/// Liest die Bilddatei und legt den Pfad im Paket anhand der Endung fest
+fn load_image(path: &Path, number: usize) -> Result<EmbeddedImage> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let media_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "tif" | "tiff" => "image/tiff",
+        _ => {
+            return Err(CertgenError::Image(format!(
+                "{}: unsupported image type (expected png, jpg, gif, svg, bmp or tif)",
+                path.display()
+            )))
+        }
+    };
+    let data = std::fs::read(path).map_err(|e| CertgenError::Image(format!("{}: {}", path.display(), e)))?;
+
+    Ok(EmbeddedImage {
+        path: format!("Pictures/certgen-img-{}.{}", number, extension),
+        media_type: media_type.to_string(),
+        data,
+    })
+}
+
+/// Pixelmaße aus dem Header von PNG, GIF, JPEG oder SVG
+fn image_size(data: &[u8]) -> Option<(f64, f64)> {
+    let be32 = |b: &[u8]| f64::from(u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") && data.len() >= 24 {
+        return Some((be32(&data[16..20]), be32(&data[20..24])));
+    }
+    if data.starts_with(b"GIF8") && data.len() >= 10 {
+        let le16 = |b: &[u8]| f64::from(u16::from_le_bytes([b[0], b[1]]));
+        return Some((le16(&data[6..8]), le16(&data[8..10])));
+    }
+    if data.starts_with(&[0xFF, 0xD8]) {
+        return jpeg_size(data);
+    }
+    svg_size(std::str::from_utf8(data).ok()?)
+}
+
+fn jpeg_size(data: &[u8]) -> Option<(f64, f64)> {
+    let mut pos = 2;
+    while pos + 9 < data.len() {
+        if data[pos] != 0xFF {
+            return None;
+        }
+        let marker = data[pos + 1];
+        let length = usize::from(u16::from_be_bytes([data[pos + 2], data[pos + 3]]));
+        // SOF0..SOF15 ohne DHT (C4), JPG (C8) und DAC (CC)
+        if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+            let height = u16::from_be_bytes([data[pos + 5], data[pos + 6]]);
+            let width = u16::from_be_bytes([data[pos + 7], data[pos + 8]]);
+            return Some((f64::from(width), f64::from(height)));
+        }
+        pos += 2 + length;
+    }
+    None
+}
+
+fn svg_size(svg: &str) -> Option<(f64, f64)> {
+    let start = svg.find("<svg")?;
+    let tag = &svg[start..start + svg[start..].find('>')?];
+    if let Some(view_box) = attribute(tag, "viewBox") {
+        let values: Vec<f64> = view_box
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|v| !v.is_empty())
+            .filter_map(|v| v.parse().ok())
+            .collect();
+        if let [_, _, width, height] = values[..] {
+            return Some((width, height));
+        }
+    }
+    let number = |value: &str| {
+        value
+            .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+            .parse::<f64>()
+            .ok()
+    };
+    Some((number(attribute(tag, "width")?)?, number(attribute(tag, "height")?)?))
+}
+
+/// Trägt die Bilder in `manifest.xml` ein (vor dem schließenden `</manifest:manifest>`)
+pub(crate) fn register_in_manifest(manifest: &str, images: &[EmbeddedImage]) -> String {
+    let entries: String = images
+        .iter()
+        .filter(|image| !manifest.contains(&format!("manifest:full-path=\"{}\"", image.path)))
+        .map(|image| {
+            format!(
+                r#" <manifest:file-entry manifest:full-path="{}" manifest:media-type="{}"/>"#,
+                image.path, image.media_type
+            ) + "\n"
+        })
+        .collect();
+
+    match manifest.rfind("</manifest:manifest>") {
+        Some(pos) => format!("{}{}{}", &manifest[..pos], entries, &manifest[pos..]),
+        None => manifest.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::odf::replacer::MissingPolicy;
+
+    /// PNG-Header mit den angegebenen Maßen (für die Größenerkennung reicht IHDR)
+    fn png_header(width: u32, height: u32) -> Vec<u8> {
+        let mut data = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        data.extend_from_slice(&width.to_be_bytes());
+        data.extend_from_slice(&height.to_be_bytes());
+        data
+    }
+
+    #[test]
+    fn test_replace_image_frame_by_name_and_desc() {
+        let dir = tempfile::tempdir().unwrap();
+        let logo = dir.path().join("logo.png");
+        std::fs::write(&logo, png_header(400, 100)).unwrap();
+
+        let content = concat!(
+            r#"<text:p><draw:frame draw:style-name="fr1" draw:name="{{LOGO}}" svg:width="4cm" svg:height="4cm">"#,
+            r#"<draw:image xlink:href="Pictures/old.png" draw:mime-type="image/png"><office:binary-data/></draw:image>"#,
+            r#"<draw:image xlink:href="Pictures/old.svm"/></draw:frame></text:p>"#,
+            r#"<text:p><draw:frame draw:name="Bild2" svg:width="2cm" svg:height="1cm"><draw:image xlink:href="Pictures/sig.png"/>"#,
+            r#"<svg:desc>{{SIGNATURE}}</svg:desc></draw:frame></text:p>"#
+        );
+        let replacements = HashMap::from([("LOGO".to_string(), logo.to_string_lossy().to_string())]);
+
+        let mut images = Vec::new();
+        let result =
+            replace_image_frames(content, &replacements, ImageFit::Contain, Path::new(""), &PlaceholderReplacer::new(), &mut images).unwrap();
+
+        assert_eq!(images.len(), 1);
+        assert_eq!(images[0].path, "Pictures/certgen-img-1.png");
+        assert!(result.contains(r#"draw:name="LOGO""#));
+        assert!(result.contains(r#"xlink:href="Pictures/certgen-img-1.png""#));
+        assert!(!result.contains("old.svm"));
+        // 4:1 in einem quadratischen Rahmen → Höhe wird angepasst
+        assert!(result.contains(r#"svg:width="4.0000cm" svg:height="1.0000cm""#));
+        // Ohne Wert bleibt der Rahmen für die MissingPolicy stehen
+        assert!(result.contains("<svg:desc>{{SIGNATURE}}</svg:desc>"));
+    }
+
+    #[test]
+    fn test_missing_image_file() {
+        let content = r#"<draw:frame draw:name="{{LOGO}}"><draw:image xlink:href="x.png"/></draw:frame>"#;
+        let replacements = HashMap::from([("LOGO".to_string(), "/nicht/vorhanden.png".to_string())]);
+        let result = replace_image_frames(content, &replacements, ImageFit::Stretch, Path::new(""), &PlaceholderReplacer::new(), &mut Vec::new());
+        assert!(matches!(result, Err(CertgenError::Image(_))));
+    }
+
+    #[test]
+    fn test_empty_image_value_follows_missing_policy() {
+        let content = r#"<draw:frame draw:name="{{LOGO}}"><draw:image xlink:href="x.png"/></draw:frame>"#;
+        let replacements = HashMap::from([("LOGO".to_string(), " ".to_string())]);
+        let replace = |policy: MissingPolicy| {
+            let replacer = PlaceholderReplacer::new().with_missing_policy(policy);
+            replace_image_frames(content, &replacements, ImageFit::Stretch, Path::new(""), &replacer, &mut Vec::new())
+        };
+
+        assert_eq!(replace(MissingPolicy::Keep).unwrap(), content);
+        assert_eq!(
+            replace(MissingPolicy::Empty).unwrap(),
+            r#"<draw:frame draw:name=""><draw:image xlink:href="x.png"/></draw:frame>"#
+        );
+        assert_eq!(
+            replace(MissingPolicy::Default("Logo".to_string())).unwrap(),
+            r#"<draw:frame draw:name="Logo"><draw:image xlink:href="x.png"/></draw:frame>"#
+        );
+        assert!(matches!(
+            replace(MissingPolicy::Error),
+            Err(CertgenError::PlaceholderNotFound { name, .. }) if name == "LOGO"
+        ));
+    }
+
+    #[test]
+    fn test_relative_image_path() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("logos")).unwrap();
+        std::fs::write(dir.path().join("logos/kunde.png"), png_header(10, 10)).unwrap();
+
+        let content = r#"<draw:frame draw:name="{{LOGO}}"><draw:image xlink:href="x.png"/></draw:frame>"#;
+        let replacements = HashMap::from([("LOGO".to_string(), "logos/kunde.png".to_string())]);
+        let mut images = Vec::new();
+        replace_image_frames(content, &replacements, ImageFit::Stretch, dir.path(), &PlaceholderReplacer::new(), &mut images).unwrap();
+        assert_eq!(images.len(), 1);
+
+        // Absolute Pfade bleiben unverändert
+        let absolute = dir.path().join("logos/kunde.png").to_string_lossy().to_string();
+        let replacements = HashMap::from([("LOGO".to_string(), absolute)]);
+        replace_image_frames(content, &replacements, ImageFit::Stretch, Path::new("/anderswo"), &PlaceholderReplacer::new(), &mut images).unwrap();
+        assert_eq!(images.len(), 2);
+    }
+
+    #[test]
+    fn test_image_size() {
+        assert_eq!(image_size(&png_header(640, 480)), Some((640.0, 480.0)));
+        assert_eq!(
+            image_size(br#"<?xml version="1.0"?><svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 300 150">"#),
+            Some((300.0, 150.0))
+        );
+        assert_eq!(image_size(b"GIF89a\x20\x00\x10\x00"), Some((32.0, 16.0)));
+    }
+
+    #[test]
+    fn test_register_in_manifest() {
+        let manifest = concat!(
+            r#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0">"#,
+            "\n",
+            r#" <manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>"#,
+            "\n</manifest:manifest>"
+        );
+        let image = EmbeddedImage {
+            path: "Pictures/qr.svg".into(),
+            media_type: "image/svg+xml".into(),
+            data: Vec::new(),
+        };
+
+        let updated = register_in_manifest(manifest, std::slice::from_ref(&image));
+        assert!(updated.contains(
+            r#"<manifest:file-entry manifest:full-path="Pictures/qr.svg" manifest:media-type="image/svg+xml"/>"#
+        ));
+        assert!(updated.ends_with("</manifest:manifest>"));
+        // Kein doppelter Eintrag
+        assert_eq!(register_in_manifest(&updated, &[image]), updated);
+    }
+}
@@ -0,0 +1,236 @@
+//! Wiederholte Tabellenzeilen und Listeneinträge: Eine Zeile (`table:table-row`) oder ein
+//! Listeneintrag (`text:list-item`), der `{{#each KEY}}` enthält, wird für jeden Eintrag
+//! der Liste `KEY` wiederholt. Darin stehen die Felder des Eintrags als `{{feld}}` zur
+//! Verfügung; bedingte Abschnitte werden pro Eintrag ausgewertet.
+
+use crate::error::{CertgenError, Result};
+use crate::odf::conditions::{apply_conditions, enclosing_element};
+use crate::odf::replacer::PlaceholderReplacer;
+use log::debug;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// Endung der Schlüssel, unter denen Listen als JSON in den Ersetzungen stehen
+pub const LIST_KEY_SUFFIX: &str = "[]";
+
+/// Schlüssel der Liste `name` in den Ersetzungen, z.B. `AGENDA[]`
+pub fn list_key(name: &str) -> String {
+    format!("{}{}", name, LIST_KEY_SUFFIX)
+}
+
+/// Findet `{{#each KEY}}`
+pub(crate) fn loop_regex() -> Regex {
+    Regex::new(r"\{\{\s*#each\s+([^{}<>\s]+)\s*\}\}").unwrap()
+}
+
+/// Zeilen bzw. Listeneinträge und das Element, in dem sie stehen
+const LOOP_ELEMENTS: [(&str, &str); 2] = [("table:table-row", "table:table"), ("text:list-item", "text:list")];
+
+/// Die Zeile bzw. der Listeneintrag, der die Markierung an `marker` umschließt (der innerste)
+pub(crate) fn loop_element(content: &str, marker: &Range<usize>) -> Option<Range<usize>> {
+    LOOP_ELEMENTS
+        .iter()
+        .filter_map(|(tag, _)| enclosing_element(content, marker, tag))
+        .max_by_key(|element| element.start)
+}
+
+/// Wiederholt alle mit `{{#each KEY}}` markierten Zeilen und Listeneinträge
+pub(crate) fn expand_loops(
+    content: &str,
+    replacements: &HashMap<String, String>,
+    replacer: &PlaceholderReplacer,
+) -> Result<String> {
+    let re = loop_regex();
+    let mut result = content.to_string();
+    let mut pos = 0;
+
+    while let Some(caps) = re.captures(&result[pos..]) {
+        let whole = caps.get(0).unwrap();
+        let marker = pos + whole.start()..pos + whole.end();
+        let key = caps[1].to_string();
+        let element = loop_element(&result, &marker).ok_or_else(|| {
+            CertgenError::TemplateSyntax(format!("{} must be inside a table row or list item", whole.as_str()))
+        })?;
+
+        let rows = list_rows(&key, replacements)?;
+        debug!("Repeating {} for {} entries", whole.as_str(), rows.len());
+
+        let (range, expanded) = if rows.is_empty() {
+            (empty_loop_range(&result, &element), String::new())
+        } else {
+            let template = format!("{}{}", &result[element.start..marker.start], &result[marker.end..element.end]);
+            let mut expanded = String::new();
+            for row in &rows {
+                expanded.push_str(&render_row(&template, row, replacements, replacer)?);
+            }
+            (element, expanded)
+        };
+        result.replace_range(range.clone(), &expanded);
+        pos = range.start + expanded.len();
+    }
+
+    Ok(result)
+}
+
+/// Bereich, der für eine leere Liste entfällt: die Zeile bzw. der Listeneintrag, oder die
+/// ganze Tabelle bzw. Liste, wenn sie sonst keine Zeilen bzw. Einträge hätte (ODF erlaubt
+/// keine leeren Tabellen und Listen)
+fn empty_loop_range(content: &str, element: &Range<usize>) -> Range<usize> {
+    for (tag, container_tag) in LOOP_ELEMENTS {
+        if !content[element.start..].starts_with(&format!("<{}", tag)) {
+            continue;
+        }
+        let Some(container) = enclosing_element(content, element, container_tag) else {
+            break;
+        };
+        let others = [&content[container.start..element.start], &content[element.end..container.end]];
+        if !others.iter().any(|xml| contains_element(xml, tag)) {
+            return container;
+        }
+    }
+    element.clone()
+}
+
+/// Enthält `xml` ein Element `<tag ...>`?
+fn contains_element(xml: &str, tag: &str) -> bool {
+    let open = format!("<{}", tag);
+    xml.match_indices(open.as_str())
+        .any(|(pos, _)| matches!(xml[pos + open.len()..].chars().next(), Some(' ' | '>' | '/')))
+}
+
+fn list_rows(key: &str, replacements: &HashMap<String, String>) -> Result<Vec<HashMap<String, String>>> {
+    let Some(json) = replacements.get(&list_key(key)) else {
+        debug!("No list {} in the data, {{{{#each {}}}}} renders no entries", key, key);
+        return Ok(Vec::new());
+    };
+
+    let rows: Vec<serde_json::Map<String, Value>> = serde_json::from_str(json)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|(field, value)| {
+                    let text = match value {
+                        Value::String(text) => text,
+                        Value::Null => String::new(),
+                        other => other.to_string(),
+                    };
+                    (field, text)
+                })
+                .collect()
+        })
+        .collect())
+}
+
+/// Setzt die Felder eines Eintrags (samt Filtern) ein; Platzhalter ohne Wert bleiben für
+/// die globale `MissingPolicy` stehen
+fn render_row(
+    template: &str,
+    row: &HashMap<String, String>,
+    replacements: &HashMap<String, String>,
+    replacer: &PlaceholderReplacer,
+) -> Result<String> {
+    let mut scope = replacements.clone();
+    scope.extend(row.iter().map(|(field, value)| (field.clone(), value.clone())));
+
+    let visible = apply_conditions(template, &scope)?;
+    replacer.replace_known(&visible, &scope)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements() -> HashMap<String, String> {
+        HashMap::from([
+            ("NAME".to_string(), "Max".to_string()),
+            (
+                list_key("AGENDA"),
+                r#"[{"module":"Grundlagen","hours":8},{"module":"Fehler & Result","hours":4,"exam":true}]"#.to_string(),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_repeat_table_row() {
+        let content = concat!(
+            "<table:table><table:table-row><table:table-cell><text:p>Modul</text:p></table:table-cell></table:table-row>",
+            "<table:table-row><table:table-cell><text:p>{{#each AGENDA}}{{module}}</text:p></table:table-cell>",
+            r#"<table:table-cell><text:p>{{hours|number:"0.0"}} h{{#if exam}} (Prüfung){{/if}}</text:p></table:table-cell></table:table-row>"#,
+            "</table:table><text:p>{{NAME}}</text:p>"
+        );
+        let result = expand_loops(content, &replacements(), &PlaceholderReplacer::new()).unwrap();
+
+        assert_eq!(
+            result,
+            concat!(
+                "<table:table><table:table-row><table:table-cell><text:p>Modul</text:p></table:table-cell></table:table-row>",
+                "<table:table-row><table:table-cell><text:p>Grundlagen</text:p></table:table-cell>",
+                "<table:table-cell><text:p>8.0 h</text:p></table:table-cell></table:table-row>",
+                "<table:table-row><table:table-cell><text:p>Fehler &amp; Result</text:p></table:table-cell>",
+                "<table:table-cell><text:p>4.0 h (Prüfung)</text:p></table:table-cell></table:table-row>",
+                "</table:table><text:p>{{NAME}}</text:p>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_repeat_list_item() {
+        let content = concat!(
+            "<text:list><text:list-item><text:p>{{#each AGENDA}}{{module}} ({{NAME}})</text:p></text:list-item></text:list>",
+            "<text:list><text:list-item><text:p>{{#each MISSING}}{{x}}</text:p></text:list-item></text:list>"
+        );
+        let result = expand_loops(content, &replacements(), &PlaceholderReplacer::new()).unwrap();
+
+        assert_eq!(
+            result,
+            concat!(
+                "<text:list><text:list-item><text:p>Grundlagen (Max)</text:p></text:list-item>",
+                "<text:list-item><text:p>Fehler &amp; Result (Max)</text:p></text:list-item></text:list>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_empty_list_removes_container() {
+        let mut replacements = replacements();
+        replacements.insert(list_key("EMPTY"), "[]".to_string());
+
+        // Eine Liste ohne weitere Einträge entfällt ganz, sonst nur der Eintrag
+        let content = concat!(
+            "<text:p>Agenda</text:p><text:list text:style-name=\"L1\"><text:list-item><text:p>{{#each EMPTY}}{{x}}</text:p></text:list-item></text:list>",
+            "<text:list><text:list-item><text:p>Fest</text:p></text:list-item><text:list-item><text:p>{{#each EMPTY}}{{x}}</text:p></text:list-item></text:list>"
+        );
+        let result = expand_loops(content, &replacements, &PlaceholderReplacer::new()).unwrap();
+        assert_eq!(
+            result,
+            "<text:p>Agenda</text:p><text:list><text:list-item><text:p>Fest</text:p></text:list-item></text:list>"
+        );
+    }
+
+    #[test]
+    fn test_empty_table_removes_container() {
+        // Ohne Kopfzeile entfällt die ganze Tabelle, mit Kopfzeile nur die wiederholte Zeile
+        let content = concat!(
+            "<table:table table:name=\"A\"><table:table-column/><table:table-row><table:table-cell><text:p>{{#each MISSING}}{{x}}</text:p></table:table-cell></table:table-row></table:table>",
+            "<table:table table:name=\"B\"><table:table-row><table:table-cell><text:p>Modul</text:p></table:table-cell></table:table-row>",
+            "<table:table-row><table:table-cell><text:p>{{#each MISSING}}{{x}}</text:p></table:table-cell></table:table-row></table:table>"
+        );
+        let result = expand_loops(content, &replacements(), &PlaceholderReplacer::new()).unwrap();
+        assert_eq!(
+            result,
+            concat!(
+                "<table:table table:name=\"B\"><table:table-row><table:table-cell><text:p>Modul</text:p></table:table-cell></table:table-row>",
+                "</table:table>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_loop_outside_row_is_rejected() {
+        let result = expand_loops("<text:p>{{#each AGENDA}}</text:p>", &replacements(), &PlaceholderReplacer::new());
+        assert!(matches!(result, Err(CertgenError::TemplateSyntax(_))));
+    }
+}
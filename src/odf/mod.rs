@@ -1,5 +1,14 @@
+mod conditions;
 pub mod document;
+pub mod filters;
+mod images;
+mod loops;
+pub mod qr;
 pub mod replacer;
 
-pub use document::OdfDocument;
-pub use replacer::PlaceholderReplacer;
+pub use document::{DocumentPart, OdfDocument, PlaceholderInfo, RenderJob};
+pub use filters::{FilterExpression, FilterRegistry};
+pub use images::ImageFit;
+pub use loops::{list_key, LIST_KEY_SUFFIX};
+pub use qr::QrOptions;
+pub use replacer::{MissingPolicy, PlaceholderReplacer};
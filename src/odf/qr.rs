@@ -0,0 +1,223 @@
+//! QR-Code-Platzhalter: `{{QR}}` bzw. `{{QR:Inhalt}}` werden durch ein lokal
+//! erzeugtes SVG ersetzt, das unter `Pictures/` ins Paket eingebettet wird.
+//!
+//! Im Inhalt können Felder als `{KEY}` referenziert werden, z.B.
+//! `{{QR:https://zertifikate.example.com/verify/{CERT_ID}?name={NAME}}}`.
+//! Beginnt der Inhalt mit `http://` oder `https://`, werden die eingesetzten Werte URL-kodiert.
+
+use crate::error::{CertgenError, Result};
+use crate::odf::images::{inline_frame, EmbeddedImage, FrameSize};
+use crate::odf::replacer::{MissingPolicy, PlaceholderReplacer};
+use log::{debug, warn};
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+
+/// Name, unter dem QR-Codes in `OdfDocument::placeholders()` auftauchen
+pub const QR_PLACEHOLDER: &str = "QR";
+
+/// Einstellungen für QR-Code-Platzhalter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QrOptions {
+    /// Inhalt für `{{QR}}` ohne eigenes Muster
+    pub default_payload: String,
+    /// Kantenlänge im Dokument als ODF-Länge, z.B. `3cm` oder `1.2in`
+    pub size: String,
+}
+
+impl Default for QrOptions {
+    fn default() -> Self {
+        Self {
+            default_payload: "{CERT_ID}".to_string(),
+            size: "3cm".to_string(),
+        }
+    }
+}
+
+/// Findet `{{QR}}` und `{{QR:...}}`; der Inhalt darf Feldreferenzen `{KEY}` enthalten
+pub(crate) fn qr_regex() -> Regex {
+    Regex::new(r"\{\{QR(?::((?:[^{}<>]|\{[A-Za-z0-9_]+\})*))?\}\}").unwrap()
+}
+
+/// Ersetzt alle QR-Platzhalter in `content` durch Bildrahmen und hängt die
+/// erzeugten Bilder an `images` an
+pub(crate) fn replace_qr_codes(
+    content: &str,
+    replacements: &HashMap<String, String>,
+    options: &QrOptions,
+    missing: &MissingPolicy,
+    images: &mut Vec<EmbeddedImage>,
+) -> Result<String> {
+    let mut error = None;
+    let size = FrameSize {
+        width: options.size.clone(),
+        height: options.size.clone(),
+    };
+
+    let result = qr_regex().replace_all(content, |caps: &Captures| {
+        if error.is_some() {
+            return String::new();
+        }
+        let template = caps
+            .get(1)
+            .map(|m| PlaceholderReplacer::unescape_xml(m.as_str()))
+            .unwrap_or_else(|| options.default_payload.clone());
+
+        match render_payload(&template, replacements, missing).and_then(|payload| qr_svg(&payload)) {
+            Ok(svg) => {
+                let number = images.len() + 1;
+                let image = EmbeddedImage {
+                    path: format!("Pictures/certgen-qr-{}.svg", number),
+                    media_type: "image/svg+xml".to_string(),
+                    data: svg.into_bytes(),
+                };
+                let frame = inline_frame(&format!("QR-Code {}", number), &image, &size);
+                images.push(image);
+                frame
+            }
+            Err(e) => {
+                error = Some(e);
+                String::new()
+            }
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(result.into_owned()),
+    }
+}
+
+/// Setzt die Feldwerte in das Inhaltsmuster ein
+fn render_payload(
+    template: &str,
+    replacements: &HashMap<String, String>,
+    missing: &MissingPolicy,
+) -> Result<String> {
+    let is_url = template.starts_with("http://") || template.starts_with("https://");
+    let field = Regex::new(r"\{([A-Za-z0-9_]+)\}").unwrap();
+
+    let mut error = None;
+    let payload = field.replace_all(template, |caps: &Captures| {
+        let value = match replacements.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => match missing {
+                MissingPolicy::Error => {
+                    error.get_or_insert(CertgenError::PlaceholderNotFound {
+                        name: caps[1].to_string(),
+                        record: None,
+                    });
+                    return String::new();
+                }
+                MissingPolicy::Keep => {
+                    warn!("✗ QR field '{}' has no value and is left as-is", &caps[0]);
+                    return caps[0].to_string();
+                }
+                MissingPolicy::Empty => String::new(),
+                MissingPolicy::Default(value) => value.clone(),
+            },
+        };
+        if is_url {
+            percent_encode(&value)
+        } else {
+            value
+        }
+    });
+
+    match error {
+        Some(e) => Err(e),
+        None => {
+            debug!("QR payload: {}", payload);
+            Ok(payload.into_owned())
+        }
+    }
+}
+
+fn qr_svg(payload: &str) -> Result<String> {
+    let code = QrCode::with_error_correction_level(payload.as_bytes(), EcLevel::M)
+        .map_err(|e| CertgenError::QrCode(format!("{} ({} bytes)", e, payload.len())))?;
+    Ok(code
+        .render::<svg::Color>()
+        .min_dimensions(256, 256)
+        .quiet_zone(true)
+        .build())
+}
+
+/// Kodiert alles außer den in URLs nicht reservierten Zeichen (RFC 3986)
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacements() -> HashMap<String, String> {
+        HashMap::from([
+            ("CERT_ID".to_string(), "B1-2024-000123".to_string()),
+            ("NAME".to_string(), "Jürgen Groß".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_url_payload_is_encoded() {
+        let payload = render_payload(
+            "https://example.com/verify/{CERT_ID}?name={NAME}&lang=de",
+            &replacements(),
+            &MissingPolicy::Keep,
+        )
+        .unwrap();
+        assert_eq!(
+            payload,
+            "https://example.com/verify/B1-2024-000123?name=J%C3%BCrgen%20Gro%C3%9F&lang=de"
+        );
+
+        let text = render_payload("{NAME} {CERT_ID}", &replacements(), &MissingPolicy::Keep).unwrap();
+        assert_eq!(text, "Jürgen Groß B1-2024-000123");
+    }
+
+    #[test]
+    fn test_missing_field_policy() {
+        assert!(matches!(
+            render_payload("{HOURS}", &replacements(), &MissingPolicy::Error),
+            Err(CertgenError::PlaceholderNotFound { ref name, .. }) if name == "HOURS"
+        ));
+        assert_eq!(
+            render_payload("x{HOURS}", &replacements(), &MissingPolicy::Empty).unwrap(),
+            "x"
+        );
+    }
+
+    #[test]
+    fn test_replace_qr_codes() {
+        let content = concat!(
+            "<text:p>{{QR}}</text:p>",
+            "<text:p>{{QR:https://example.com/v/{CERT_ID}?a=1&amp;b=2}}</text:p>",
+            "<text:p>{{NAME}}</text:p>"
+        );
+        let mut images = Vec::new();
+        let result = replace_qr_codes(
+            content,
+            &replacements(),
+            &QrOptions::default(),
+            &MissingPolicy::Keep,
+            &mut images,
+        )
+        .unwrap();
+
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[1].path, "Pictures/certgen-qr-2.svg");
+        assert!(String::from_utf8_lossy(&images[0].data).starts_with("<?xml"));
+        assert!(result.contains(r#"xlink:href="Pictures/certgen-qr-1.svg""#));
+        assert!(result.contains(r#"svg:width="3cm""#));
+        assert!(!result.contains("{{QR"));
+        assert!(result.contains("{{NAME}}"));
+    }
+}
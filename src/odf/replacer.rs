@@ -1,9 +1,49 @@
+use crate::error::{CertgenError, Result};
+use crate::odf::filters::{FilterExpression, FilterRegistry};
 use std::collections::HashMap;
-use log::{warn, info};
+use std::str::FromStr;
+use log::{debug, warn};
+use regex::Regex;
+
+/// Verhalten für Platzhalter, die nach dem Ersetzen noch im Dokument stehen
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum MissingPolicy {
+    /// Platzhalter unverändert stehen lassen (nur Warnung im Log)
+    #[default]
+    Keep,
+    /// Platzhalter durch einen leeren String ersetzen
+    Empty,
+    /// Platzhalter durch einen festen Standardwert ersetzen
+    Default(String),
+    /// Mit `CertgenError::PlaceholderNotFound` abbrechen
+    Error,
+}
+
+impl FromStr for MissingPolicy {
+    type Err = String;
+
+    /// Akzeptiert `keep`, `empty`, `error` und `default=TEXT`
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(MissingPolicy::Keep),
+            "empty" => Ok(MissingPolicy::Empty),
+            "error" => Ok(MissingPolicy::Error),
+            _ => match s.strip_prefix("default=") {
+                Some(value) => Ok(MissingPolicy::Default(value.to_string())),
+                None => Err(format!(
+                    "invalid policy `{}`: expected keep, empty, error or default=TEXT",
+                    s
+                )),
+            },
+        }
+    }
+}
 
 pub struct PlaceholderReplacer {
     prefix: String,
     suffix: String,
+    missing: MissingPolicy,
+    filters: FilterRegistry,
 }
 
 impl PlaceholderReplacer {
@@ -11,27 +51,63 @@ impl PlaceholderReplacer {
         Self {
             prefix: "{{".to_string(),
             suffix: "}}".to_string(),
+            missing: MissingPolicy::default(),
+            filters: FilterRegistry::new(),
         }
     }
 
-    /// Escaped XML-Sonderzeichen und konvertiert Newlines zu XML line breaks
-    fn escape_xml(text: &str) -> String {
+    /// Setzt das Verhalten für Platzhalter ohne Wert
+    pub fn with_missing_policy(mut self, policy: MissingPolicy) -> Self {
+        self.missing = policy;
+        self
+    }
+
+    /// Setzt die Filter für `{{KEY|filter}}` (Standard: die eingebauten Filter)
+    pub fn with_filters(mut self, filters: FilterRegistry) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Escaped XML-Sonderzeichen und konvertiert Newlines zu XML line breaks.
+    /// Geschweifte Klammern werden als Zeichenreferenz geschrieben, damit eingesetzte Werte
+    /// keine Platzhalter bilden, die spätere Schritte (`MissingPolicy`, Bedingungen) für
+    /// Platzhalter der Vorlage halten.
+    pub(crate) fn escape_xml(text: &str) -> String {
         // Erst die normalen XML-Zeichen escapen
         let escaped = text
             .replace('&', "&amp;")
             .replace('<', "&lt;")
             .replace('>', "&gt;")
             .replace('"', "&quot;")
-            .replace('\'', "&apos;");
+            .replace('\'', "&apos;")
+            .replace('{', "&#123;")
+            .replace('}', "&#125;");
         
         // Dann Newlines zu ODF line breaks konvertieren
         escaped.replace('\n', "<text:line-break/>")
     }
 
-    pub fn replace_all(&self, content: &str, replacements: &HashMap<String, String>) -> String {
-        let mut result = content.to_string();
+    /// Löst die vordefinierten XML-Entities in Text aus der Vorlage auf, z.B. in
+    /// Filterargumenten oder QR-Inhalten
+    pub(crate) fn unescape_xml(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    }
+
+    /// Ersetzt alle bekannten Platzhalter und behandelt übrig gebliebene gemäß `MissingPolicy`
+    pub fn replace_all(&self, content: &str, replacements: &HashMap<String, String>) -> Result<String> {
+        let result = self.replace_known(content, replacements)?;
+        self.handle_missing(&result)
+    }
+
+    /// Ersetzt alle Platzhalter, für die es einen Wert gibt; übrige bleiben stehen
+    pub(crate) fn replace_known(&self, content: &str, replacements: &HashMap<String, String>) -> Result<String> {
+        let mut result = self.apply_filters(content, replacements)?;
         
-        info!("Starting replacements. Total placeholders: {}", replacements.len());
+        debug!("Starting replacements. Total placeholders: {}", replacements.len());
         
         for (key, value) in replacements {
             let placeholder = format!("{}{}{}", self.prefix, key, self.suffix);
@@ -39,14 +115,85 @@ impl PlaceholderReplacer {
             
             if count > 0 {
                 let escaped_value = Self::escape_xml(value);
-                info!("✓ Replacing {} occurrences of '{}' with '{}'", count, placeholder, value);
+                debug!("✓ Replacing {} occurrences of '{}' with '{}'", count, placeholder, value);
                 result = result.replace(&placeholder, &escaped_value);
             } else {
-                warn!("✗ Placeholder '{}' not found in document", placeholder);
+                debug!("✗ Placeholder '{}' not found in this part", placeholder);
             }
         }
         
-        result
+        Ok(result)
+    }
+
+    /// Ersetzt Platzhalter mit Filtern (`{{KEY|filter:arg}}`). Ohne Ergebnis bleibt der
+    /// Platzhalter für die `MissingPolicy` stehen.
+    fn apply_filters(&self, content: &str, replacements: &HashMap<String, String>) -> Result<String> {
+        let re = Regex::new(&format!(
+            r"{}([^{{}}|]+\|[^{{}}]*){}",
+            regex::escape(&self.prefix),
+            regex::escape(&self.suffix)
+        ))
+        .unwrap();
+
+        let mut error = None;
+        let result = re.replace_all(content, |caps: &regex::Captures| {
+            let filtered = FilterExpression::parse(&caps[1]).and_then(|expression| {
+                let value = self.filters.apply(&expression, replacements.get(&expression.key).map(String::as_str))?;
+                match value {
+                    None if self.missing == MissingPolicy::Error => Err(CertgenError::PlaceholderNotFound {
+                        name: expression.key,
+                        record: None,
+                    }),
+                    _ => Ok(value),
+                }
+            });
+            match filtered {
+                Ok(Some(value)) => Self::escape_xml(&value),
+                Ok(None) => caps[0].to_string(),
+                Err(e) => {
+                    error.get_or_insert(e);
+                    caps[0].to_string()
+                }
+            }
+        });
+
+        match error {
+            Some(e) => Err(e),
+            None => Ok(result.into_owned()),
+        }
+    }
+
+    /// Wendet die `MissingPolicy` auf alle Platzhalter an, die nach dem Ersetzen noch übrig sind.
+    /// Eingesetzte Werte enthalten keine `{{`/`}}` mehr (siehe `escape_xml`), es bleiben also
+    /// nur Platzhalter aus der Vorlage.
+    pub(crate) fn handle_missing(&self, content: &str) -> Result<String> {
+        let re = Regex::new(&format!(
+            r"{}([^{{}}]+){}",
+            regex::escape(&self.prefix),
+            regex::escape(&self.suffix)
+        ))
+        .unwrap();
+
+        if self.missing == MissingPolicy::Error {
+            return match re.captures(content) {
+                Some(caps) => Err(CertgenError::PlaceholderNotFound {
+                    name: caps[1].to_string(),
+                    record: None,
+                }),
+                None => Ok(content.to_string()),
+            };
+        }
+
+        let result = re.replace_all(content, |caps: &regex::Captures| match &self.missing {
+            MissingPolicy::Empty => String::new(),
+            MissingPolicy::Default(value) => Self::escape_xml(value),
+            _ => {
+                warn!("✗ Placeholder '{}' has no value and is left as-is", &caps[0]);
+                caps[0].to_string()
+            }
+        });
+
+        Ok(result.into_owned())
     }
 }
 
@@ -95,8 +242,78 @@ mod tests {
         replacements.insert("COMPANY".to_string(), "1&1 Internet".to_string());
         
         let content = "Firma: {{COMPANY}}";
-        let result = replacer.replace_all(content, &replacements);
+        let result = replacer.replace_all(content, &replacements).unwrap();
         
         assert_eq!(result, "Firma: 1&amp;1 Internet");
     }
+
+    #[test]
+    fn test_missing_policies() {
+        let mut replacements = HashMap::new();
+        replacements.insert("NAME".to_string(), "Max".to_string());
+        let content = "{{NAME}} von {{INSTRUCTOR}}";
+
+        let keep = PlaceholderReplacer::new();
+        assert_eq!(keep.replace_all(content, &replacements).unwrap(), "Max von {{INSTRUCTOR}}");
+
+        let empty = PlaceholderReplacer::new().with_missing_policy(MissingPolicy::Empty);
+        assert_eq!(empty.replace_all(content, &replacements).unwrap(), "Max von ");
+
+        let default = PlaceholderReplacer::new()
+            .with_missing_policy(MissingPolicy::Default("N&N".to_string()));
+        assert_eq!(default.replace_all(content, &replacements).unwrap(), "Max von N&amp;N");
+
+        let error = PlaceholderReplacer::new().with_missing_policy(MissingPolicy::Error);
+        match error.replace_all(content, &replacements) {
+            Err(CertgenError::PlaceholderNotFound { name, record }) => {
+                assert_eq!(name, "INSTRUCTOR");
+                assert_eq!(record, None);
+            }
+            other => panic!("expected PlaceholderNotFound, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_values_with_braces_are_not_placeholders() {
+        let mut replacements = HashMap::new();
+        replacements.insert("NAME".to_string(), "{{INSTRUCTOR}}".to_string());
+        replacements.insert("TITLE".to_string(), "{{NAME}}".to_string());
+        replacements.insert("NOTE".to_string(), "a}}b".to_string());
+
+        let error = PlaceholderReplacer::new().with_missing_policy(MissingPolicy::Error);
+        assert_eq!(
+            error.replace_all("{{NAME}} {{TITLE|upper}} {{NOTE}}", &replacements).unwrap(),
+            "&#123;&#123;INSTRUCTOR&#125;&#125; &#123;&#123;NAME&#125;&#125; a&#125;&#125;b"
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let mut replacements = HashMap::new();
+        replacements.insert("NAME".to_string(), "Max & Co".to_string());
+        replacements.insert("SCORE".to_string(), "87.26".to_string());
+        let content = r#"{{NAME|upper}} {{SCORE|number:"0,0"}} {{HOURS|default:"8"}} {{NAME}} {{INSTRUCTOR|upper}}"#;
+
+        let replacer = PlaceholderReplacer::new();
+        assert_eq!(
+            replacer.replace_all(content, &replacements).unwrap(),
+            "MAX &amp; CO 87,3 8 Max &amp; Co {{INSTRUCTOR|upper}}"
+        );
+
+        let error = PlaceholderReplacer::new().with_missing_policy(MissingPolicy::Error);
+        assert!(matches!(
+            error.replace_all(content, &replacements),
+            Err(CertgenError::PlaceholderNotFound { ref name, .. }) if name == "INSTRUCTOR"
+        ));
+    }
+
+    #[test]
+    fn test_parse_missing_policy() {
+        assert_eq!("keep".parse::<MissingPolicy>(), Ok(MissingPolicy::Keep));
+        assert_eq!(
+            "default=-".parse::<MissingPolicy>(),
+            Ok(MissingPolicy::Default("-".to_string()))
+        );
+        assert!("drop".parse::<MissingPolicy>().is_err());
+    }
 }
@@ -0,0 +1,453 @@
+//! Lokales Register der ausgestellten Zertifikate.
+//!
+//! Das Register ist eine JSONL-Datei mit einer Zeile pro Ausstellung. Wird ein
+//! Zertifikat erneut erzeugt, kommt eine neue Zeile mit derselben ID hinzu; beim
+//! Nachschlagen gilt jeweils der letzte Eintrag.
+//!
+//! Eine ID wird nur für denselben Datensatz wiederverwendet: Er hat die ID bereits als
+//! `cert_id` oder wird über E-Mail-Adresse, Name, Titel und Daten wiedererkannt, egal in
+//! welcher Zeile der Eingabe er steht. Jede ID wird pro Lauf nur einmal vergeben, sodass
+//! gleichnamige Teilnehmer desselben Kurses eigene IDs bekommen.
+//!
+//! Die Sprachfassungen eines Zertifikats teilen sich eine ID; jede Fassung bekommt eine
+//! eigene Zeile mit `language` und wird über ID und Sprache nachgeschlagen.
+
+use crate::error::{CertgenError, Result};
+use crate::template::dates::{parse_date, storage_text, Locale};
+use crate::template::CertificateData;
+use chrono::{Datelike, NaiveDate};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Dateiname des Registers im Ausgabeverzeichnis, wenn weder CLI noch Konfiguration
+/// ein Register angeben
+pub const DEFAULT_REGISTRY_FILE: &str = "certgen-registry.jsonl";
+
+/// Aufbau neuer Zertifikats-IDs
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum IdFormat {
+    /// Zeitlich sortierbare ULID, z.B. `01HMZ3W6B7V1N6Q9X2K4R8T5YA`
+    Ulid,
+    /// Muster mit `{year}` und `{seq}` bzw. `{seq:N}` (mit Nullen auf N Stellen aufgefüllt),
+    /// z.B. `B1-{year}-{seq:6}` → `B1-2024-000123`
+    Pattern(String),
+}
+
+impl Default for IdFormat {
+    fn default() -> Self {
+        IdFormat::Pattern("CERT-{year}-{seq:6}".to_string())
+    }
+}
+
+impl FromStr for IdFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("ulid") {
+            return Ok(IdFormat::Ulid);
+        }
+        if !seq_regex().is_match(s) {
+            return Err(format!(
+                "invalid ID format `{}`: expected `ulid` or a pattern containing {{seq}} or {{seq:N}}",
+                s
+            ));
+        }
+        Ok(IdFormat::Pattern(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for IdFormat {
+    type Error = String;
+
+    fn try_from(s: String) -> std::result::Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for IdFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IdFormat::Ulid => f.write_str("ulid"),
+            IdFormat::Pattern(pattern) => f.write_str(pattern),
+        }
+    }
+}
+
+impl IdFormat {
+    /// Erzeugt die ID für Jahr und laufende Nummer (bei ULID werden beide ignoriert)
+    fn render(&self, year: i32, seq: u64) -> String {
+        match self {
+            IdFormat::Ulid => ulid::Ulid::new().to_string(),
+            IdFormat::Pattern(pattern) => {
+                let with_year = pattern.replace("{year}", &year.to_string());
+                seq_regex()
+                    .replace_all(&with_year, |caps: &regex::Captures| {
+                        let width = caps.get(1).map_or(0, |w| w.as_str().parse().unwrap_or(0));
+                        format!("{:0width$}", seq, width = width)
+                    })
+                    .into_owned()
+            }
+        }
+    }
+
+    /// Laufende Nummer einer bestehenden ID, falls sie zum Muster und Jahr passt
+    fn sequence_of(&self, id: &str, year: i32) -> Option<u64> {
+        let IdFormat::Pattern(pattern) = self else {
+            return None;
+        };
+        let literal = regex::escape(&pattern.replace("{year}", &year.to_string()));
+        // `{` und `}` werden von regex::escape maskiert
+        let source = Regex::new(r"\\\{seq(?::\d+)?\\\}").unwrap().replace_all(&literal, r"(\d+)");
+        let regex = Regex::new(&format!("^{}$", source)).ok()?;
+        regex.captures(id)?.get(1)?.as_str().parse().ok()
+    }
+}
+
+fn seq_regex() -> Regex {
+    Regex::new(r"\{seq(?::(\d+))?\}").unwrap()
+}
+
+/// Woran ein Datensatz neben Name, Titel und Daten in einem späteren Lauf wiedererkannt
+/// wird: seine E-Mail-Adresse, falls vorhanden. Die Zeile in der Eingabe (nur im Batch)
+/// wird nur im Register festgehalten
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordKey {
+    pub row: Option<usize>,
+    pub email: Option<String>,
+}
+
+/// Eine Ausstellung im Register
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    pub id: String,
+    pub name: String,
+    /// Zeile des Datensatzes in der Eingabe (nur im Batch)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub date: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_to: Option<String>,
+    /// Sprache der Fassung (nur bei mehrsprachigen Zertifikaten)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Locale>,
+    /// Pfad der erzeugten Datei
+    pub file: String,
+    /// SHA-256 der erzeugten Datei (hex)
+    pub sha256: String,
+    /// Zeitpunkt der Ausstellung (RFC 3339)
+    pub issued_at: String,
+}
+
+impl RegistryEntry {
+    fn matches(&self, data: &CertificateData, key: &RecordKey) -> bool {
+        self.email == key.email
+            && self.name == data.name
+            && self.title.as_ref() == data.custom_fields.get("TITLE")
+            && same_date(Some(&self.date), Some(data.date))
+            && same_date(self.date_from.as_deref(), data.date_from)
+            && same_date(self.date_to.as_deref(), data.date_to)
+    }
+}
+
+/// Register der ausgestellten Zertifikate, vergibt und merkt sich Zertifikats-IDs
+pub struct Registry {
+    path: PathBuf,
+    format: IdFormat,
+    entries: Vec<RegistryEntry>,
+    /// In diesem Lauf vergebene, noch nicht eingetragene IDs
+    pending: Vec<String>,
+}
+
+impl Registry {
+    /// Öffnet das Register; eine fehlende Datei wird beim ersten Eintrag angelegt
+    pub fn open(path: impl Into<PathBuf>, format: IdFormat) -> Result<Self> {
+        let path = path.into();
+        let mut entries = Vec::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(&path)?);
+            for (line_no, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry = serde_json::from_str(&line).map_err(|e| {
+                    CertgenError::Registry(format!("{}:{}: {}", path.display(), line_no + 1, e))
+                })?;
+                entries.push(entry);
+            }
+        }
+
+        Ok(Self {
+            path,
+            format,
+            entries,
+            pending: Vec::new(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Letzter Eintrag zu einer ID in der angegebenen Sprache
+    pub fn find(&self, id: &str, language: Option<Locale>) -> Option<&RegistryEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.id == id && entry.language == language)
+    }
+
+    /// Letzte Einträge zu einer ID, einer je Sprachfassung
+    pub fn files(&self, id: &str) -> Vec<&RegistryEntry> {
+        let mut latest: Vec<&RegistryEntry> = Vec::new();
+        for entry in self.entries.iter().rev().filter(|entry| entry.id == id) {
+            if !latest.iter().any(|known| known.language == entry.language) {
+                latest.push(entry);
+            }
+        }
+        latest.reverse();
+        latest
+    }
+
+    /// Liefert die ID für einen Datensatz: eine bereits gesetzte `cert_id`, sonst die
+    /// älteste ID, deren letzte Ausstellung zum Datensatz passt (gleiche E-Mail-Adresse und
+    /// gleiche Daten) und die in diesem Lauf noch nicht vergeben wurde, sonst eine neue ID
+    pub fn assign_id(&mut self, data: &CertificateData, key: &RecordKey) -> String {
+        if let Some(id) = &data.cert_id {
+            self.pending.push(id.clone());
+            return id.clone();
+        }
+
+        // Letzte Ausstellung je ID, in der Reihenfolge der ersten Ausstellung
+        let mut latest: Vec<&RegistryEntry> = Vec::new();
+        let mut positions: HashMap<&str, usize> = HashMap::new();
+        for entry in &self.entries {
+            match positions.get(entry.id.as_str()) {
+                Some(&pos) => latest[pos] = entry,
+                None => {
+                    positions.insert(&entry.id, latest.len());
+                    latest.push(entry);
+                }
+            }
+        }
+        let existing = latest
+            .into_iter()
+            .find(|entry| entry.matches(data, key) && !self.pending.contains(&entry.id))
+            .map(|entry| entry.id.clone());
+
+        let id = existing.unwrap_or_else(|| self.mint_id());
+        self.pending.push(id.clone());
+        id
+    }
+
+    fn mint_id(&self) -> String {
+        let year = chrono::Local::now().year();
+        let known = || {
+            self.entries
+                .iter()
+                .map(|entry| entry.id.as_str())
+                .chain(self.pending.iter().map(String::as_str))
+        };
+
+        let mut seq = known()
+            .filter_map(|id| self.format.sequence_of(id, year))
+            .max()
+            .unwrap_or(0);
+        loop {
+            seq += 1;
+            let id = self.format.render(year, seq);
+            if !known().any(|known| known == id) {
+                return id;
+            }
+        }
+    }
+
+    /// Trägt eine erzeugte Datei (Sprachfassung `language`) mit ihrem SHA-256 ins Register ein
+    pub fn record(
+        &mut self,
+        id: &str,
+        data: &CertificateData,
+        key: &RecordKey,
+        language: Option<Locale>,
+        file: &Path,
+    ) -> Result<RegistryEntry> {
+        let entry = RegistryEntry {
+            id: id.to_string(),
+            name: data.name.clone(),
+            row: key.row,
+            email: key.email.clone(),
+            title: data.custom_fields.get("TITLE").cloned(),
+            date: storage_text(data.date),
+            date_from: data.date_from.map(storage_text),
+            date_to: data.date_to.map(storage_text),
+            language,
+            file: file.to_string_lossy().to_string(),
+            sha256: sha256_file(file)?,
+            issued_at: chrono::Local::now().to_rfc3339(),
+        };
+
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+
+        self.pending.retain(|pending| pending != id);
+        self.entries.push(entry.clone());
+        Ok(entry)
+    }
+}
+
+/// Vergleicht ein gespeichertes Datum unabhängig vom Eingabeformat
+fn same_date(stored: Option<&str>, date: Option<NaiveDate>) -> bool {
+    match (stored, date) {
+        (Some(stored), Some(date)) => parse_date(stored) == Ok(date),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// SHA-256 einer Datei als Hex-String
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut hasher = openssl::sha::Sha256::new();
+    let mut file = File::open(path)?;
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hasher.finish().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(name: &str, title: &str) -> CertificateData {
+        let mut data = CertificateData::new(name.into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        data.add_field("TITLE".into(), title.into());
+        data
+    }
+
+    #[test]
+    fn test_pattern_format() {
+        let format: IdFormat = "B1-{year}-{seq:6}".parse().unwrap();
+        assert_eq!(format.render(2024, 123), "B1-2024-000123");
+        assert_eq!(format.sequence_of("B1-2024-000123", 2024), Some(123));
+        assert_eq!(format.sequence_of("B1-2023-000123", 2024), None);
+        assert!("B1-{year}".parse::<IdFormat>().is_err());
+        assert_eq!("ulid".parse::<IdFormat>().unwrap(), IdFormat::Ulid);
+    }
+
+    #[test]
+    fn test_ids_are_reused_only_for_the_same_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("registry.jsonl");
+        let pdf = dir.path().join("max.pdf");
+        std::fs::write(&pdf, b"abc").unwrap();
+        let key = |row: usize, email: Option<&str>| RecordKey {
+            row: Some(row),
+            email: email.map(String::from),
+        };
+
+        let format: IdFormat = "T-{seq:3}".parse().unwrap();
+        let mut registry = Registry::open(&path, format.clone()).unwrap();
+        let max = registry.assign_id(&record("Max", "Rust"), &key(0, Some("max@example.com")));
+        assert_eq!(max, "T-001");
+        // Zwei Teilnehmer mit gleichem Namen im selben Kurs bekommen eigene IDs
+        let second = registry.assign_id(&record("Max", "Rust"), &key(1, None));
+        assert_eq!(second, "T-002");
+        assert_eq!(registry.assign_id(&record("Max", "Rust"), &key(2, None)), "T-003");
+
+        let entry = registry
+            .record(&max, &record("Max", "Rust"), &key(0, Some("max@example.com")), None, &pdf)
+            .unwrap();
+        assert_eq!(
+            entry.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        registry.record(&second, &record("Max", "Rust"), &key(1, None), None, &pdf).unwrap();
+
+        // Im nächsten Lauf wird derselbe Datensatz wiedererkannt, alles andere bekommt eine neue ID
+        let mut reopened = Registry::open(&path, format).unwrap();
+        assert_eq!(reopened.find("T-001", None).unwrap().name, "Max");
+        assert_eq!(reopened.assign_id(&record("Max", "Rust"), &key(0, Some("max@example.com"))), "T-001");
+        assert_eq!(reopened.assign_id(&record("Max", "Rust"), &key(0, Some("max@example.org"))), "T-003");
+        assert_eq!(reopened.assign_id(&record("Max", "Python"), &key(0, Some("max@example.com"))), "T-004");
+        assert_eq!(reopened.assign_id(&record("Max", "Rust"), &RecordKey::default()), "T-002");
+        // Die ID ist in diesem Lauf schon vergeben
+        assert_eq!(reopened.assign_id(&record("Max", "Rust"), &RecordKey::default()), "T-005");
+
+        let mut explicit = record("Erika", "Rust");
+        explicit.cert_id = Some("T-042".into());
+        assert_eq!(reopened.assign_id(&explicit, &RecordKey::default()), "T-042");
+    }
+
+    #[test]
+    fn test_ids_survive_reordered_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("registry.jsonl");
+        let pdf = dir.path().join("cert.pdf");
+        std::fs::write(&pdf, b"abc").unwrap();
+        let key = |row: usize, email: &str| RecordKey {
+            row: Some(row),
+            email: Some(email.to_string()),
+        };
+
+        let mut registry = Registry::open(&path, IdFormat::default()).unwrap();
+        let max = registry.assign_id(&record("Max", "Rust"), &key(0, "max@example.com"));
+        let erika = registry.assign_id(&record("Erika", "Rust"), &key(1, "erika@example.com"));
+        registry.record(&max, &record("Max", "Rust"), &key(0, "max@example.com"), None, &pdf).unwrap();
+        registry.record(&erika, &record("Erika", "Rust"), &key(1, "erika@example.com"), None, &pdf).unwrap();
+
+        // Neue Zeile vorne, die übrigen vertauscht
+        let mut reopened = Registry::open(&path, IdFormat::default()).unwrap();
+        let new = reopened.assign_id(&record("Anna", "Rust"), &key(0, "anna@example.com"));
+        assert_eq!(reopened.assign_id(&record("Erika", "Rust"), &key(1, "erika@example.com")), erika);
+        assert_eq!(reopened.assign_id(&record("Max", "Rust"), &key(2, "max@example.com")), max);
+        assert!(new != max && new != erika);
+    }
+
+    #[test]
+    fn test_language_versions_share_an_id() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("registry.jsonl");
+        let german = dir.path().join("max_de.pdf");
+        let english = dir.path().join("max_en.pdf");
+        std::fs::write(&german, b"de").unwrap();
+        std::fs::write(&english, b"en").unwrap();
+
+        let mut registry = Registry::open(&path, IdFormat::default()).unwrap();
+        let data = record("Max", "Rust");
+        let id = registry.assign_id(&data, &RecordKey::default());
+        registry.record(&id, &data, &RecordKey::default(), Some(Locale::De), &german).unwrap();
+        registry.record(&id, &data, &RecordKey::default(), Some(Locale::En), &english).unwrap();
+
+        let reopened = Registry::open(&path, IdFormat::default()).unwrap();
+        let de = reopened.find(&id, Some(Locale::De)).unwrap();
+        assert_eq!(de.sha256, sha256_file(&german).unwrap());
+        let en = reopened.find(&id, Some(Locale::En)).unwrap();
+        assert_eq!(en.sha256, sha256_file(&english).unwrap());
+        assert!(reopened.find(&id, None).is_none());
+
+        let files: Vec<Option<Locale>> = reopened.files(&id).iter().map(|entry| entry.language).collect();
+        assert_eq!(files, vec![Some(Locale::De), Some(Locale::En)]);
+    }
+}
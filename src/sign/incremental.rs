@@ -0,0 +1,211 @@
+//! Inkrementelles Update einer PDF-Datei: neue und geänderte Objekte werden
+//! hinter den Originalinhalt geschrieben, die signierten Bytes bleiben unverändert.
+
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+pub(crate) struct IncrementalUpdate {
+    buffer: Vec<u8>,
+    offsets: BTreeMap<ObjectId, usize>,
+}
+
+impl IncrementalUpdate {
+    pub fn new(original: &[u8]) -> Self {
+        let mut buffer = original.to_vec();
+        if !buffer.ends_with(b"\n") {
+            buffer.push(b'\n');
+        }
+        Self {
+            buffer,
+            offsets: BTreeMap::new(),
+        }
+    }
+
+    pub fn write_object(&mut self, id: ObjectId, object: &Object) {
+        let mut body = Vec::new();
+        write_object(&mut body, object);
+        self.write_raw_object(id, &body);
+    }
+
+    /// Schreibt ein bereits serialisiertes Objekt und liefert den Offset von `body` in der Datei
+    pub fn write_raw_object(&mut self, id: ObjectId, body: &[u8]) -> usize {
+        self.offsets.insert(id, self.buffer.len());
+        let _ = writeln!(self.buffer, "{} {} obj", id.0, id.1);
+        let start = self.buffer.len();
+        self.buffer.extend_from_slice(body);
+        self.buffer.extend_from_slice(b"\nendobj\n");
+        start
+    }
+
+    /// Hängt Xref-Tabelle und Trailer an und liefert die komplette Datei
+    pub fn finish(mut self, mut trailer: Dictionary, prev_xref: usize) -> Vec<u8> {
+        let xref_offset = self.buffer.len();
+        self.buffer.extend_from_slice(b"xref\n");
+
+        let entries: Vec<(ObjectId, usize)> = self.offsets.iter().map(|(id, offset)| (*id, *offset)).collect();
+        let mut start = 0;
+        while start < entries.len() {
+            // Aufeinanderfolgende Objektnummern bilden einen Unterabschnitt
+            let mut end = start + 1;
+            while end < entries.len() && entries[end].0 .0 == entries[end - 1].0 .0 + 1 {
+                end += 1;
+            }
+            let _ = writeln!(self.buffer, "{} {}", entries[start].0 .0, end - start);
+            for ((_, generation), offset) in &entries[start..end] {
+                let _ = write!(self.buffer, "{:010} {:05} n\r\n", offset, generation);
+            }
+            start = end;
+        }
+
+        trailer.set("Prev", Object::Integer(prev_xref as i64));
+        self.buffer.extend_from_slice(b"trailer\n");
+        write_object(&mut self.buffer, &Object::Dictionary(trailer));
+        let _ = write!(self.buffer, "\nstartxref\n{}\n%%EOF\n", xref_offset);
+        self.buffer
+    }
+}
+
+/// Serialisiert ein Objekt in PDF-Syntax
+pub(crate) fn write_object(out: &mut Vec<u8>, object: &Object) {
+    match object {
+        Object::Null => out.extend_from_slice(b"null"),
+        Object::Boolean(value) => {
+            let _ = write!(out, "{}", value);
+        }
+        Object::Integer(value) => {
+            let _ = write!(out, "{}", value);
+        }
+        Object::Real(value) => write_real(out, *value),
+        Object::Name(name) => write_name(out, name),
+        Object::String(bytes, StringFormat::Literal) => write_literal_string(out, bytes),
+        Object::String(bytes, StringFormat::Hexadecimal) => {
+            out.push(b'<');
+            out.extend_from_slice(to_hex(bytes).as_bytes());
+            out.push(b'>');
+        }
+        Object::Array(items) => {
+            out.push(b'[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(b' ');
+                }
+                write_object(out, item);
+            }
+            out.push(b']');
+        }
+        Object::Dictionary(dict) => write_dictionary(out, dict),
+        Object::Stream(stream) => {
+            let mut dict = stream.dict.clone();
+            dict.set("Length", Object::Integer(stream.content.len() as i64));
+            write_dictionary(out, &dict);
+            out.extend_from_slice(b"\nstream\n");
+            out.extend_from_slice(&stream.content);
+            out.extend_from_slice(b"\nendstream");
+        }
+        Object::Reference((id, generation)) => {
+            let _ = write!(out, "{} {} R", id, generation);
+        }
+    }
+}
+
+pub(crate) fn write_dictionary(out: &mut Vec<u8>, dict: &Dictionary) {
+    out.extend_from_slice(b"<<");
+    write_dictionary_entries(out, dict);
+    out.extend_from_slice(b" >>");
+}
+
+/// Nur die Einträge, ohne `<<` und `>>`
+pub(crate) fn write_dictionary_entries(out: &mut Vec<u8>, dict: &Dictionary) {
+    for (key, value) in dict.iter() {
+        out.push(b' ');
+        write_name(out, key);
+        out.push(b' ');
+        write_object(out, value);
+    }
+}
+
+fn write_real(out: &mut Vec<u8>, value: f32) {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    out.extend_from_slice(if text.is_empty() || text == "-" { "0" } else { text }.as_bytes());
+}
+
+fn write_name(out: &mut Vec<u8>, name: &[u8]) {
+    out.push(b'/');
+    for &byte in name {
+        let regular = byte.is_ascii_graphic()
+            && !matches!(byte, b'#' | b'/' | b'%' | b'(' | b')' | b'<' | b'>' | b'[' | b']' | b'{' | b'}');
+        if regular {
+            out.push(byte);
+        } else {
+            let _ = write!(out, "#{:02X}", byte);
+        }
+    }
+}
+
+fn write_literal_string(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.push(b'(');
+    for &byte in bytes {
+        match byte {
+            b'(' | b')' | b'\\' => out.extend_from_slice(&[b'\\', byte]),
+            b'\r' => out.extend_from_slice(b"\\r"),
+            b'\n' => out.extend_from_slice(b"\\n"),
+            _ => out.push(byte),
+        }
+    }
+    out.push(b')');
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02X}", b)).collect()
+}
+
+/// PDF-Textstring: ASCII bleibt lesbar, alles andere als UTF-16BE mit BOM
+pub(crate) fn text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        Object::String(text.as_bytes().to_vec(), StringFormat::Literal)
+    } else {
+        let mut bytes = vec![0xFE, 0xFF];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_be_bytes());
+        }
+        Object::String(bytes, StringFormat::Hexadecimal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_objects() {
+        let mut dict = Dictionary::new();
+        dict.set("Type", Object::Name(b"Annot".to_vec()));
+        dict.set("Rect", Object::Array(vec![0.into(), Object::Real(1.5), Object::Real(20.0)]));
+        dict.set("T", Object::string_literal("Sig (1)"));
+        dict.set("P", Object::Reference((4, 0)));
+        dict.set("N", Object::Name(b"A B".to_vec()));
+
+        let mut out = Vec::new();
+        write_object(&mut out, &Object::Dictionary(dict));
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "<< /Type /Annot /Rect [0 1.5 20] /T (Sig \\(1\\)) /P 4 0 R /N /A#20B >>"
+        );
+    }
+
+    #[test]
+    fn test_xref_subsections() {
+        let mut update = IncrementalUpdate::new(b"%PDF-1.7\n");
+        update.write_object((7, 0), &Object::Null);
+        update.write_object((1, 0), &Object::Null);
+        update.write_object((8, 0), &Object::Null);
+        let pdf = String::from_utf8(update.finish(Dictionary::new(), 123)).unwrap();
+
+        assert!(pdf.contains("xref\n1 1\n"));
+        assert!(pdf.contains("7 2\n"));
+        assert!(pdf.contains("/Prev 123"));
+        assert!(pdf.ends_with("%%EOF\n"));
+    }
+}
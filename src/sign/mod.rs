@@ -0,0 +1,714 @@
+//! Digitale Signatur der erzeugten PDFs (CMS, `adbe.pkcs7.detached`) und deren Prüfung.
+//!
+//! Die Signatur wird als inkrementelles Update angehängt, sodass die von
+//! LibreOffice erzeugten Bytes (inkl. PDF/A-Metadaten) unverändert bleiben.
+
+mod incremental;
+
+use crate::error::{CertgenError, Result};
+use crate::source::write_atomic;
+use incremental::{to_hex, text_string, write_dictionary_entries, IncrementalUpdate};
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream};
+use openssl::hash::MessageDigest;
+use openssl::pkcs12::Pkcs12;
+use openssl::pkcs7::{Pkcs7, Pkcs7Flags};
+use openssl::pkey::{PKey, Private};
+use openssl::stack::Stack;
+use openssl::x509::store::{X509Store, X509StoreBuilder};
+use openssl::x509::X509;
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// Reservierter Platz für die CMS-Signatur (DER) im `/Contents`-Eintrag
+const SIGNATURE_CAPACITY: usize = 16 * 1024;
+
+/// Platzhalter fester Breite, wird nach dem Schreiben durch die echten Offsets ersetzt
+const BYTE_RANGE_PLACEHOLDER: &str = "[0 0000000000 0000000000 0000000000]";
+
+/// Privater Schlüssel samt Zertifikat (und optionaler Kette) zum Signieren
+pub struct SigningKey {
+    pkey: PKey<Private>,
+    cert: X509,
+    chain: Vec<X509>,
+}
+
+impl SigningKey {
+    /// Lädt Schlüssel und Zertifikate aus einer PKCS#12-Datei (`.p12`/`.pfx`)
+    pub fn from_pkcs12(path: &Path, password: &str) -> Result<Self> {
+        let der = std::fs::read(path)?;
+        let parsed = Pkcs12::from_der(&der)
+            .and_then(|p12| p12.parse2(password))
+            .map_err(|e| signature_error(format!("cannot read {}: {}", path.display(), e)))?;
+
+        let pkey = parsed
+            .pkey
+            .ok_or_else(|| signature_error(format!("{} contains no private key", path.display())))?;
+        let cert = parsed
+            .cert
+            .ok_or_else(|| signature_error(format!("{} contains no certificate", path.display())))?;
+        let chain = parsed.ca.map(|ca| ca.into_iter().collect()).unwrap_or_default();
+
+        Self::new(pkey, cert, chain)
+    }
+
+    /// Lädt einen PEM-Schlüssel und ein PEM-Zertifikat; weitere Zertifikate in
+    /// `cert_path` werden als Kette mitgeschickt
+    pub fn from_pem(key_path: &Path, cert_path: &Path, password: Option<&str>) -> Result<Self> {
+        let key_pem = std::fs::read(key_path)?;
+        let pkey = match password {
+            Some(password) => PKey::private_key_from_pem_passphrase(&key_pem, password.as_bytes()),
+            None => PKey::private_key_from_pem(&key_pem),
+        }
+        .map_err(|e| signature_error(format!("cannot read {}: {}", key_path.display(), e)))?;
+
+        let mut certs = X509::stack_from_pem(&std::fs::read(cert_path)?)
+            .map_err(|e| signature_error(format!("cannot read {}: {}", cert_path.display(), e)))?
+            .into_iter();
+        let cert = certs
+            .next()
+            .ok_or_else(|| signature_error(format!("{} contains no certificate", cert_path.display())))?;
+
+        Self::new(pkey, cert, certs.collect())
+    }
+
+    fn new(pkey: PKey<Private>, cert: X509, chain: Vec<X509>) -> Result<Self> {
+        if !cert.public_key()?.public_eq(&pkey) {
+            return Err(signature_error("private key does not match the certificate"));
+        }
+        Ok(Self { pkey, cert, chain })
+    }
+
+    /// Common Name des Zertifikats, für Signaturfeld und Sichtbarkeit
+    pub fn signer_name(&self) -> String {
+        common_name(&self.cert)
+    }
+
+    /// Erstellt eine abgetrennte PKCS#7-Signatur über `data`
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let mut chain = Stack::new()?;
+        for cert in &self.chain {
+            chain.push(cert.clone())?;
+        }
+        let flags = Pkcs7Flags::DETACHED | Pkcs7Flags::BINARY | Pkcs7Flags::NOSMIMECAP;
+        let pkcs7 = Pkcs7::sign(&self.cert, &self.pkey, &chain, data, flags)?;
+        Ok(pkcs7.to_der()?)
+    }
+}
+
+/// Position einer sichtbaren Signatur in PDF-Punkten (Ursprung unten links)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignatureRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl FromStr for SignatureRect {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let values: Vec<f32> = s
+            .split(',')
+            .map(|v| v.trim().parse::<f32>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| format!("invalid rectangle `{}`: expected x,y,width,height", s))?;
+
+        match values[..] {
+            [x, y, width, height] if width > 0.0 && height > 0.0 => Ok(Self { x, y, width, height }),
+            _ => Err(format!("invalid rectangle `{}`: expected x,y,width,height", s)),
+        }
+    }
+}
+
+impl fmt::Display for SignatureRect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Sichtbares Signaturfeld auf einer Seite (1 = erste Seite)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SignatureAppearance {
+    pub page: u32,
+    pub rect: SignatureRect,
+}
+
+/// Signiert PDF-Dateien mit einem [`SigningKey`]
+pub struct PdfSigner {
+    key: SigningKey,
+    reason: Option<String>,
+    location: Option<String>,
+    appearance: Option<SignatureAppearance>,
+}
+
+impl PdfSigner {
+    /// Unsichtbare Signatur ohne Grund und Ort
+    pub fn new(key: SigningKey) -> Self {
+        Self {
+            key,
+            reason: None,
+            location: None,
+            appearance: None,
+        }
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_location(mut self, location: impl Into<String>) -> Self {
+        self.location = Some(location.into());
+        self
+    }
+
+    /// Zeigt die Signatur als Kasten auf der angegebenen Seite an
+    pub fn with_appearance(mut self, appearance: SignatureAppearance) -> Self {
+        self.appearance = Some(appearance);
+        self
+    }
+
+    /// Zertifikat und Einstellungen der Signatur als Text für einen Inhalts-Hash
+    pub fn settings(&self) -> Result<String> {
+        let digest = self.key.cert.digest(MessageDigest::sha256())?;
+        Ok(format!(
+            "cert={} reason={:?} location={:?} appearance={:?}",
+            to_hex(&digest),
+            self.reason,
+            self.location,
+            self.appearance
+        ))
+    }
+
+    /// Signiert die Datei an Ort und Stelle; sie wird atomar ersetzt, ein Abbruch
+    /// hinterlässt also nie eine halb geschriebene PDF
+    pub fn sign_file(&self, path: &Path) -> Result<()> {
+        let signed = self.sign_bytes(&std::fs::read(path)?)?;
+        write_atomic(path, &signed)
+    }
+
+    /// Hängt Signaturfeld und Signatur als inkrementelles Update an `pdf` an
+    pub fn sign_bytes(&self, pdf: &[u8]) -> Result<Vec<u8>> {
+        let doc = Document::load_mem(pdf)?;
+        if doc.is_encrypted() {
+            return Err(signature_error("encrypted PDFs cannot be signed"));
+        }
+
+        let root_id = doc.trailer.get(b"Root").and_then(Object::as_reference)?;
+        let page_number = self.appearance.map(|a| a.page).unwrap_or(1);
+        let page_id = *doc
+            .get_pages()
+            .get(&page_number)
+            .ok_or_else(|| signature_error(format!("PDF has no page {}", page_number)))?;
+
+        let sig_id = (doc.max_id + 1, 0);
+        let widget_id = (doc.max_id + 2, 0);
+        let appearance_id = (doc.max_id + 3, 0);
+        let now = chrono::Local::now();
+
+        // Signaturfeld im AcroForm des Katalogs eintragen
+        let mut catalog = doc.catalog()?.clone();
+        let mut acro_form = match catalog.get(b"AcroForm") {
+            Ok(obj) => doc.dereference(obj)?.1.as_dict()?.clone(),
+            Err(_) => Dictionary::new(),
+        };
+        let mut fields = match acro_form.get(b"Fields") {
+            Ok(obj) => doc.dereference(obj)?.1.as_array()?.clone(),
+            Err(_) => Vec::new(),
+        };
+        let field_name = format!("Signatur{}", fields.len() + 1);
+        fields.push(Object::Reference(widget_id));
+        acro_form.set("Fields", Object::Array(fields));
+        acro_form.set("SigFlags", Object::Integer(3));
+        catalog.set("AcroForm", Object::Dictionary(acro_form));
+
+        // Widget-Annotation auf der Seite verankern
+        let mut page = doc.get_dictionary(page_id)?.clone();
+        let mut annots = match page.get(b"Annots") {
+            Ok(obj) => doc.dereference(obj)?.1.as_array()?.clone(),
+            Err(_) => Vec::new(),
+        };
+        annots.push(Object::Reference(widget_id));
+        page.set("Annots", Object::Array(annots));
+
+        let mut widget = Dictionary::new();
+        widget.set("Type", Object::Name(b"Annot".to_vec()));
+        widget.set("Subtype", Object::Name(b"Widget".to_vec()));
+        widget.set("FT", Object::Name(b"Sig".to_vec()));
+        widget.set("T", text_string(&field_name));
+        widget.set("V", Object::Reference(sig_id));
+        widget.set("P", Object::Reference(page_id));
+        // Print + Locked
+        widget.set("F", Object::Integer(132));
+
+        let appearance = match self.appearance {
+            Some(SignatureAppearance { rect: r, .. }) => {
+                widget.set(
+                    "Rect",
+                    Object::Array(vec![
+                        Object::Real(r.x),
+                        Object::Real(r.y),
+                        Object::Real(r.x + r.width),
+                        Object::Real(r.y + r.height),
+                    ]),
+                );
+                let mut ap = Dictionary::new();
+                ap.set("N", Object::Reference(appearance_id));
+                widget.set("AP", Object::Dictionary(ap));
+
+                let lines = appearance_lines(&self.key.signer_name(), &now, self.reason.as_deref());
+                Some(appearance_stream(r, &lines))
+            }
+            None => {
+                widget.set("Rect", Object::Array(vec![0.into(), 0.into(), 0.into(), 0.into()]));
+                None
+            }
+        };
+
+        let mut update = IncrementalUpdate::new(pdf);
+        let sig_body = signature_dictionary(&self.key.signer_name(), &now, self.reason.as_deref(), self.location.as_deref());
+        let sig_offset = update.write_raw_object(sig_id, &sig_body);
+        update.write_object(widget_id, &Object::Dictionary(widget));
+        let next_id = if appearance.is_some() { appearance_id.0 } else { widget_id.0 } + 1;
+        if let Some(stream) = appearance {
+            update.write_object(appearance_id, &Object::Stream(stream));
+        }
+        update.write_object(page_id, &Object::Dictionary(page));
+        update.write_object(root_id, &Object::Dictionary(catalog));
+
+        let mut trailer = Dictionary::new();
+        let size = doc.trailer.get(b"Size").and_then(Object::as_i64).unwrap_or(0);
+        trailer.set("Size", Object::Integer(size.max(i64::from(next_id))));
+        trailer.set("Root", Object::Reference(root_id));
+        for key in [b"Info".as_slice(), b"ID".as_slice()] {
+            if let Ok(value) = doc.trailer.get(key) {
+                trailer.set(key, value.clone());
+            }
+        }
+        let mut output = update.finish(trailer, doc.xref_start);
+
+        self.embed_signature(&mut output, sig_offset)?;
+        Ok(output)
+    }
+
+    /// Trägt `/ByteRange` ein und schreibt die Signatur über alle Bytes außer `/Contents`
+    fn embed_signature(&self, output: &mut [u8], sig_offset: usize) -> Result<()> {
+        let body = output.get(sig_offset..).unwrap_or_default();
+        let range_pos = sig_offset
+            + find(body, BYTE_RANGE_PLACEHOLDER.as_bytes())
+                .ok_or_else(|| signature_error("no /ByteRange placeholder in the signature dictionary"))?;
+        let contents_start = sig_offset
+            + find(body, b"/Contents <")
+                .ok_or_else(|| signature_error("no /Contents placeholder in the signature dictionary"))?
+            + b"/Contents ".len();
+        let contents_end = contents_start + 2 * SIGNATURE_CAPACITY + 2;
+        if contents_end > output.len() {
+            return Err(signature_error("the /Contents placeholder is truncated"));
+        }
+
+        let byte_range = format!(
+            "[0 {} {} {}]",
+            contents_start,
+            contents_end,
+            output.len() - contents_end
+        );
+        let byte_range = format!("{:<width$}", byte_range, width = BYTE_RANGE_PLACEHOLDER.len());
+        output[range_pos..range_pos + byte_range.len()].copy_from_slice(byte_range.as_bytes());
+
+        let mut signed_data = output[..contents_start].to_vec();
+        signed_data.extend_from_slice(&output[contents_end..]);
+        let signature = self.key.sign(&signed_data)?;
+        if signature.len() > SIGNATURE_CAPACITY {
+            return Err(signature_error(format!(
+                "signature needs {} bytes but only {} are reserved",
+                signature.len(),
+                SIGNATURE_CAPACITY
+            )));
+        }
+
+        let hex = to_hex(&signature);
+        output[contents_start + 1..contents_start + 1 + hex.len()].copy_from_slice(hex.as_bytes());
+        Ok(())
+    }
+}
+
+/// Signaturobjekt mit Platzhaltern für `/ByteRange` und `/Contents`
+fn signature_dictionary(
+    signer: &str,
+    time: &chrono::DateTime<chrono::Local>,
+    reason: Option<&str>,
+    location: Option<&str>,
+) -> Vec<u8> {
+    let mut body = format!(
+        "<< /Type /Sig /Filter /Adobe.PPKLite /SubFilter /adbe.pkcs7.detached /ByteRange {} /Contents <{}>",
+        BYTE_RANGE_PLACEHOLDER,
+        "0".repeat(2 * SIGNATURE_CAPACITY)
+    )
+    .into_bytes();
+
+    let mut dict = Dictionary::new();
+    dict.set("M", Object::string_literal(pdf_date(time)));
+    dict.set("Name", text_string(signer));
+    if let Some(reason) = reason {
+        dict.set("Reason", text_string(reason));
+    }
+    if let Some(location) = location {
+        dict.set("Location", text_string(location));
+    }
+    write_dictionary_entries(&mut body, &dict);
+    body.extend_from_slice(b" >>");
+    body
+}
+
+fn appearance_lines(signer: &str, time: &chrono::DateTime<chrono::Local>, reason: Option<&str>) -> Vec<String> {
+    let mut lines = vec![
+        "Digital signiert von".to_string(),
+        signer.to_string(),
+        format!("Datum: {}", time.format("%d.%m.%Y %H:%M")),
+    ];
+    if let Some(reason) = reason {
+        lines.push(format!("Grund: {}", reason));
+    }
+    lines
+}
+
+/// Formular-XObject mit Rahmen und Text in Helvetica (WinAnsi)
+fn appearance_stream(rect: SignatureRect, lines: &[String]) -> Stream {
+    let font_size = (rect.height / (lines.len() as f32 * 1.25 + 0.5)).min(10.0);
+    let leading = font_size * 1.25;
+
+    let mut content = format!(
+        "q 0.4 G 0.5 w 0.25 0.25 {:.2} {:.2} re S Q\nBT /Helv {:.2} Tf {:.2} TL 3 {:.2} Td\n",
+        rect.width - 0.5,
+        rect.height - 0.5,
+        font_size,
+        leading,
+        rect.height - leading
+    )
+    .into_bytes();
+    for (i, line) in lines.iter().enumerate() {
+        if i > 0 {
+            content.extend_from_slice(b"T* ");
+        }
+        incremental::write_object(
+            &mut content,
+            &Object::String(win_ansi(line), lopdf::StringFormat::Literal),
+        );
+        content.extend_from_slice(b" Tj\n");
+    }
+    content.extend_from_slice(b"ET");
+
+    let mut font = Dictionary::new();
+    font.set("Type", Object::Name(b"Font".to_vec()));
+    font.set("Subtype", Object::Name(b"Type1".to_vec()));
+    font.set("BaseFont", Object::Name(b"Helvetica".to_vec()));
+    font.set("Encoding", Object::Name(b"WinAnsiEncoding".to_vec()));
+    let mut fonts = Dictionary::new();
+    fonts.set("Helv", Object::Dictionary(font));
+    let mut resources = Dictionary::new();
+    resources.set("Font", Object::Dictionary(fonts));
+
+    let mut dict = Dictionary::new();
+    dict.set("Type", Object::Name(b"XObject".to_vec()));
+    dict.set("Subtype", Object::Name(b"Form".to_vec()));
+    dict.set(
+        "BBox",
+        Object::Array(vec![0.into(), 0.into(), Object::Real(rect.width), Object::Real(rect.height)]),
+    );
+    dict.set("Resources", Object::Dictionary(resources));
+    Stream::new(dict, content)
+}
+
+/// Latin-1-Zeichen passen direkt in WinAnsi, alles andere wird zu `?`
+fn win_ansi(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).ok().filter(|b| *b >= 0x20).unwrap_or(b'?'))
+        .collect()
+}
+
+fn pdf_date(time: &chrono::DateTime<chrono::Local>) -> String {
+    let offset = time.format("%z").to_string();
+    format!("D:{}{}'{}'", time.format("%Y%m%d%H%M%S"), &offset[..3], &offset[3..])
+}
+
+/// Ergebnis der Prüfung einer einzelnen Signatur
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureInfo {
+    /// Common Name des Signaturzertifikats
+    pub signer: Option<String>,
+    /// `/M` aus dem Signaturobjekt
+    pub signing_time: Option<String>,
+    pub reason: Option<String>,
+    /// Die Signatur deckt die gesamte Datei ab (keine späteren Änderungen)
+    pub covers_document: bool,
+    /// Die signierten Bytes sind unverändert
+    pub intact: bool,
+    /// Zertifikatskette gegen die angegebenen CAs geprüft; `None` ohne CA-Datei
+    pub trusted: Option<bool>,
+    pub error: Option<String>,
+}
+
+impl SignatureInfo {
+    pub fn is_valid(&self) -> bool {
+        self.intact && self.covers_document && self.trusted != Some(false)
+    }
+}
+
+/// Lädt vertrauenswürdige CA-Zertifikate aus einer PEM-Datei
+pub fn load_trust_store(path: &Path) -> Result<X509Store> {
+    let certs = X509::stack_from_pem(&std::fs::read(path)?)
+        .map_err(|e| signature_error(format!("cannot read {}: {}", path.display(), e)))?;
+    let mut builder = X509StoreBuilder::new()?;
+    for cert in certs {
+        builder.add_cert(cert)?;
+    }
+    Ok(builder.build())
+}
+
+/// Prüft alle Signaturen einer PDF-Datei; ohne `trust` nur die Integrität
+pub fn verify_pdf(pdf: &[u8], trust: Option<&X509Store>) -> Result<Vec<SignatureInfo>> {
+    let doc = Document::load_mem(pdf)?;
+
+    let mut signatures: Vec<(ObjectId, &Dictionary)> = doc
+        .objects
+        .iter()
+        .filter_map(|(id, obj)| obj.as_dict().ok().map(|dict| (*id, dict)))
+        .filter(|(_, dict)| dict.has(b"ByteRange") && dict.has(b"Contents"))
+        .filter(|(_, dict)| dict.get(b"Type").and_then(Object::as_name).map_or(true, |t| t == b"Sig"))
+        .collect();
+    signatures.sort_by_key(|(id, _)| *id);
+
+    Ok(signatures
+        .into_iter()
+        .map(|(_, dict)| verify_signature(pdf, dict, trust))
+        .collect())
+}
+
+fn verify_signature(pdf: &[u8], dict: &Dictionary, trust: Option<&X509Store>) -> SignatureInfo {
+    let text = |key: &[u8]| dict.get(key).and_then(lopdf::decode_text_string).ok();
+    let mut info = SignatureInfo {
+        signer: None,
+        signing_time: text(b"M"),
+        reason: text(b"Reason"),
+        covers_document: false,
+        intact: false,
+        trusted: None,
+        error: None,
+    };
+    if let Err(e) = check_signature(pdf, dict, trust, &mut info) {
+        info.error = Some(e.to_string());
+    }
+    info
+}
+
+fn check_signature(pdf: &[u8], dict: &Dictionary, trust: Option<&X509Store>, info: &mut SignatureInfo) -> Result<()> {
+    let range: Vec<usize> = dict
+        .get(b"ByteRange")
+        .and_then(Object::as_array)?
+        .iter()
+        .map(|v| v.as_i64().ok().and_then(|v| usize::try_from(v).ok()))
+        .collect::<Option<_>>()
+        .filter(|r: &Vec<usize>| r.len() == 4)
+        .ok_or_else(|| signature_error("malformed /ByteRange"))?;
+    let (start, first_len, second_start, second_len) = (range[0], range[1], range[2], range[3]);
+    if start + first_len > second_start || second_start + second_len > pdf.len() {
+        return Err(signature_error("/ByteRange points outside the file"));
+    }
+    info.covers_document = start == 0 && second_start + second_len == pdf.len();
+
+    let mut signed_data = pdf[start..start + first_len].to_vec();
+    signed_data.extend_from_slice(&pdf[second_start..second_start + second_len]);
+
+    // /Contents ist mit Nullen aufgefüllt, nur die eigentliche DER-Struktur parsen
+    let contents = dict.get(b"Contents").and_then(Object::as_str)?;
+    let der = &contents[..der_length(contents).unwrap_or(contents.len()).min(contents.len())];
+    let pkcs7 = Pkcs7::from_der(der).map_err(|e| signature_error(format!("invalid signature container: {}", e)))?;
+
+    let no_certs = Stack::new()?;
+    info.signer = pkcs7
+        .signers(&no_certs, Pkcs7Flags::empty())
+        .ok()
+        .and_then(|signers| signers.iter().next().map(common_name));
+
+    let empty_store = X509StoreBuilder::new()?.build();
+    info.intact = pkcs7
+        .verify(&no_certs, &empty_store, Some(&signed_data), None, Pkcs7Flags::NOVERIFY)
+        .is_ok();
+
+    if let Some(store) = trust {
+        let result = pkcs7.verify(&no_certs, store, Some(&signed_data), None, Pkcs7Flags::empty());
+        info.trusted = Some(result.is_ok());
+        if let Err(e) = result {
+            return Err(signature_error(format!("certificate not trusted: {}", e)));
+        }
+    }
+    Ok(())
+}
+
+/// Gesamtlänge einer DER-SEQUENCE anhand ihres Headers
+fn der_length(der: &[u8]) -> Option<usize> {
+    match der {
+        [0x30, len, ..] if *len < 0x80 => Some(2 + usize::from(*len)),
+        [0x30, marker, rest @ ..] => {
+            let count = usize::from(marker & 0x7f);
+            let bytes = rest.get(..count)?;
+            let len = bytes.iter().fold(0usize, |acc, b| (acc << 8) | usize::from(*b));
+            Some(2 + count + len)
+        }
+        _ => None,
+    }
+}
+
+fn common_name(cert: &openssl::x509::X509Ref) -> String {
+    cert.subject_name()
+        .entries_by_nid(openssl::nid::Nid::COMMONNAME)
+        .next()
+        .and_then(|entry| entry.data().to_string().ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack.windows(needle.len()).position(|window| window == needle)
+}
+
+fn signature_error(message: impl Into<String>) -> CertgenError {
+    CertgenError::Signature(message.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::hash::MessageDigest;
+    use openssl::rsa::Rsa;
+    use openssl::x509::X509NameBuilder;
+
+    /// Minimales einseitiges PDF mit klassischer Xref-Tabelle
+    fn minimal_pdf() -> Vec<u8> {
+        let objects = [
+            "<< /Type /Catalog /Pages 2 0 R >>",
+            "<< /Type /Pages /Kids [3 0 R] /Count 1 >>",
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] >>",
+        ];
+        let mut pdf = b"%PDF-1.7\n".to_vec();
+        let mut offsets = Vec::new();
+        for (i, obj) in objects.iter().enumerate() {
+            offsets.push(pdf.len());
+            pdf.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, obj).as_bytes());
+        }
+        let xref = pdf.len();
+        pdf.extend_from_slice(b"xref\n0 4\n0000000000 65535 f\r\n");
+        for offset in offsets {
+            pdf.extend_from_slice(format!("{:010} 00000 n\r\n", offset).as_bytes());
+        }
+        pdf.extend_from_slice(format!("trailer\n<< /Size 4 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n", xref).as_bytes());
+        pdf
+    }
+
+    fn test_key(cn: &str) -> SigningKey {
+        let pkey = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_nid(openssl::nid::Nid::COMMONNAME, cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder
+            .set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap())
+            .unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&pkey).unwrap();
+        builder.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        builder.set_not_after(&Asn1Time::days_from_now(30).unwrap()).unwrap();
+        builder.sign(&pkey, MessageDigest::sha256()).unwrap();
+
+        SigningKey::new(pkey, builder.build(), Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn test_sign_and_verify() {
+        let signer = PdfSigner::new(test_key("Schulungszentrum Nürnberg")).with_reason("Teilnahmebestätigung");
+        let signed = signer.sign_bytes(&minimal_pdf()).unwrap();
+
+        // Originalbytes bleiben unverändert
+        assert!(signed.starts_with(&minimal_pdf()));
+
+        let signatures = verify_pdf(&signed, None).unwrap();
+        assert_eq!(signatures.len(), 1);
+        let info = &signatures[0];
+        assert!(info.is_valid(), "{:?}", info);
+        assert_eq!(info.signer.as_deref(), Some("Schulungszentrum Nürnberg"));
+        assert_eq!(info.reason.as_deref(), Some("Teilnahmebestätigung"));
+        assert_eq!(info.trusted, None);
+    }
+
+    #[test]
+    fn test_sign_file_and_missing_placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zertifikat.pdf");
+        std::fs::write(&path, minimal_pdf()).unwrap();
+
+        let signer = PdfSigner::new(test_key("Test"));
+        signer.sign_file(&path).unwrap();
+        assert!(verify_pdf(&std::fs::read(&path).unwrap(), None).unwrap()[0].is_valid());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let mut broken = minimal_pdf();
+        assert!(matches!(
+            signer.embed_signature(&mut broken, 0),
+            Err(CertgenError::Signature(_))
+        ));
+    }
+
+    #[test]
+    fn test_tampering_is_detected() {
+        let signed = PdfSigner::new(test_key("Test")).sign_bytes(&minimal_pdf()).unwrap();
+
+        let mut tampered = signed.clone();
+        let pos = find(&tampered, b"595 842").unwrap();
+        tampered[pos..pos + 3].copy_from_slice(b"600");
+        assert!(!verify_pdf(&tampered, None).unwrap()[0].intact);
+
+        let mut appended = signed;
+        appended.extend_from_slice(b"% appended later\n");
+        let info = &verify_pdf(&appended, None).unwrap()[0];
+        assert!(info.intact);
+        assert!(!info.covers_document);
+    }
+
+    #[test]
+    fn test_visible_signature_and_trust() {
+        let key = test_key("Aussteller");
+        let ca = key.cert.clone();
+        let signer = PdfSigner::new(key).with_appearance(SignatureAppearance {
+            page: 1,
+            rect: "36,36,200,50".parse().unwrap(),
+        });
+        let signed = signer.sign_bytes(&minimal_pdf()).unwrap();
+
+        let doc = Document::load_mem(&signed).unwrap();
+        let page = doc.get_dictionary(doc.get_pages()[&1]).unwrap();
+        assert_eq!(page.get(b"Annots").unwrap().as_array().unwrap().len(), 1);
+
+        let mut store = X509StoreBuilder::new().unwrap();
+        store.add_cert(ca).unwrap();
+        let info = &verify_pdf(&signed, Some(&store.build())).unwrap()[0];
+        assert_eq!(info.trusted, Some(true));
+
+        let other = X509StoreBuilder::new().unwrap().build();
+        assert_eq!(verify_pdf(&signed, Some(&other)).unwrap()[0].trusted, Some(false));
+    }
+
+    #[test]
+    fn test_parse_signature_rect() {
+        let rect: SignatureRect = "36, 40.5, 200, 50".parse().unwrap();
+        assert_eq!(rect, SignatureRect { x: 36.0, y: 40.5, width: 200.0, height: 50.0 });
+        assert!("36,40,200".parse::<SignatureRect>().is_err());
+        assert!("36,40,0,50".parse::<SignatureRect>().is_err());
+    }
+}
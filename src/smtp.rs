@@ -0,0 +1,554 @@
+//! Versand der erzeugten E-Mails (.eml) per SMTP: STARTTLS oder TLS, Anmeldung mit
+//! PLAIN/LOGIN, Ratenbegrenzung und Wiederholung bei vorübergehenden Fehlern.
+//! Absender und Empfänger des Umschlags stammen aus den Kopfzeilen der Nachricht.
+
+use crate::error::{CertgenError, Result};
+use crate::manifest::{Delivery, DeliveryStatus};
+use lettre::address::{Address, Envelope};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::SmtpTransport;
+use lettre::Transport;
+use log::{debug, warn};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+/// Standardanzahl der Wiederholungen bei vorübergehenden Fehlern
+pub const DEFAULT_RETRIES: u32 = 3;
+/// Wartezeit vor der ersten Wiederholung; sie wächst mit jedem Versuch
+pub const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(5);
+/// Zeitlimit für jeden SMTP-Befehl
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Verschlüsselung der Verbindung zum SMTP-Server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Unverschlüsselt verbinden und mit STARTTLS verschlüsseln (Port 587)
+    #[default]
+    StartTls,
+    /// Verschlüsselte Verbindung von Anfang an (Port 465)
+    Tls,
+    /// Unverschlüsselt, nur für lokale Testserver (Port 25)
+    None,
+}
+
+impl SmtpSecurity {
+    /// Üblicher Port für die Verschlüsselung
+    pub fn default_port(self) -> u16 {
+        match self {
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+            SmtpSecurity::None => 25,
+        }
+    }
+}
+
+impl FromStr for SmtpSecurity {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "starttls" => Ok(SmtpSecurity::StartTls),
+            "tls" => Ok(SmtpSecurity::Tls),
+            "none" => Ok(SmtpSecurity::None),
+            _ => Err(format!("invalid SMTP security `{}`: expected starttls, tls or none", s)),
+        }
+    }
+}
+
+impl fmt::Display for SmtpSecurity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SmtpSecurity::StartTls => "starttls",
+            SmtpSecurity::Tls => "tls",
+            SmtpSecurity::None => "none",
+        })
+    }
+}
+
+/// Anmeldeverfahren am SMTP-Server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMechanism {
+    Plain,
+    Login,
+}
+
+impl FromStr for AuthMechanism {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "plain" => Ok(AuthMechanism::Plain),
+            "login" => Ok(AuthMechanism::Login),
+            _ => Err(format!("invalid SMTP authentication `{}`: expected plain or login", s)),
+        }
+    }
+}
+
+impl fmt::Display for AuthMechanism {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AuthMechanism::Plain => "plain",
+            AuthMechanism::Login => "login",
+        })
+    }
+}
+
+impl From<AuthMechanism> for Mechanism {
+    fn from(mechanism: AuthMechanism) -> Self {
+        match mechanism {
+            AuthMechanism::Plain => Mechanism::Plain,
+            AuthMechanism::Login => Mechanism::Login,
+        }
+    }
+}
+
+/// Verbindung, Anmeldung und Versandverhalten
+#[derive(Debug, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    /// Ohne Angabe der übliche Port der Verschlüsselung
+    pub port: Option<u16>,
+    pub security: SmtpSecurity,
+    /// Benutzername; ohne ihn wird keine Anmeldung versucht
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Erlaubte Anmeldeverfahren (leer = PLAIN und LOGIN)
+    pub auth: Vec<AuthMechanism>,
+    /// Höchstens so viele Nachrichten pro Minute
+    pub rate_limit: Option<u32>,
+    /// Wiederholungen bei vorübergehenden Fehlern (4xx, Verbindungsabbruch)
+    pub retries: u32,
+    pub retry_delay: Duration,
+    pub timeout: Duration,
+}
+
+impl SmtpSettings {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.to_string(),
+            port: None,
+            security: SmtpSecurity::default(),
+            username: None,
+            password: None,
+            auth: Vec::new(),
+            rate_limit: None,
+            retries: DEFAULT_RETRIES,
+            retry_delay: DEFAULT_RETRY_DELAY,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or_else(|| self.security.default_port())
+    }
+
+    /// Mindestabstand zwischen zwei Nachrichten laut `rate_limit`
+    fn interval(&self) -> Option<Duration> {
+        self.rate_limit
+            .filter(|&limit| limit > 0)
+            .map(|limit| Duration::from_secs(60) / limit)
+    }
+}
+
+/// Verschickt fertige Nachrichten über einen SMTP-Server
+pub struct Mailer {
+    transport: SmtpTransport,
+    interval: Option<Duration>,
+    retries: u32,
+    retry_delay: Duration,
+    last_sent: Option<Instant>,
+}
+
+impl Mailer {
+    /// Prüft Verbindung und Anmeldung, damit falsche Einstellungen vor der ersten
+    /// Nachricht auffallen
+    pub fn connect(settings: &SmtpSettings) -> Result<Self> {
+        let smtp_error = |e: lettre::transport::smtp::Error| {
+            CertgenError::Smtp(format!("{}:{}: {}", settings.host, settings.port(), e))
+        };
+        let builder = match settings.security {
+            SmtpSecurity::StartTls => SmtpTransport::starttls_relay(&settings.host).map_err(smtp_error)?,
+            SmtpSecurity::Tls => SmtpTransport::relay(&settings.host).map_err(smtp_error)?,
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(&settings.host),
+        };
+        let mut builder = builder.port(settings.port()).timeout(Some(settings.timeout));
+        if let Some(username) = &settings.username {
+            let password = settings.password.clone().unwrap_or_default();
+            let mechanisms = if settings.auth.is_empty() {
+                vec![Mechanism::Plain, Mechanism::Login]
+            } else {
+                settings.auth.iter().copied().map(Mechanism::from).collect()
+            };
+            builder = builder
+                .credentials(Credentials::new(username.clone(), password))
+                .authentication(mechanisms);
+        }
+        let transport = builder.build();
+        transport.test_connection().map_err(smtp_error)?;
+
+        Ok(Self {
+            transport,
+            interval: settings.interval(),
+            retries: settings.retries,
+            retry_delay: settings.retry_delay,
+            last_sent: None,
+        })
+    }
+
+    /// Verschickt eine Nachricht im .eml-Format; vorübergehende Fehler werden bis zu
+    /// `retries`-mal mit wachsender Wartezeit wiederholt. Eine Nachricht ohne gültige
+    /// Empfänger wird gar nicht erst versucht.
+    pub fn send(&mut self, message: &[u8]) -> Delivery {
+        let envelope = match envelope(message) {
+            Ok(envelope) => envelope,
+            Err(e) => return Delivery::failed(e.to_string()),
+        };
+        let mut delivery = Delivery::failed(String::new());
+        delivery.recipients = envelope.to().iter().map(ToString::to_string).collect();
+        let message = strip_bcc(message);
+
+        loop {
+            self.wait();
+            delivery.attempts += 1;
+            delivery.at = chrono::Local::now().to_rfc3339();
+            let result = self.transport.send_raw(&envelope, &message);
+            self.last_sent = Some(Instant::now());
+            match result {
+                Ok(response) => {
+                    let text = response.message().collect::<Vec<_>>().join(" ");
+                    delivery.status = DeliveryStatus::Sent;
+                    delivery.response = Some(format!("{} {}", response.code(), text).trim_end().to_string());
+                    delivery.error = None;
+                    return delivery;
+                }
+                Err(e) if is_retryable(&e) && delivery.attempts <= self.retries => {
+                    let delay = self.retry_delay * delivery.attempts;
+                    warn!(
+                        "Sending to {} failed ({}), retrying in {} s",
+                        delivery.recipients.join(", "),
+                        e,
+                        delay.as_secs_f32()
+                    );
+                    delivery.error = Some(e.to_string());
+                    std::thread::sleep(delay);
+                }
+                Err(e) => {
+                    delivery.error = Some(e.to_string());
+                    return delivery;
+                }
+            }
+        }
+    }
+
+    /// Wartet bis zum Mindestabstand nach der letzten Nachricht
+    fn wait(&self) {
+        if let (Some(interval), Some(last_sent)) = (self.interval, self.last_sent) {
+            let elapsed = last_sent.elapsed();
+            if elapsed < interval {
+                debug!("Rate limit: waiting {} ms", (interval - elapsed).as_millis());
+                std::thread::sleep(interval - elapsed);
+            }
+        }
+    }
+}
+
+/// Vorübergehende Fehler (4xx) und Verbindungsprobleme; abgelehnte Empfänger (5xx),
+/// ungültige Nachrichten und TLS-Fehler ändern sich durch Warten nicht
+fn is_retryable(error: &lettre::transport::smtp::Error) -> bool {
+    !(error.is_permanent() || error.is_client() || error.is_response() || error.is_tls())
+}
+
+/// Prüft eine Adresse für Kopfzeilen, z.B. `kurs@example.com` oder
+/// `Kursteam <kurs@example.com>`, und gibt sie unverändert zurück
+pub fn parse_mailbox(value: &str) -> Result<String> {
+    let value = value.trim();
+    match addresses(value).as_slice() {
+        [address] => {
+            address
+                .parse::<Address>()
+                .map_err(|e| CertgenError::Smtp(format!("invalid email address `{}`: {}", value, e)))?;
+            Ok(value.to_string())
+        }
+        _ => Err(CertgenError::Smtp(format!("expected a single email address, got `{}`", value))),
+    }
+}
+
+/// Umschlag der Nachricht: Absender aus `From` (Pflicht), Empfänger aus `To`, `Cc` und `Bcc`
+pub fn envelope(message: &[u8]) -> Result<Envelope> {
+    let headers = headers(message);
+    let parse = |address: &str| {
+        address
+            .parse::<Address>()
+            .map_err(|e| CertgenError::Smtp(format!("invalid email address `{}`: {}", address, e)))
+    };
+
+    let from = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("from"))
+        .and_then(|(_, value)| addresses(value).into_iter().next())
+        .map(|address| parse(&address))
+        .transpose()?;
+    let mut recipients = Vec::new();
+    for (name, value) in &headers {
+        if ["to", "cc", "bcc"].iter().any(|h| name.eq_ignore_ascii_case(h)) {
+            for address in addresses(value) {
+                let address = parse(&address)?;
+                if !recipients.contains(&address) {
+                    recipients.push(address);
+                }
+            }
+        }
+    }
+    let envelope = Envelope::new(from, recipients)
+        .map_err(|e| CertgenError::Smtp(format!("message has no recipients: {}", e)))?;
+    if envelope.from().is_none() {
+        return Err(CertgenError::Smtp("message has no sender (From), set one with --from".to_string()));
+    }
+    Ok(envelope)
+}
+
+/// Die Nachricht ohne `Bcc`-Kopfzeile, damit Blindkopien für die anderen Empfänger
+/// unsichtbar bleiben
+pub fn strip_bcc(message: &[u8]) -> Vec<u8> {
+    let split = header_end(message);
+    let mut result = Vec::with_capacity(message.len());
+    let mut skipping = false;
+    for line in message[..split].split_inclusive(|&b| b == b'\n') {
+        let folded = line.first().is_some_and(|b| *b == b' ' || *b == b'\t');
+        if !folded {
+            skipping = line.len() >= 4 && line[..4].eq_ignore_ascii_case(b"bcc:");
+        }
+        if !skipping {
+            result.extend_from_slice(line);
+        }
+    }
+    result.extend_from_slice(&message[split..]);
+    result
+}
+
+/// Ende des Nachrichtenkopfs (Beginn der Leerzeile)
+fn header_end(message: &[u8]) -> usize {
+    let crlf = message.windows(4).position(|w| w == b"\r\n\r\n").map(|p| p + 2);
+    let lf = message.windows(2).position(|w| w == b"\n\n").map(|p| p + 1);
+    match (crlf, lf) {
+        (Some(a), Some(b)) => a.min(b),
+        (a, b) => a.or(b).unwrap_or(message.len()),
+    }
+}
+
+/// Kopfzeilen als (Name, Wert), Fortsetzungszeilen angehängt
+fn headers(message: &[u8]) -> Vec<(String, String)> {
+    let head = String::from_utf8_lossy(&message[..header_end(message)]);
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in head.lines() {
+        if line.starts_with([' ', '\t']) {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+/// Adressen einer Kopfzeile wie `Max <max@example.com>, "Muster, Erika" <erika@example.com>`
+fn addresses(value: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            ',' if !quoted => parts.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    parts.push(current);
+
+    parts
+        .iter()
+        .filter_map(|part| {
+            let part = part.trim();
+            let address = match (part.rfind('<'), part.rfind('>')) {
+                (Some(start), Some(end)) if start < end => &part[start + 1..end],
+                _ => part,
+            };
+            let address = address.trim();
+            (!address.is_empty() && !address.ends_with(':')).then(|| address.to_string())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    const MESSAGE: &str = "From: Kursteam <team@example.com>\r\n\
+        To: Max <max@example.com>\r\n\
+        Cc: \"Muster, Erika\" <erika@example.com>,\r\n kurs@example.com\r\n\
+        Bcc: archiv@example.com\r\n\
+        Subject: Ihr Zertifikat\r\n\
+        \r\n\
+        Bcc: bleibt im Text\r\n";
+
+    /// Minimaler SMTP-Server: lehnt die ersten `reject` Nachrichten vorübergehend ab und
+    /// speichert alle angenommenen Nachrichten
+    fn smtp_sink(reject: usize) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let messages = Arc::clone(&received);
+        std::thread::spawn(move || {
+            let mut rejected = 0;
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                stream.write_all(b"220 localhost ESMTP sink\r\n").unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap_or(0) > 0 {
+                    let command = line.trim_end().to_ascii_uppercase();
+                    let reply: &[u8] = if command.starts_with("EHLO") {
+                        b"250-localhost\r\n250 AUTH PLAIN LOGIN\r\n"
+                    } else if command.starts_with("AUTH PLAIN") {
+                        b"235 2.7.0 Authentication successful\r\n"
+                    } else if command.starts_with("RCPT") && rejected < reject {
+                        rejected += 1;
+                        b"451 4.3.0 Try again later\r\n"
+                    } else if command == "DATA" {
+                        stream.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").unwrap();
+                        let mut data = String::new();
+                        loop {
+                            let mut data_line = String::new();
+                            reader.read_line(&mut data_line).unwrap();
+                            if data_line == ".\r\n" {
+                                break;
+                            }
+                            data.push_str(&data_line);
+                        }
+                        messages.lock().unwrap().push(data);
+                        b"250 2.0.0 Ok: queued\r\n"
+                    } else if command == "QUIT" {
+                        stream.write_all(b"221 Bye\r\n").unwrap();
+                        break;
+                    } else {
+                        b"250 Ok\r\n"
+                    };
+                    stream.write_all(reply).unwrap();
+                    line.clear();
+                }
+            }
+        });
+        (port, received)
+    }
+
+    fn settings(port: u16) -> SmtpSettings {
+        SmtpSettings {
+            port: Some(port),
+            security: SmtpSecurity::None,
+            username: Some("kurs".to_string()),
+            password: Some("geheim".to_string()),
+            retry_delay: Duration::from_millis(10),
+            timeout: Duration::from_secs(5),
+            ..SmtpSettings::new("127.0.0.1")
+        }
+    }
+
+    #[test]
+    fn test_envelope_from_headers() {
+        let parsed = envelope(MESSAGE.as_bytes()).unwrap();
+        assert_eq!(parsed.from().unwrap().to_string(), "team@example.com");
+        let recipients: Vec<String> = parsed.to().iter().map(ToString::to_string).collect();
+        assert_eq!(
+            recipients,
+            ["max@example.com", "erika@example.com", "kurs@example.com", "archiv@example.com"]
+        );
+
+        assert!(envelope(b"From: team@example.com\r\n\r\nText").is_err());
+        assert!(envelope(b"To: kein-at-zeichen\r\n\r\nText").is_err());
+        assert!(envelope(b"To: max@example.com\r\n\r\nText").is_err());
+    }
+
+    #[test]
+    fn test_parse_mailbox() {
+        assert_eq!(parse_mailbox(" kurs@example.com ").unwrap(), "kurs@example.com");
+        assert_eq!(
+            parse_mailbox("\"Team, Kurs\" <kurs@example.com>").unwrap(),
+            "\"Team, Kurs\" <kurs@example.com>"
+        );
+        assert!(parse_mailbox("Kursteam").is_err());
+        assert!(parse_mailbox("a@example.com, b@example.com").is_err());
+        assert!(parse_mailbox("").is_err());
+    }
+
+    #[test]
+    fn test_strip_bcc() {
+        let stripped = String::from_utf8(strip_bcc(MESSAGE.as_bytes())).unwrap();
+        assert!(!stripped.contains("archiv@example.com"));
+        assert!(stripped.contains(" kurs@example.com\r\nSubject: Ihr Zertifikat\r\n"));
+        assert!(stripped.ends_with("\r\n\r\nBcc: bleibt im Text\r\n"));
+    }
+
+    #[test]
+    fn test_parse_options() {
+        assert_eq!("tls".parse::<SmtpSecurity>().unwrap(), SmtpSecurity::Tls);
+        assert!("ssl".parse::<SmtpSecurity>().is_err());
+        assert_eq!(SmtpSecurity::default().default_port(), 587);
+        assert_eq!("LOGIN".parse::<AuthMechanism>().unwrap(), AuthMechanism::Login);
+
+        let limited = SmtpSettings {
+            rate_limit: Some(30),
+            ..SmtpSettings::new("mail.example.com")
+        };
+        assert_eq!(limited.interval(), Some(Duration::from_secs(2)));
+        assert_eq!(limited.port(), 587);
+    }
+
+    #[test]
+    fn test_send_with_retry() {
+        let (port, received) = smtp_sink(1);
+        let mut mailer = Mailer::connect(&settings(port)).unwrap();
+
+        let delivery = mailer.send(MESSAGE.as_bytes());
+        assert_eq!(delivery.status, DeliveryStatus::Sent, "{:?}", delivery.error);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.recipients.len(), 4);
+        assert_eq!(delivery.response.as_deref(), Some("250 2.0.0 Ok: queued"));
+
+        let received = received.lock().unwrap();
+        assert_eq!(received.len(), 1);
+        assert!(received[0].contains("Subject: Ihr Zertifikat"));
+        assert!(!received[0].contains("archiv@example.com"));
+    }
+
+    #[test]
+    fn test_send_gives_up_after_retries() {
+        let (port, received) = smtp_sink(usize::MAX);
+        let mut mailer = Mailer::connect(&SmtpSettings {
+            retries: 1,
+            ..settings(port)
+        })
+        .unwrap();
+
+        let delivery = mailer.send(MESSAGE.as_bytes());
+        assert_eq!(delivery.status, DeliveryStatus::Failed);
+        assert_eq!(delivery.attempts, 2);
+        assert!(delivery.error.unwrap().contains("Try again later"));
+        assert!(received.lock().unwrap().is_empty());
+
+        let invalid = mailer.send(b"Subject: ohne Empfaenger\r\n\r\n");
+        assert_eq!(invalid.attempts, 0);
+        assert!(invalid.error.unwrap().contains("no recipients"));
+    }
+}
@@ -0,0 +1,540 @@
+//! Datenquellen für den Batch-Modus: JSON-Dateien sowie Tabellen als CSV, ODS oder XLSX.
+//!
+//! Tabellen haben eine Kopfzeile. Spalten werden über ihren Namen den Feldern
+//! zugeordnet: `name`, `date`, `date_from`, `date_to`, `agenda`, `email`, `language`
+//! und `cert_id` werden ohne Beachtung der Groß-/Kleinschreibung erkannt, alle
+//! anderen Spalten werden zu zusätzlichen Feldern unter ihrem Spaltennamen.
+//! Abweichende Spaltennamen lassen sich mit `ColumnMapping` zuordnen.
+
+use crate::error::{CertgenError, Result};
+use calamine::{open_workbook_auto, Data, Reader};
+use chrono::NaiveDate;
+use encoding_rs::Encoding;
+use log::{debug, info};
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Felder, die ohne Zuordnung am Spaltennamen erkannt werden
+const KNOWN_FIELDS: [&str; 8] = ["name", "date", "date_from", "date_to", "agenda", "email", "language", "cert_id"];
+
+/// Liefert die Einträge für den Batch-Modus
+pub trait DataSource {
+    /// Die Einträge im Format der Batch-JSON-Datei: direkt oder als
+    /// `{ "email": ..., "certificate": { ... } }`
+    fn read_items(&self) -> Result<Vec<Value>>;
+
+    /// Schreibt die Einträge (mit `generated_file` und `cert_id`) in die Quelle zurück,
+    /// atomar und mit Sicherungskopie; `false`, wenn die Quelle das nicht unterstützt
+    fn write_back(&self, _items: &[Value]) -> Result<bool> {
+        Ok(false)
+    }
+}
+
+/// Zuordnung von Feldern zu Spaltennamen, z.B. `name` → `Teilnehmer`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMapping {
+    columns: Vec<(String, String)>,
+}
+
+impl ColumnMapping {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ordnet dem Feld `field` die Spalte `column` zu; eine spätere Zuordnung desselben Felds gewinnt
+    pub fn map(mut self, field: impl Into<String>, column: impl Into<String>) -> Self {
+        let field = field.into();
+        self.columns.retain(|(existing, _)| *existing != field);
+        self.columns.push((field, column.into()));
+        self
+    }
+
+    /// Feldnamen für die Spalten der Kopfzeile
+    fn fields(&self, headers: &[String]) -> Result<Vec<Option<String>>> {
+        let mut fields: Vec<Option<String>> = headers
+            .iter()
+            .map(|header| {
+                let header = header.trim();
+                if header.is_empty() {
+                    return None;
+                }
+                let lower = header.to_lowercase();
+                Some(match KNOWN_FIELDS.contains(&lower.as_str()) {
+                    true => lower,
+                    false => header.to_string(),
+                })
+            })
+            .collect();
+
+        for (field, column) in &self.columns {
+            let index = headers
+                .iter()
+                .position(|header| header.trim().eq_ignore_ascii_case(column.trim()))
+                .ok_or_else(|| {
+                    CertgenError::DataSource(format!("column `{}` for field `{}` not found", column, field))
+                })?;
+            // Eine Spalte, die bisher unter diesem Feldnamen stand, wird nicht mehr verwendet
+            for other in fields.iter_mut().filter(|other| other.as_deref() == Some(field.as_str())) {
+                *other = None;
+            }
+            fields[index] = Some(field.clone());
+        }
+        Ok(fields)
+    }
+}
+
+/// Einstellungen zum Lesen von Tabellen
+#[derive(Debug, Clone, Default)]
+pub struct SourceOptions {
+    /// Trennzeichen für CSV; ohne Angabe wird `;`, `,` oder Tabulator aus der Kopfzeile erkannt
+    pub delimiter: Option<u8>,
+    /// Zeichenkodierung für CSV, z.B. `windows-1252` (Standard: UTF-8)
+    pub encoding: Option<String>,
+    /// Tabellenblatt in ODS/XLSX (Standard: das erste)
+    pub sheet: Option<String>,
+    pub columns: ColumnMapping,
+}
+
+/// Öffnet die Datenquelle passend zur Dateiendung (`.json`, `.csv`/`.tsv`/`.txt`, `.ods`, `.xlsx`/`.xls`);
+/// `.txt` steht für Text-Exporte aus Tabellenprogrammen, die CSV-Daten enthalten
+pub fn open_source(path: &Path, options: &SourceOptions) -> Result<Box<dyn DataSource>> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let path = path.to_path_buf();
+    Ok(match extension.as_str() {
+        "json" => Box::new(JsonSource { path }),
+        "csv" | "tsv" | "txt" => Box::new(CsvSource {
+            path,
+            options: options.clone(),
+        }),
+        "ods" | "xlsx" | "xlsm" | "xls" => Box::new(SpreadsheetSource {
+            path,
+            sheet: options.sheet.clone(),
+            columns: options.columns.clone(),
+        }),
+        _ => {
+            return Err(CertgenError::DataSource(format!(
+                "unsupported input file `{}` (expected .json, .csv, .ods or .xlsx)",
+                path.display()
+            )))
+        }
+    })
+}
+
+/// JSON-Datei mit einem Array von Einträgen
+pub struct JsonSource {
+    path: PathBuf,
+}
+
+impl DataSource for JsonSource {
+    fn read_items(&self) -> Result<Vec<Value>> {
+        let content = std::fs::read_to_string(&self.path)?;
+        match serde_json::from_str(&content)? {
+            Value::Array(items) => Ok(items),
+            _ => Err(CertgenError::DataSource(
+                "Expected top-level JSON array in batch file".to_string(),
+            )),
+        }
+    }
+
+    fn write_back(&self, items: &[Value]) -> Result<bool> {
+        let backup = backup_path(&self.path);
+        std::fs::copy(&self.path, &backup)?;
+        info!("Saved a backup of {} as {}", self.path.display(), backup.display());
+
+        let pretty = serde_json::to_string_pretty(items)?;
+        write_atomic(&self.path, pretty.as_bytes())?;
+        Ok(true)
+    }
+}
+
+/// Sicherungskopie neben der Datei, z.B. `teilnehmer.json.bak`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Schreibt erst in eine temporäre Datei im selben Verzeichnis und benennt sie dann um,
+/// damit ein Abbruch nie eine halb geschriebene Datei hinterlässt
+pub fn write_atomic(path: &Path, content: &[u8]) -> Result<()> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".tmp-{}", std::process::id()));
+    let temp = path.with_file_name(name);
+
+    let written = std::fs::File::create(&temp).and_then(|mut file| {
+        file.write_all(content)?;
+        file.sync_all()
+    });
+    if let Err(e) = written.and_then(|_| std::fs::rename(&temp, path)) {
+        let _ = std::fs::remove_file(&temp);
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// CSV-Datei mit Kopfzeile
+pub struct CsvSource {
+    path: PathBuf,
+    options: SourceOptions,
+}
+
+impl DataSource for CsvSource {
+    fn read_items(&self) -> Result<Vec<Value>> {
+        let bytes = std::fs::read(&self.path)?;
+        let text = decode(&bytes, self.options.encoding.as_deref())?;
+        parse_csv(&text, self.options.delimiter, &self.options.columns)
+    }
+}
+
+/// Erstes bzw. ausgewähltes Blatt einer ODS- oder XLSX-Datei
+pub struct SpreadsheetSource {
+    path: PathBuf,
+    sheet: Option<String>,
+    columns: ColumnMapping,
+}
+
+impl DataSource for SpreadsheetSource {
+    fn read_items(&self) -> Result<Vec<Value>> {
+        let error = |e: calamine::Error| CertgenError::DataSource(format!("{}: {}", self.path.display(), e));
+        let mut workbook = open_workbook_auto(&self.path).map_err(error)?;
+
+        let sheet = match &self.sheet {
+            Some(sheet) => sheet.clone(),
+            None => workbook
+                .sheet_names()
+                .first()
+                .cloned()
+                .ok_or_else(|| CertgenError::DataSource(format!("{} has no sheets", self.path.display())))?,
+        };
+        info!("Reading sheet '{}' of {}", sheet, self.path.display());
+        let range = workbook.worksheet_range(&sheet).map_err(error)?;
+
+        let mut rows = range.rows().map(|row| row.iter().map(cell_text).collect::<Vec<_>>());
+        let headers = rows.next().unwrap_or_default();
+        rows_to_items(&headers, rows, &self.columns)
+    }
+}
+
+/// Dekodiert CSV-Inhalt; ein BOM hat Vorrang vor der angegebenen Kodierung
+fn decode(bytes: &[u8], encoding: Option<&str>) -> Result<String> {
+    let encoding = match encoding {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| CertgenError::DataSource(format!("unknown encoding `{}`", label)))?,
+        None => encoding_rs::UTF_8,
+    };
+    let (text, used, had_errors) = encoding.decode(bytes);
+    if had_errors {
+        return Err(CertgenError::DataSource(format!(
+            "input is not valid {} (use --encoding, e.g. windows-1252)",
+            used.name()
+        )));
+    }
+    Ok(text.into_owned())
+}
+
+fn parse_csv(text: &str, delimiter: Option<u8>, columns: &ColumnMapping) -> Result<Vec<Value>> {
+    let delimiter = delimiter.unwrap_or_else(|| detect_delimiter(text));
+    debug!("Reading CSV with delimiter {:?}", delimiter as char);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(|e| CertgenError::DataSource(format!("CSV: {}", e)))?;
+        rows.push(record.iter().map(str::to_string).collect::<Vec<_>>());
+    }
+    let mut rows = rows.into_iter();
+    let headers = rows.next().unwrap_or_default();
+    rows_to_items(&headers, rows, columns)
+}
+
+/// Das häufigste der Trennzeichen `;`, `,` und Tabulator in der Kopfzeile
+fn detect_delimiter(text: &str) -> u8 {
+    let header = text.lines().next().unwrap_or_default();
+    [b',', b';', b'\t']
+        .into_iter()
+        .max_by_key(|d| header.bytes().filter(|b| b == d).count())
+        .unwrap_or(b',')
+}
+
+/// Text einer Tabellenzelle; Datumszellen als `TT.MM.JJJJ`, ganze Zahlen ohne Nachkommastellen
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty | Data::Error(_) => String::new(),
+        Data::String(text) | Data::DurationIso(text) => text.clone(),
+        // ODS liefert Daten als ISO 8601, z.B. `2024-01-15` oder `2024-01-15T00:00:00`
+        Data::DateTimeIso(text) => text
+            .get(..10)
+            .and_then(|day| NaiveDate::parse_from_str(day, "%Y-%m-%d").ok())
+            .map(|day| day.format("%d.%m.%Y").to_string())
+            .unwrap_or_else(|| text.clone()),
+        Data::Int(number) => number.to_string(),
+        Data::Float(number) if number.fract() == 0.0 && number.abs() < 1e15 => format!("{}", *number as i64),
+        Data::Float(number) => number.to_string(),
+        Data::Bool(value) => value.to_string(),
+        Data::DateTime(date) => match date.as_datetime() {
+            Some(datetime) => datetime.format("%d.%m.%Y").to_string(),
+            None => date.as_f64().to_string(),
+        },
+    }
+}
+
+/// Baut aus Kopfzeile und Zeilen die Einträge; leere Zeilen und Zellen werden übersprungen
+fn rows_to_items(
+    headers: &[String],
+    rows: impl Iterator<Item = Vec<String>>,
+    columns: &ColumnMapping,
+) -> Result<Vec<Value>> {
+    let fields = columns.fields(headers)?;
+
+    let mut items = Vec::new();
+    for row in rows {
+        let mut certificate = Map::new();
+        let mut email = None;
+        for (field, cell) in fields.iter().zip(&row) {
+            let (Some(field), cell) = (field, cell.trim()) else {
+                continue;
+            };
+            if cell.is_empty() {
+                continue;
+            }
+            match field.as_str() {
+                "email" => email = Some(cell.to_string()),
+                _ => {
+                    certificate.insert(field.clone(), Value::String(cell.to_string()));
+                }
+            }
+        }
+        if certificate.is_empty() && email.is_none() {
+            continue;
+        }
+
+        items.push(match email {
+            Some(email) => {
+                let mut item = Map::new();
+                item.insert("email".to_string(), Value::String(email));
+                item.insert("certificate".to_string(), Value::Object(certificate));
+                Value::Object(item)
+            }
+            None => Value::Object(certificate),
+        });
+    }
+    Ok(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::CertificateData;
+
+    #[test]
+    fn test_csv_with_known_and_custom_columns() {
+        let csv = "Name;Date;Agenda;E-Mail;TITLE\n\
+                   Max Mustermann;15.01.2024;\"· Grundlagen\n· Ownership\";max@example.com;Rust\n\
+                   ;;;;\n\
+                   Erika;2024-01-16;Go;;Go\n";
+        let columns = ColumnMapping::new().map("email", "e-mail");
+        let items = parse_csv(csv, None, &columns).unwrap();
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["email"], "max@example.com");
+        let data = CertificateData::from_batch_item(&items[0]).unwrap();
+        assert_eq!(data.name, "Max Mustermann");
+        assert_eq!(data.agenda, "· Grundlagen\n· Ownership");
+        assert_eq!(data.custom_fields.get("TITLE"), Some(&"Rust".to_string()));
+
+        // Ohne E-Mail bleibt es beim direkten Format
+        assert!(items[1].get("certificate").is_none());
+        assert_eq!(items[1]["date"], "2024-01-16");
+    }
+
+    #[test]
+    fn test_column_mapping() {
+        let csv = "Teilnehmer,Kursdatum,Inhalt,name\nMax,15.01.2024,Rust,alt\n";
+        let columns = ColumnMapping::new()
+            .map("name", "Teilnehmer")
+            .map("date", "Kursdatum")
+            .map("agenda", "Inhalt");
+        let items = parse_csv(csv, Some(b','), &columns).unwrap();
+        assert_eq!(items[0], serde_json::json!({ "name": "Max", "date": "15.01.2024", "agenda": "Rust" }));
+
+        let missing = ColumnMapping::new().map("name", "Vorname");
+        assert!(matches!(parse_csv(csv, None, &missing), Err(CertgenError::DataSource(_))));
+    }
+
+    #[test]
+    fn test_decode_and_detect() {
+        let latin1 = b"name;date;agenda\nJ\xfcrgen;15.01.2024;Rust\n";
+        assert!(decode(latin1, None).is_err());
+        let text = decode(latin1, Some("windows-1252")).unwrap();
+        assert!(text.contains("Jürgen"));
+        assert_eq!(detect_delimiter(&text), b';');
+        assert_eq!(detect_delimiter("name\tdate\n"), b'\t');
+        assert!(decode(b"x", Some("klingonisch")).is_err());
+    }
+
+    #[test]
+    fn test_json_write_back_keeps_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("teilnehmer.json");
+        let original = "[ {\"name\": \"Max\", \"date\": \"15.01.2024\", \"agenda\": \"Rust\"} ]";
+        std::fs::write(&path, original).unwrap();
+
+        let source = open_source(&path, &SourceOptions::default()).unwrap();
+        let mut items = source.read_items().unwrap();
+        items[0]["generated_file"] = Value::String("out/Max.pdf".to_string());
+        assert!(source.write_back(&items).unwrap());
+
+        assert_eq!(std::fs::read_to_string(backup_path(&path)).unwrap(), original);
+        assert_eq!(source.read_items().unwrap(), items);
+        // Keine temporären Dateien zurückgelassen
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_read_ods_sheet() {
+        let content = concat!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+            r#"<office:document-content xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" "#,
+            r#"xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" "#,
+            r#"xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" office:version="1.2">"#,
+            r#"<office:body><office:spreadsheet><table:table table:name="Teilnehmer">"#,
+            r#"<table:table-row><table:table-cell office:value-type="string"><text:p>name</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="string"><text:p>date</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="string"><text:p>agenda</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="string"><text:p>HOURS</text:p></table:table-cell></table:table-row>"#,
+            r#"<table:table-row><table:table-cell office:value-type="string"><text:p>Max</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="date" office:date-value="2024-01-15"><text:p>15.01.24</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="string"><text:p>Rust</text:p></table:table-cell>"#,
+            r#"<table:table-cell office:value-type="float" office:value="16"><text:p>16</text:p></table:table-cell></table:table-row>"#,
+            r#"</table:table></office:spreadsheet></office:body></office:document-content>"#
+        );
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("teilnehmer.ods");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        let stored = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("mimetype", stored).unwrap();
+        zip.write_all(b"application/vnd.oasis.opendocument.spreadsheet").unwrap();
+        zip.start_file("META-INF/manifest.xml", stored).unwrap();
+        zip.write_all(
+            concat!(
+                r#"<manifest:manifest xmlns:manifest="urn:oasis:names:tc:opendocument:xmlns:manifest:1.0">"#,
+                r#"<manifest:file-entry manifest:full-path="content.xml" manifest:media-type="text/xml"/>"#,
+                r#"</manifest:manifest>"#
+            )
+            .as_bytes(),
+        )
+        .unwrap();
+        zip.start_file("content.xml", stored).unwrap();
+        zip.write_all(content.as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let source = open_source(&path, &SourceOptions::default()).unwrap();
+        let items = source.read_items().unwrap();
+        assert_eq!(
+            items,
+            vec![serde_json::json!({ "name": "Max", "date": "15.01.2024", "agenda": "Rust", "HOURS": "16" })]
+        );
+        assert!(!source.write_back(&items).unwrap());
+    }
+
+    #[test]
+    fn test_read_xlsx_sheet() {
+        let parts = [
+            (
+                "[Content_Types].xml",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+                    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+                    r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+                    r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+                    r#"<Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+                    r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+                    r#"</Types>"#
+                ),
+            ),
+            (
+                "_rels/.rels",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+                    r#"</Relationships>"#
+                ),
+            ),
+            (
+                "xl/workbook.xml",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" "#,
+                    r#"xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships">"#,
+                    r#"<sheets><sheet name="Teilnehmer" sheetId="1" r:id="rId1"/></sheets></workbook>"#
+                ),
+            ),
+            (
+                "xl/_rels/workbook.xml.rels",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+                    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>"#,
+                    r#"<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+                    r#"</Relationships>"#
+                ),
+            ),
+            (
+                // Stil 1 ist ein Datumsformat (numFmtId 14), damit die Zahl als Datum gelesen wird
+                "xl/styles.xml",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main">"#,
+                    r#"<cellXfs count="2"><xf numFmtId="0"/><xf numFmtId="14" applyNumberFormat="1"/></cellXfs>"#,
+                    r#"</styleSheet>"#
+                ),
+            ),
+            (
+                "xl/worksheets/sheet1.xml",
+                concat!(
+                    r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                    r#"<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+                    r#"<row r="1"><c r="A1" t="inlineStr"><is><t>Name</t></is></c><c r="B1" t="inlineStr"><is><t>Date</t></is></c>"#,
+                    r#"<c r="C1" t="inlineStr"><is><t>Agenda</t></is></c><c r="D1" t="inlineStr"><is><t>HOURS</t></is></c></row>"#,
+                    r#"<row r="2"><c r="A2" t="inlineStr"><is><t>Erika</t></is></c><c r="B2" s="1"><v>45306</v></c>"#,
+                    r#"<c r="C2" t="inlineStr"><is><t>Go</t></is></c><c r="D2"><v>8</v></c></row>"#,
+                    r#"</sheetData></worksheet>"#
+                ),
+            ),
+        ];
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("teilnehmer.xlsx");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).unwrap());
+        for (name, content) in parts {
+            zip.start_file(name, zip::write::FileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        zip.finish().unwrap();
+
+        let options = SourceOptions {
+            sheet: Some("Teilnehmer".to_string()),
+            ..SourceOptions::default()
+        };
+        let source = open_source(&path, &options).unwrap();
+        let items = source.read_items().unwrap();
+        assert_eq!(
+            items,
+            vec![serde_json::json!({ "name": "Erika", "date": "15.01.2024", "agenda": "Go", "HOURS": "8" })]
+        );
+        assert!(!source.write_back(&items).unwrap());
+    }
+}
@@ -1,37 +1,222 @@
+use crate::odf::list_key;
+use crate::template::dates::{parse_date, storage_text, DateOptions, Locale};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+/// Felder, die `batch` mit den erzeugten Dateien in die Einträge zurückschreibt
+pub const GENERATED_KEYS: [&str; 4] = ["generated_file", "generated_files", "generated_eml", "generated_emls"];
+
+/// Eine Zeile einer Liste, z.B. ein Agenda-Modul mit Titel und Stunden
+pub type Row = serde_json::Map<String, Value>;
 
 /// Daten für das Zertifikat
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RawCertificateData", into = "RawCertificateData")]
 pub struct CertificateData {
     pub name: String,
     
     /// Hauptdatum (bei eintägigen Kursen) oder Enddatum (bei mehrtägigen)
-    pub date: String,
+    pub date: NaiveDate,
     
+    /// Agenda als Text; wurde sie als Liste angegeben, die daraus erzeugte Textfassung
     pub agenda: String,
     
     /// Startdatum (optional, nur bei mehrtägigen Kursen)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub date_from: Option<String>,
+    pub date_from: Option<NaiveDate>,
     
-    /// Enddatum (optional, nur bei mehrtägigen Kursen)
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub date_to: Option<String>,
+    /// Enddatum (optional, nur bei mehrtägigen Kursen; ohne Angabe ist der Kurs eintägig
+    /// am `date_from`)
+    pub date_to: Option<NaiveDate>,
+
+    /// Eindeutige Zertifikats-ID, wird beim Erzeugen aus dem Register vergeben
+    pub cert_id: Option<String>,
+
+    /// Sprachen, in denen das Zertifikat erzeugt wird (Feld `language`, z.B. `"de,en"`);
+    /// leer = Sprachen aus `--languages` bzw. eine Ausgabe ohne Sprachendung
+    pub languages: Vec<Locale>,
     
-    #[serde(flatten)]
+    /// Weitere Platzhalter; Zahlen und Wahrheitswerte (z.B. für `{{#if KEY}}`) werden als Text übernommen
     pub custom_fields: HashMap<String, String>,
+
+    /// Felder, die als JSON-Array von Objekten angegeben wurden (die Agenda unter `AGENDA`),
+    /// für Tabellenzeilen und Listeneinträge mit `{{#each KEY}}`
+    pub lists: BTreeMap<String, Vec<Row>>,
+}
+
+/// JSON-Form von `CertificateData`: `agenda` und weitere Felder dürfen Listen sein,
+/// Daten stehen als Text in einem der `dates::INPUT_FORMATS`
+#[derive(Serialize, Deserialize)]
+struct RawCertificateData {
+    name: String,
+    date: String,
+    agenda: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_from: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    date_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cert_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    language: Option<Value>,
+    #[serde(flatten)]
+    fields: BTreeMap<String, Value>,
+}
+
+impl TryFrom<RawCertificateData> for CertificateData {
+    type Error = String;
+
+    fn try_from(raw: RawCertificateData) -> std::result::Result<Self, Self::Error> {
+        let mut data = CertificateData::new(raw.name, parse_date_field("date", &raw.date)?, String::new());
+        data.date_from = raw.date_from.map(|from| parse_date_field("date_from", &from)).transpose()?;
+        data.date_to = raw.date_to.map(|to| parse_date_field("date_to", &to)).transpose()?;
+        data.check_dates()?;
+        data.cert_id = raw.cert_id;
+        data.languages = match raw.language {
+            None | Some(Value::Null) => Vec::new(),
+            Some(language) => parse_languages(language)?,
+        };
+
+        match raw.agenda {
+            Value::String(text) => data.agenda = text,
+            Value::Array(items) => {
+                let rows = parse_rows("agenda", items)?;
+                data.agenda = list_text(&rows);
+                data.lists.insert("AGENDA".to_string(), rows);
+            }
+            _ => return Err("field `agenda` must be a string or a list of objects".to_string()),
+        }
+
+        for (key, value) in raw.fields {
+            match value {
+                Value::Null => {}
+                Value::String(text) => data.add_field(key, text),
+                Value::Bool(_) | Value::Number(_) => data.add_field(key, value.to_string()),
+                Value::Array(items) => {
+                    let rows = parse_rows(&key, items)?;
+                    data.add_field(key.clone(), list_text(&rows));
+                    data.lists.insert(key, rows);
+                }
+                Value::Object(_) => {
+                    return Err(format!("field `{}` must be a string, number, boolean or list of objects", key))
+                }
+            }
+        }
+        Ok(data)
+    }
+}
+
+impl From<CertificateData> for RawCertificateData {
+    fn from(data: CertificateData) -> Self {
+        let mut lists = data.lists;
+        let agenda = match lists.remove("AGENDA") {
+            Some(rows) => Value::Array(rows.into_iter().map(Value::Object).collect()),
+            None => Value::String(data.agenda),
+        };
+
+        // Textfassungen von Listen nicht zusätzlich ausgeben
+        let mut fields: BTreeMap<String, Value> = data
+            .custom_fields
+            .into_iter()
+            .filter(|(key, _)| !lists.contains_key(key))
+            .map(|(key, value)| (key, Value::String(value)))
+            .collect();
+        for (key, rows) in lists {
+            fields.insert(key, Value::Array(rows.into_iter().map(Value::Object).collect()));
+        }
+
+        RawCertificateData {
+            name: data.name,
+            date: storage_text(data.date),
+            agenda,
+            date_from: data.date_from.map(storage_text),
+            date_to: data.date_to.map(storage_text),
+            cert_id: data.cert_id,
+            language: match data.languages.as_slice() {
+                [] => None,
+                [language] => Some(Value::String(language.to_string())),
+                languages => Some(Value::Array(
+                    languages.iter().map(|l| Value::String(l.to_string())).collect(),
+                )),
+            },
+            fields,
+        }
+    }
+}
+
+fn parse_date_field(field: &str, text: &str) -> std::result::Result<NaiveDate, String> {
+    parse_date(text).map_err(|e| format!("field `{}`: {}", field, e))
+}
+
+/// `language` ist eine Sprache, eine kommagetrennte Aufzählung oder eine Liste von Sprachen
+fn parse_languages(value: Value) -> std::result::Result<Vec<Locale>, String> {
+    let codes: Vec<String> = match value {
+        Value::String(text) => text.split(',').map(|code| code.trim().to_string()).collect(),
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::String(code) => Ok(code),
+                _ => Err("field `language` must contain language codes".to_string()),
+            })
+            .collect::<std::result::Result<_, _>>()?,
+        _ => return Err("field `language` must be a language code or a list of language codes".to_string()),
+    };
+
+    let mut languages = Vec::new();
+    for code in codes.iter().filter(|code| !code.is_empty()) {
+        let language: Locale = code.parse().map_err(|e| format!("field `language`: {}", e))?;
+        if !languages.contains(&language) {
+            languages.push(language);
+        }
+    }
+    Ok(languages)
+}
+
+/// Listen bestehen aus Objekten mit Text, Zahlen oder Wahrheitswerten
+fn parse_rows(key: &str, items: Vec<Value>) -> std::result::Result<Vec<Row>, String> {
+    items
+        .into_iter()
+        .map(|item| match item {
+            Value::Object(row) if row.values().all(|v| !v.is_object() && !v.is_array()) => Ok(row),
+            _ => Err(format!(
+                "list `{}` must contain objects with text, number or boolean values",
+                key
+            )),
+        })
+        .collect()
+}
+
+/// Textfassung einer Liste für `{{KEY}}`: eine Zeile pro Eintrag im Agenda-Stil `· Wert · Wert`
+fn list_text(rows: &[Row]) -> String {
+    rows.iter()
+        .map(|row| {
+            let values: Vec<String> = row
+                .values()
+                .filter(|value| !value.is_null())
+                .map(|value| match value {
+                    Value::String(text) => text.clone(),
+                    other => other.to_string(),
+                })
+                .collect();
+            format!("· {}", values.join(" · "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 impl CertificateData {
-    pub fn new(name: String, date: String, agenda: String) -> Self {
+    pub fn new(name: String, date: NaiveDate, agenda: String) -> Self {
         Self {
             name,
             date,
             agenda,
             date_from: None,
             date_to: None,
+            cert_id: None,
+            languages: Vec::new(),
             custom_fields: HashMap::new(),
+            lists: BTreeMap::new(),
         }
     }
 
@@ -40,45 +225,101 @@ impl CertificateData {
         self.custom_fields.insert(key, value);
     }
 
-    /// Generiert den intelligenten Datumstext
-    fn get_intelligent_date_text(&self) -> String {
-        match (&self.date_from, &self.date_to) {
-            (Some(from), Some(to)) => {
-                // Mehrtägiger Kurs: "vom ... bis ..."
-                format!("von {} bis {}", from, to)
-            }
-            _ => {
-                // Eintägiger Kurs: "am ..."
-                format!("am {}", self.date)
-            }
+    /// Start- und Enddatum des Kurses; bei eintägigen Kursen zweimal `date` bzw. `date_from`
+    pub fn period(&self) -> (NaiveDate, NaiveDate) {
+        match self.date_from {
+            Some(from) => (from, self.date_to.unwrap_or(from)),
+            None => (self.date, self.date),
+        }
+    }
+
+    /// Prüft, dass ein Enddatum ein Startdatum hat und der Kurs nicht vor seinem Start endet
+    pub fn check_dates(&self) -> std::result::Result<(), String> {
+        if let (None, Some(to)) = (self.date_from, self.date_to) {
+            return Err(format!("end date {} needs a start date (date_from)", storage_text(to)));
         }
+        let (from, to) = self.period();
+        if to < from {
+            return Err(format!(
+                "end date {} is before start date {}",
+                storage_text(to),
+                storage_text(from)
+            ));
+        }
+        Ok(())
+    }
+
+    /// Datumstext in der gewünschten Sprache: "am ..." bzw. "vom ... bis ..."
+    pub fn date_text(&self, dates: &DateOptions) -> String {
+        let (from, to) = self.period();
+        dates.range_text(from, to)
     }
 
-    /// Gibt alle Platzhalter mit ihren Werten zurück
+    /// Gibt alle Platzhalter mit ihren Werten zurück (Daten im Standardformat, deutsch)
     pub fn to_replacements(&self) -> HashMap<String, String> {
+        self.to_replacements_with(&DateOptions::default())
+    }
+
+    /// Gibt alle Platzhalter mit ihren Werten zurück, Daten nach `dates` formatiert
+    pub fn to_replacements_with(&self, dates: &DateOptions) -> HashMap<String, String> {
         let mut replacements = HashMap::new();
         
         // NAME
         replacements.insert("NAME".to_string(), self.name.clone());
         
         // VON_AN - der intelligente Datumstext
-        let date_text = self.get_intelligent_date_text();
+        let date_text = self.date_text(dates);
         replacements.insert("VON_AN".to_string(), date_text.clone());
         
         // DATE - auch als Alias für VON_AN
         replacements.insert("DATE".to_string(), date_text);
+
+        // DATE_FROM/DATE_TO - Start und Ende als JJJJ-MM-TT, z.B. für `{{DATE_FROM|date:"…"}}`
+        let (from, to) = self.period();
+        replacements.insert("DATE_FROM".to_string(), from.format("%Y-%m-%d").to_string());
+        replacements.insert("DATE_TO".to_string(), to.format("%Y-%m-%d").to_string());
         
         // AGENDA
         replacements.insert("AGENDA".to_string(), self.agenda.clone());
         
+        // CERT_ID - nur wenn bereits vergeben
+        if let Some(id) = &self.cert_id {
+            replacements.insert("CERT_ID".to_string(), id.clone());
+        }
+
         // Benutzerdefinierte Felder (z.B. TITLE)
         for (key, value) in &self.custom_fields {
             replacements.insert(key.clone(), value.clone());
         }
+
+        // Listen für `{{#each KEY}}` (zusätzlich zur Textfassung unter KEY)
+        for (key, rows) in &self.lists {
+            let json = serde_json::to_string(rows).unwrap_or_default();
+            replacements.insert(list_key(key), json);
+        }
         
         replacements
     }
 
+    /// Liest einen Eintrag aus einer Batch-Datei.
+    /// Unterstützt sowohl das direkte Format als auch den Wrapper
+    /// `{ "email": "...", "certificate": { ... } }` aus `create-json`.
+    /// Eine zurückgeschriebene `cert_id` steht neben `generated_file` auf oberster Ebene;
+    /// die zurückgeschriebenen Dateien (`GENERATED_KEYS`) sind keine Platzhalter.
+    pub fn from_batch_item(item: &serde_json::Value) -> crate::error::Result<Self> {
+        let mut cert_value = item.get("certificate").unwrap_or(item).clone();
+        if let Value::Object(map) = &mut cert_value {
+            for key in GENERATED_KEYS {
+                map.remove(key);
+            }
+        }
+        let mut data: Self = serde_json::from_value(cert_value)?;
+        if data.cert_id.is_none() {
+            data.cert_id = item.get("cert_id").and_then(|id| id.as_str()).map(String::from);
+        }
+        Ok(data)
+    }
+
     /// Lädt Daten aus einer JSON-Datei
     pub fn from_json_file(path: &str) -> crate::error::Result<Self> {
         let content = std::fs::read_to_string(path)?;
@@ -97,12 +338,56 @@ impl CertificateData {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::template::dates::Locale;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn test_scalar_custom_fields() {
+        let data: CertificateData = serde_json::from_value(serde_json::json!({
+            "name": "Max", "date": "15.01.2024", "agenda": "Rust",
+            "EXAM_PASSED": true, "HOURS": 8, "NOTE": null
+        }))
+        .unwrap();
+        assert_eq!(data.custom_fields.get("EXAM_PASSED"), Some(&"true".to_string()));
+        assert_eq!(data.custom_fields.get("HOURS"), Some(&"8".to_string()));
+        assert!(!data.custom_fields.contains_key("NOTE"));
+    }
+
+    #[test]
+    fn test_agenda_as_list() {
+        let json = serde_json::json!({
+            "name": "Max", "date": "15.01.2024",
+            "agenda": [
+                { "module": "Grundlagen", "hours": 8 },
+                { "module": "Ownership", "hours": 4 }
+            ],
+            "TRAINERS": [{ "name": "Dr. Schmidt" }]
+        });
+        let data: CertificateData = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(data.agenda, "· Grundlagen · 8\n· Ownership · 4");
+        assert_eq!(data.lists["AGENDA"].len(), 2);
+        assert_eq!(data.custom_fields.get("TRAINERS"), Some(&"· Dr. Schmidt".to_string()));
+
+        let replacements = data.to_replacements();
+        assert_eq!(
+            replacements.get("AGENDA[]").map(String::as_str),
+            Some(r#"[{"module":"Grundlagen","hours":8},{"module":"Ownership","hours":4}]"#)
+        );
+        // Listen werden wieder als Listen geschrieben
+        assert_eq!(serde_json::to_value(&data).unwrap(), json);
+
+        let nested = serde_json::json!({ "name": "Max", "date": "15.01.2024", "agenda": [["a"]] });
+        assert!(serde_json::from_value::<CertificateData>(nested).is_err());
+    }
 
     #[test]
     fn test_single_day_course() {
         let data = CertificateData::new(
             "Max".to_string(),
-            "15.01.2024".to_string(),
+            date("15.01.2024"),
             "Rust".to_string(),
         );
         
@@ -110,17 +395,117 @@ mod tests {
         assert_eq!(replacements.get("VON_AN"), Some(&"am 15.01.2024".to_string()));
     }
 
+    #[test]
+    fn test_from_batch_item_wrapper() {
+        let item = serde_json::json!({
+            "email": "max@example.com",
+            "certificate": { "name": "Max", "date": "15.01.2024", "agenda": "Rust", "TITLE": "Kurs" }
+        });
+        let data = CertificateData::from_batch_item(&item).unwrap();
+        assert_eq!(data.name, "Max");
+        assert_eq!(data.custom_fields.get("TITLE"), Some(&"Kurs".to_string()));
+        assert!(!data.custom_fields.contains_key("email"));
+    }
+
+    #[test]
+    fn test_cert_id_from_batch_item() {
+        let item = serde_json::json!({
+            "email": "max@example.com",
+            "cert_id": "B1-2024-000001",
+            "certificate": { "name": "Max", "date": "15.01.2024", "agenda": "Rust" }
+        });
+        let data = CertificateData::from_batch_item(&item).unwrap();
+        assert_eq!(data.cert_id.as_deref(), Some("B1-2024-000001"));
+        assert!(!data.custom_fields.contains_key("cert_id"));
+        assert_eq!(data.to_replacements().get("CERT_ID"), Some(&"B1-2024-000001".to_string()));
+    }
+
+    #[test]
+    fn test_generated_files_are_not_fields() {
+        let item = serde_json::json!({
+            "name": "Max", "date": "15.01.2024", "agenda": "Rust", "cert_id": "B1-2024-000001",
+            "generated_file": "out/Max_Kurs_de.pdf",
+            "generated_files": { "de": "out/Max_Kurs_de.pdf", "en": "out/Max_Kurs_en.pdf" },
+            "generated_eml": "out/emails/Max_Kurs_de.eml",
+            "generated_emls": { "de": "out/emails/Max_Kurs_de.eml", "en": "out/emails/Max_Kurs_en.eml" }
+        });
+        let data = CertificateData::from_batch_item(&item).unwrap();
+        assert_eq!(data.cert_id.as_deref(), Some("B1-2024-000001"));
+        assert!(data.custom_fields.is_empty());
+    }
+
     #[test]
     fn test_multi_day_course() {
         let mut data = CertificateData::new(
             "Max".to_string(),
-            "15.01.2024".to_string(),
+            date("15.01.2024"),
             "Rust".to_string(),
         );
-        data.date_from = Some("10.01.2024".to_string());
-        data.date_to = Some("15.01.2024".to_string());
+        data.date_from = Some(date("10.01.2024"));
+        data.date_to = Some(date("15.01.2024"));
         
         let replacements = data.to_replacements();
         assert_eq!(replacements.get("VON_AN"), Some(&"vom 10.01.2024 bis 15.01.2024".to_string()));
+        assert_eq!(replacements.get("DATE_FROM"), Some(&"2024-01-10".to_string()));
+        assert_eq!(replacements.get("DATE_TO"), Some(&"2024-01-15".to_string()));
+
+        let english = data.to_replacements_with(&DateOptions::for_locale(Locale::En));
+        assert_eq!(english.get("DATE"), Some(&"from 10/01/2024 to 15/01/2024".to_string()));
+    }
+
+    #[test]
+    fn test_start_date_without_end_is_single_day() {
+        let mut data = CertificateData::new("Max".to_string(), date("15.01.2024"), "Rust".to_string());
+        data.date_from = Some(date("10.01.2024"));
+
+        let replacements = data.to_replacements();
+        assert_eq!(replacements.get("VON_AN"), Some(&"am 10.01.2024".to_string()));
+        assert_eq!(replacements.get("DATE_TO"), Some(&"2024-01-10".to_string()));
+    }
+
+    #[test]
+    fn test_language_field() {
+        let data: CertificateData = serde_json::from_value(serde_json::json!({
+            "name": "Max", "date": "15.01.2024", "agenda": "Rust", "language": "de, en"
+        }))
+        .unwrap();
+        assert_eq!(data.languages, vec![Locale::De, Locale::En]);
+        assert!(!data.custom_fields.contains_key("language"));
+        assert_eq!(serde_json::to_value(&data).unwrap()["language"], serde_json::json!(["de", "en"]));
+
+        let single = serde_json::json!({ "name": "Max", "date": "15.01.2024", "agenda": "Rust", "language": ["fr"] });
+        let data: CertificateData = serde_json::from_value(single).unwrap();
+        assert_eq!(data.languages, vec![Locale::Fr]);
+
+        let unknown = serde_json::json!({ "name": "Max", "date": "15.01.2024", "agenda": "Rust", "language": "es" });
+        assert!(serde_json::from_value::<CertificateData>(unknown).is_err());
+    }
+
+    #[test]
+    fn test_dates_are_parsed_and_checked() {
+        let data: CertificateData = serde_json::from_value(serde_json::json!({
+            "name": "Max", "date": "2024-01-15", "date_from": "10/01/2024", "agenda": "Rust"
+        }))
+        .unwrap();
+        assert_eq!(data.period(), (date("10.01.2024"), date("10.01.2024")));
+        assert_eq!(serde_json::to_value(&data).unwrap()["date"], "15.01.2024");
+
+        let invalid = serde_json::json!({ "name": "Max", "date": "15.13.2024", "agenda": "Rust" });
+        let error = serde_json::from_value::<CertificateData>(invalid).unwrap_err();
+        assert!(error.to_string().contains("field `date`"));
+
+        let reversed = serde_json::json!({
+            "name": "Max", "date": "10.01.2024", "date_from": "15.01.2024", "date_to": "10.01.2024", "agenda": "Rust"
+        });
+        let error = serde_json::from_value::<CertificateData>(reversed).unwrap_err();
+        assert!(error.to_string().contains("before start date"));
+
+        let without_start = serde_json::json!({ "name": "Max", "date": "15.01.2024", "date_to": "15.01.2024", "agenda": "Rust" });
+        let error = serde_json::from_value::<CertificateData>(without_start).unwrap_err();
+        assert!(error.to_string().contains("needs a start date"));
+
+        let mut data = CertificateData::new("Max".to_string(), date("15.01.2024"), "Rust".to_string());
+        data.date_to = Some(date("15.01.2024"));
+        assert!(data.check_dates().is_err());
     }
 }
@@ -0,0 +1,345 @@
+//! Datumsangaben: Einlesen aus mehreren Eingabeformaten und Ausgabe je Sprache,
+//! z.B. `am 15.01.2024`, `from 10 to 15 January 2024` oder `10.–15. Januar 2024`.
+
+use chrono::{Datelike, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Write};
+use std::str::FromStr;
+
+/// Eingabeformate, die erkannt werden (in dieser Reihenfolge)
+pub const INPUT_FORMATS: [&str; 3] = ["%d.%m.%Y", "%Y-%m-%d", "%d/%m/%Y"];
+
+/// Format, in dem Daten in JSON-Dateien und im Register gespeichert werden
+pub const STORAGE_FORMAT: &str = "%d.%m.%Y";
+
+/// Liest ein Datum in einem der `INPUT_FORMATS`; zweistellige Jahre werden abgelehnt
+pub fn parse_date(text: &str) -> Result<NaiveDate, String> {
+    let text = text.trim();
+    INPUT_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(text, format).ok())
+        .filter(|date| (1000..=9999).contains(&date.year()))
+        .ok_or_else(|| format!("`{}` is not a date (expected e.g. 15.01.2024 or 2024-01-15)", text))
+}
+
+/// Datum im `STORAGE_FORMAT`
+pub fn storage_text(date: NaiveDate) -> String {
+    date.format(STORAGE_FORMAT).to_string()
+}
+
+/// Sprache für Monatsnamen, Zahlenformat und Standardformulierungen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Locale {
+    #[default]
+    De,
+    En,
+    Fr,
+}
+
+impl Locale {
+    fn months(self) -> [&'static str; 12] {
+        match self {
+            Locale::De => [
+                "Januar", "Februar", "März", "April", "Mai", "Juni", "Juli", "August", "September", "Oktober",
+                "November", "Dezember",
+            ],
+            Locale::En => [
+                "January", "February", "March", "April", "May", "June", "July", "August", "September", "October",
+                "November", "December",
+            ],
+            Locale::Fr => [
+                "janvier", "février", "mars", "avril", "mai", "juin", "juillet", "août", "septembre", "octobre",
+                "novembre", "décembre",
+            ],
+        }
+    }
+
+    fn weekdays(self) -> [&'static str; 7] {
+        match self {
+            Locale::De => ["Montag", "Dienstag", "Mittwoch", "Donnerstag", "Freitag", "Samstag", "Sonntag"],
+            Locale::En => ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"],
+            Locale::Fr => ["lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche"],
+        }
+    }
+
+    /// Standardformulierung für eintägige Kurse
+    fn single_phrase(self) -> &'static str {
+        match self {
+            Locale::De => "am {date}",
+            Locale::En => "on {date}",
+            Locale::Fr => "le {date}",
+        }
+    }
+
+    /// Standardformulierung für Zeiträume
+    fn range_phrase(self) -> &'static str {
+        match self {
+            Locale::De => "vom {from} bis {to}",
+            Locale::En => "from {from} to {to}",
+            Locale::Fr => "du {from} au {to}",
+        }
+    }
+
+    fn month_name(self, date: NaiveDate) -> &'static str {
+        self.months()[date.month0() as usize]
+    }
+
+    /// Tag im langen Format, im Französischen `1er` für den Monatsersten
+    fn day(self, date: NaiveDate) -> String {
+        match (self, date.day()) {
+            (Locale::De, day) => format!("{}.", day),
+            (Locale::Fr, 1) => "1er".to_string(),
+            (_, day) => day.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Locale::De => "de",
+            Locale::En => "en",
+            Locale::Fr => "fr",
+        })
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Akzeptiert auch Regionalangaben wie `de-AT` oder `en_GB`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "de" => Ok(Locale::De),
+            "en" => Ok(Locale::En),
+            "fr" => Ok(Locale::Fr),
+            _ => Err(format!("unknown locale `{}` (expected de, en or fr)", s)),
+        }
+    }
+}
+
+/// Zahlen (`15.01.2024`) oder ausgeschriebener Monat (`15. Januar 2024`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateStyle {
+    #[default]
+    Numeric,
+    Long,
+}
+
+impl fmt::Display for DateStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            DateStyle::Numeric => "numeric",
+            DateStyle::Long => "long",
+        })
+    }
+}
+
+impl FromStr for DateStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "numeric" => Ok(DateStyle::Numeric),
+            "long" => Ok(DateStyle::Long),
+            _ => Err(format!("unknown date style `{}` (expected numeric or long)", s)),
+        }
+    }
+}
+
+/// Wie Datumsangaben im Zertifikat ausgegeben werden
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DateOptions {
+    pub locale: Locale,
+    pub style: DateStyle,
+    /// Gemeinsamen Monat und gemeinsames Jahr eines Zeitraums nur beim Enddatum ausgeben
+    pub collapse: bool,
+    /// Formulierung für eintägige Kurse mit `{date}`; Standard je Sprache, z.B. `am {date}`
+    pub single: Option<String>,
+    /// Formulierung für Zeiträume mit `{from}` und `{to}`; Standard je Sprache, z.B. `vom {from} bis {to}`
+    pub range: Option<String>,
+}
+
+impl DateOptions {
+    /// Standardeinstellungen für eine Sprache
+    pub fn for_locale(locale: Locale) -> Self {
+        Self {
+            locale,
+            ..Self::default()
+        }
+    }
+
+    /// Ein einzelnes Datum ohne Formulierung, z.B. `15.01.2024` oder `15. Januar 2024`
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        match (self.style, self.locale) {
+            (DateStyle::Numeric, Locale::De) => date.format("%d.%m.%Y").to_string(),
+            (DateStyle::Numeric, _) => date.format("%d/%m/%Y").to_string(),
+            (DateStyle::Long, locale) => {
+                format!("{} {} {}", locale.day(date), locale.month_name(date), date.year())
+            }
+        }
+    }
+
+    /// Text für einen eintägigen Kurs, z.B. `am 15.01.2024`
+    pub fn single_text(&self, date: NaiveDate) -> String {
+        let phrase = self.single.as_deref().unwrap_or(self.locale.single_phrase());
+        phrase.replace("{date}", &self.format_date(date))
+    }
+
+    /// Text für einen Zeitraum, z.B. `vom 10.01.2024 bis 15.01.2024`; fallen Start
+    /// und Ende auf denselben Tag, wird der Text für eintägige Kurse verwendet
+    pub fn range_text(&self, from: NaiveDate, to: NaiveDate) -> String {
+        if from == to {
+            return self.single_text(from);
+        }
+        let start = if self.collapse {
+            self.collapsed_start(from, to)
+        } else {
+            self.format_date(from)
+        };
+        let phrase = self.range.as_deref().unwrap_or(self.locale.range_phrase());
+        phrase.replace("{from}", &start).replace("{to}", &self.format_date(to))
+    }
+
+    /// Startdatum ohne die Teile, die es mit dem Enddatum gemeinsam hat
+    fn collapsed_start(&self, from: NaiveDate, to: NaiveDate) -> String {
+        let locale = self.locale;
+        let same_year = from.year() == to.year();
+        let same_month = same_year && from.month() == to.month();
+
+        match self.style {
+            DateStyle::Long if same_month => locale.day(from),
+            DateStyle::Long if same_year => format!("{} {}", locale.day(from), locale.month_name(from)),
+            DateStyle::Numeric if same_year && locale == Locale::De => from.format("%d.%m.").to_string(),
+            DateStyle::Numeric if same_year => from.format("%d/%m").to_string(),
+            _ => self.format_date(from),
+        }
+    }
+}
+
+/// Formatiert ein Datum mit chrono-Formatangaben; `%B`/`%b` (Monat) und `%A`/`%a`
+/// (Wochentag) werden in der angegebenen Sprache ausgegeben
+pub fn format_localized(date: NaiveDate, format: &str, locale: Locale) -> Result<String, String> {
+    let month = locale.month_name(date);
+    let weekday = locale.weekdays()[date.weekday().num_days_from_monday() as usize];
+
+    let mut localized = String::with_capacity(format.len());
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            localized.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('B') => localized.push_str(month),
+            Some('b') => localized.push_str(&abbreviate(month)),
+            Some('A') => localized.push_str(weekday),
+            Some('a') => localized.push_str(&abbreviate(weekday)),
+            Some(other) => {
+                localized.push('%');
+                localized.push(other);
+            }
+            None => localized.push('%'),
+        }
+    }
+
+    let mut text = String::new();
+    write!(text, "{}", date.format(&localized)).map_err(|_| format!("invalid date format `{}`", format))?;
+    Ok(text)
+}
+
+fn abbreviate(name: &str) -> String {
+    name.chars().take(3).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        parse_date(text).unwrap()
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let expected = NaiveDate::from_ymd_opt(2024, 1, 15).unwrap();
+        assert_eq!(parse_date("15.01.2024"), Ok(expected));
+        assert_eq!(parse_date(" 2024-01-15 "), Ok(expected));
+        assert_eq!(parse_date("15/01/2024"), Ok(expected));
+        assert!(parse_date("15.01.24").is_err());
+        assert!(parse_date("31.02.2024").is_err());
+        assert!(parse_date("morgen").is_err());
+    }
+
+    #[test]
+    fn test_locale_from_str() {
+        assert_eq!("en".parse(), Ok(Locale::En));
+        assert_eq!("de-AT".parse(), Ok(Locale::De));
+        assert_eq!("fr_CA".parse(), Ok(Locale::Fr));
+        assert!("es".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_default_phrases() {
+        let (from, to) = (date("10.01.2024"), date("15.01.2024"));
+        let de = DateOptions::default();
+        assert_eq!(de.single_text(to), "am 15.01.2024");
+        assert_eq!(de.range_text(from, to), "vom 10.01.2024 bis 15.01.2024");
+        assert_eq!(de.range_text(to, to), "am 15.01.2024");
+
+        let en = DateOptions::for_locale(Locale::En);
+        assert_eq!(en.range_text(from, to), "from 10/01/2024 to 15/01/2024");
+
+        let fr = DateOptions {
+            style: DateStyle::Long,
+            ..DateOptions::for_locale(Locale::Fr)
+        };
+        assert_eq!(fr.single_text(date("01.08.2024")), "le 1er août 2024");
+    }
+
+    #[test]
+    fn test_collapsed_ranges() {
+        let options = DateOptions {
+            style: DateStyle::Long,
+            collapse: true,
+            range: Some("{from}–{to}".to_string()),
+            ..DateOptions::default()
+        };
+        assert_eq!(options.range_text(date("10.01.2024"), date("15.01.2024")), "10.–15. Januar 2024");
+        assert_eq!(
+            options.range_text(date("29.02.2024"), date("02.03.2024")),
+            "29. Februar–2. März 2024"
+        );
+        assert_eq!(
+            options.range_text(date("30.12.2024"), date("02.01.2025")),
+            "30. Dezember 2024–2. Januar 2025"
+        );
+
+        let en = DateOptions {
+            style: DateStyle::Long,
+            collapse: true,
+            ..DateOptions::for_locale(Locale::En)
+        };
+        assert_eq!(en.range_text(date("10.01.2024"), date("15.01.2024")), "from 10 to 15 January 2024");
+
+        let numeric = DateOptions {
+            collapse: true,
+            ..DateOptions::default()
+        };
+        assert_eq!(
+            numeric.range_text(date("10.01.2024"), date("15.02.2024")),
+            "vom 10.01. bis 15.02.2024"
+        );
+    }
+
+    #[test]
+    fn test_format_localized() {
+        let day = date("15.01.2024");
+        assert_eq!(format_localized(day, "%A, %d. %B %Y", Locale::De).unwrap(), "Montag, 15. Januar 2024");
+        assert_eq!(format_localized(day, "%a %e %b", Locale::Fr).unwrap(), "lun 15 jan");
+        assert_eq!(format_localized(day, "100%% %B", Locale::En).unwrap(), "100% January");
+    }
+}
@@ -0,0 +1,214 @@
+//! Mehrsprachige Zertifikate: Ein Datensatz wird in jeder gewünschten Sprache erzeugt,
+//! mit eigenem Datumstext, festen Texten (`strings`) und E-Mail je Sprache.
+
+use crate::config::LanguageConfig;
+use crate::template::dates::{DateOptions, Locale};
+use crate::template::CertificateData;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Sprachabhängige Einstellungen für das Erzeugen der Zertifikate
+#[derive(Debug, Clone, Default)]
+pub struct Localization {
+    /// Datumsformat; `locale` gilt für Ausgaben ohne Sprache
+    pub dates: DateOptions,
+    /// Sprachen aus `--languages` für Datensätze ohne eigenes `language`-Feld
+    pub languages: Vec<Locale>,
+    /// Einstellungen je Sprache aus der Konfigurationsdatei
+    pub settings: BTreeMap<Locale, LanguageConfig>,
+}
+
+impl Localization {
+    /// Die Sprachen, in denen ein Datensatz erzeugt wird; `None` steht für eine
+    /// einzelne Ausgabe ohne Sprachendung im Dateinamen
+    pub fn languages_for(&self, data: &CertificateData) -> Vec<Option<Locale>> {
+        let languages = if data.languages.is_empty() {
+            &self.languages
+        } else {
+            &data.languages
+        };
+        if languages.is_empty() {
+            vec![None]
+        } else {
+            languages.iter().copied().map(Some).collect()
+        }
+    }
+
+    /// Datumsformat für eine Sprache; die Formulierungen aus `dates` gelten nur für ihre eigene Sprache
+    pub fn date_options(&self, language: Option<Locale>) -> DateOptions {
+        let Some(language) = language else {
+            return self.dates.clone();
+        };
+        let settings = self.settings.get(&language);
+        let own = language == self.dates.locale;
+        DateOptions {
+            locale: language,
+            single: settings
+                .and_then(|s| s.date_single.clone())
+                .or_else(|| self.dates.single.clone().filter(|_| own)),
+            range: settings
+                .and_then(|s| s.date_range.clone())
+                .or_else(|| self.dates.range.clone().filter(|_| own)),
+            ..self.dates.clone()
+        }
+    }
+
+    /// Der Datensatz mit den festen Texten der Sprache als zusätzliche Felder (ohne
+    /// Sprache die von `dates.locale`); sie ersetzen gleichnamige Felder des Datensatzes,
+    /// damit z.B. ein übersetzter `TITLE` greift
+    pub fn localize(&self, data: &CertificateData, language: Option<Locale>) -> CertificateData {
+        let mut localized = data.clone();
+        if let Some(settings) = self.settings.get(&language.unwrap_or(self.dates.locale)) {
+            for (key, value) in &settings.strings {
+                localized.custom_fields.insert(key.clone(), value.clone());
+            }
+        }
+        localized
+    }
+
+    /// Alle Ersetzungen eines Datensatzes in einer Sprache
+    pub fn replacements(&self, data: &CertificateData, language: Option<Locale>) -> HashMap<String, String> {
+        self.localize(data, language)
+            .to_replacements_with(&self.date_options(language))
+    }
+
+    /// Vorlage für den Betreff der E-Mail mit `{{PLACEHOLDER}}`-Feldern
+    pub fn email_subject(&self, language: Option<Locale>) -> &str {
+        let locale = language.unwrap_or(self.dates.locale);
+        self.setting(language, |s| s.email_subject.as_deref())
+            .unwrap_or(match locale {
+                Locale::De => r#"Ihr Zertifikat: {{TITLE|default:"Kurs"}}"#,
+                Locale::En => r#"Your certificate: {{TITLE|default:"Course"}}"#,
+                Locale::Fr => r#"Votre certificat : {{TITLE|default:"Formation"}}"#,
+            })
+    }
+
+    /// Vorlage für den Text der E-Mail; `{{CERT_FILE}}` ist der Dateiname des Anhangs
+    pub fn email_body(&self, language: Option<Locale>) -> &str {
+        let locale = language.unwrap_or(self.dates.locale);
+        self.setting(language, |s| s.email_body.as_deref())
+            .unwrap_or(match locale {
+                Locale::De => "Guten Tag {{NAME}},\n\nanbei Ihr Zertifikat {{CERT_FILE}}.\n\nMit freundlichen Grüßen\nDas Team",
+                Locale::En => "Dear {{NAME}},\n\nplease find attached your certificate {{CERT_FILE}}.\n\nKind regards\nThe team",
+                Locale::Fr => "Bonjour {{NAME}},\n\nveuillez trouver ci-joint votre certificat {{CERT_FILE}}.\n\nCordialement\nL'équipe",
+            })
+    }
+
+    fn setting<'a>(
+        &'a self,
+        language: Option<Locale>,
+        get: impl Fn(&'a LanguageConfig) -> Option<&'a str>,
+    ) -> Option<&'a str> {
+        self.settings.get(&language.unwrap_or(self.dates.locale)).and_then(get)
+    }
+}
+
+/// Schreibt die früheren E-Mail-Platzhalter `<name>`, `<cert>` und `<title>` in die
+/// `{{PLACEHOLDER}}`-Syntax um; `None`, wenn der Text keine enthält
+pub fn upgrade_email_placeholders(text: &str) -> Option<String> {
+    const LEGACY: [(&str, &str); 3] = [("<name>", "{{NAME}}"), ("<cert>", "{{CERT_FILE}}"), ("<title>", "{{TITLE}}")];
+    if !LEGACY.iter().any(|(old, _)| text.contains(old)) {
+        return None;
+    }
+    Some(LEGACY.iter().fold(text.to_string(), |text, (old, new)| text.replace(old, new)))
+}
+
+/// Hängt die Sprache an den Dateinamen an, z.B. `Max_Rust.pdf` → `Max_Rust_en.pdf`
+pub fn with_language_suffix(path: &Path, language: Option<Locale>) -> PathBuf {
+    let Some(language) = language else {
+        return path.to_path_buf();
+    };
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}_{}.{}", stem, language, extension.to_string_lossy()),
+        None => format!("{}_{}", stem, language),
+    };
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::dates::parse_date;
+
+    fn localization() -> Localization {
+        let english = LanguageConfig {
+            strings: BTreeMap::from([
+                ("HEADING".to_string(), "Certificate of attendance".to_string()),
+                ("TITLE".to_string(), "Rust course".to_string()),
+            ]),
+            email_subject: Some("Certificate {{TITLE}}".to_string()),
+            ..LanguageConfig::default()
+        };
+        Localization {
+            dates: DateOptions {
+                range: Some("{from} – {to}".to_string()),
+                ..DateOptions::default()
+            },
+            languages: vec![Locale::De, Locale::En],
+            settings: BTreeMap::from([(Locale::En, english)]),
+        }
+    }
+
+    fn record() -> CertificateData {
+        let mut data = CertificateData::new("Max".into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        data.date_from = Some(parse_date("10.01.2024").unwrap());
+        data.date_to = Some(parse_date("15.01.2024").unwrap());
+        data.add_field("TITLE".into(), "Rust-Kurs".into());
+        data
+    }
+
+    #[test]
+    fn test_replacements_per_language() {
+        let localization = localization();
+        let data = record();
+        assert_eq!(localization.languages_for(&data), vec![Some(Locale::De), Some(Locale::En)]);
+
+        let german = localization.replacements(&data, Some(Locale::De));
+        assert_eq!(german["VON_AN"], "10.01.2024 – 15.01.2024");
+        assert!(!german.contains_key("HEADING"));
+
+        let english = localization.replacements(&data, Some(Locale::En));
+        assert_eq!(english["VON_AN"], "from 10/01/2024 to 15/01/2024");
+        assert_eq!(english["HEADING"], "Certificate of attendance");
+        // Die festen Texte der Sprache haben Vorrang vor den Feldern des Datensatzes
+        assert_eq!(english["TITLE"], "Rust course");
+        assert_eq!(german["TITLE"], "Rust-Kurs");
+    }
+
+    #[test]
+    fn test_record_languages_take_precedence() {
+        let mut data = record();
+        data.languages = vec![Locale::Fr];
+        assert_eq!(localization().languages_for(&data), vec![Some(Locale::Fr)]);
+        assert_eq!(Localization::default().languages_for(&data), vec![Some(Locale::Fr)]);
+        assert_eq!(Localization::default().languages_for(&record()), vec![None]);
+    }
+
+    #[test]
+    fn test_email_texts() {
+        let localization = localization();
+        assert_eq!(localization.email_subject(Some(Locale::En)), "Certificate {{TITLE}}");
+        assert_eq!(localization.email_subject(None), r#"Ihr Zertifikat: {{TITLE|default:"Kurs"}}"#);
+        assert!(localization.email_body(Some(Locale::Fr)).starts_with("Bonjour {{NAME}}"));
+    }
+
+    #[test]
+    fn test_upgrade_email_placeholders() {
+        assert_eq!(
+            upgrade_email_placeholders("Hallo <name>, anbei <cert> (<title>)").as_deref(),
+            Some("Hallo {{NAME}}, anbei {{CERT_FILE}} ({{TITLE}})")
+        );
+        assert_eq!(upgrade_email_placeholders("Hallo {{NAME}}"), None);
+    }
+
+    #[test]
+    fn test_language_suffix() {
+        assert_eq!(
+            with_language_suffix(Path::new("out/Max_Rust.pdf"), Some(Locale::En)),
+            PathBuf::from("out/Max_Rust_en.pdf")
+        );
+        assert_eq!(with_language_suffix(Path::new("zertifikat"), Some(Locale::De)), PathBuf::from("zertifikat_de"));
+        assert_eq!(with_language_suffix(Path::new("a.odt"), None), PathBuf::from("a.odt"));
+    }
+}
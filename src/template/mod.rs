@@ -1,3 +1,9 @@
 pub mod data;
+pub mod dates;
+pub mod localization;
+pub mod text;
 
 pub use data::CertificateData;
+pub use dates::{DateOptions, DateStyle, Locale};
+pub use localization::Localization;
+pub use text::TextTemplate;
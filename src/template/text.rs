@@ -0,0 +1,134 @@
+//! Textvorlagen, z.B. für Betreff und Text der E-Mails: `{{PLACEHOLDER}}` samt Filtern
+//! wie in der Zertifikatsvorlage, mit den Feldern des Datensatzes.
+
+use crate::error::{CertgenError, Result};
+use crate::odf::{FilterExpression, FilterRegistry};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Text mit `{{KEY}}`- und `{{KEY|filter:arg}}`-Platzhaltern
+#[derive(Clone)]
+pub struct TextTemplate {
+    source: String,
+    filters: FilterRegistry,
+}
+
+impl TextTemplate {
+    /// Prüft die Syntax der Platzhalter und dass nur eingebaute Filter verwendet werden
+    pub fn parse(source: &str) -> Result<Self> {
+        Self::parse_with_filters(source, FilterRegistry::new())
+    }
+
+    /// Wie `parse`, mit eigenen Filtern für `{{KEY|filter}}` (z.B. die eingebauten plus eigene)
+    pub fn parse_with_filters(source: &str, filters: FilterRegistry) -> Result<Self> {
+        let re = placeholder_regex();
+        for caps in re.captures_iter(source) {
+            filters.check(&FilterExpression::parse(&caps[1])?)?;
+        }
+        let literal = re.replace_all(source, "");
+        if literal.contains("{{") || literal.contains("}}") {
+            return Err(CertgenError::TemplateSyntax(format!(
+                "unclosed placeholder in text template `{}`",
+                source.lines().next().unwrap_or_default()
+            )));
+        }
+        Ok(Self {
+            source: source.to_string(),
+            filters,
+        })
+    }
+
+    /// Liest die Vorlage aus einer UTF-8-Datei; ein Zeilenumbruch am Ende wird entfernt
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(source.strip_suffix('\n').unwrap_or(&source).trim_end_matches('\r'))
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Setzt die Felder ein; fehlt ein Feld ohne `default`-Filter, ist das ein Fehler
+    pub fn render(&self, fields: &HashMap<String, String>) -> Result<String> {
+        let mut result = String::with_capacity(self.source.len());
+        let mut last = 0;
+        for caps in placeholder_regex().captures_iter(&self.source) {
+            let placeholder = caps.get(0).unwrap();
+            result.push_str(&self.source[last..placeholder.start()]);
+            let expression = FilterExpression::parse(&caps[1])?;
+            let value = self
+                .filters
+                .apply(&expression, fields.get(&expression.key).map(String::as_str))?
+                .ok_or(CertgenError::PlaceholderNotFound {
+                    name: expression.key,
+                    record: None,
+                })?;
+            result.push_str(&value);
+            last = placeholder.end();
+        }
+        result.push_str(&self.source[last..]);
+        Ok(result)
+    }
+}
+
+impl fmt::Debug for TextTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TextTemplate").field("source", &self.source).finish()
+    }
+}
+
+fn placeholder_regex() -> Regex {
+    Regex::new(r"\{\{([^{}]+)\}\}").unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> HashMap<String, String> {
+        HashMap::from([
+            ("NAME".to_string(), "Max Müller".to_string()),
+            ("TITLE".to_string(), "Rust <Grundlagen>".to_string()),
+            ("CERT_FILE".to_string(), "Max_Muller_Rust.pdf".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_render() {
+        let template = TextTemplate::parse("Guten Tag {{NAME}},\nanbei {{CERT_FILE}} zum Kurs {{TITLE}}.").unwrap();
+        assert_eq!(
+            template.render(&fields()).unwrap(),
+            "Guten Tag Max Müller,\nanbei Max_Muller_Rust.pdf zum Kurs Rust <Grundlagen>."
+        );
+
+        let filtered = TextTemplate::parse(r#"{{NAME|upper}}: {{INSTRUCTOR|default:"das Team"}}"#).unwrap();
+        assert_eq!(filtered.render(&fields()).unwrap(), "MAX MÜLLER: das Team");
+    }
+
+    #[test]
+    fn test_missing_field_and_syntax_errors() {
+        let missing = TextTemplate::parse("{{INSTRUCTOR}}").unwrap().render(&fields());
+        assert!(matches!(missing, Err(CertgenError::PlaceholderNotFound { name, .. }) if name == "INSTRUCTOR"));
+
+        assert!(matches!(TextTemplate::parse("Hallo {{NAME"), Err(CertgenError::TemplateSyntax(_))));
+        assert!(matches!(TextTemplate::parse("{{NAME|unknown}}"), Err(CertgenError::Filter(_))));
+
+        let mut filters = FilterRegistry::new();
+        filters.register("initials", |value, _| {
+            Ok(value.map(|v| v.split_whitespace().filter_map(|w| w.chars().next()).collect()))
+        });
+        let custom = TextTemplate::parse_with_filters("{{NAME|initials}}", filters).unwrap();
+        assert_eq!(custom.render(&fields()).unwrap(), "MM");
+    }
+
+    #[test]
+    fn test_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("subject.txt");
+        std::fs::write(&path, "Zertifikat {{TITLE}}\r\n").unwrap();
+        let template = TextTemplate::from_file(&path).unwrap();
+        assert_eq!(template.source(), "Zertifikat {{TITLE}}");
+    }
+}
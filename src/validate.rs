@@ -0,0 +1,164 @@
+//! Vorab-Prüfung von Datensätzen gegen die Platzhalter einer Vorlage.
+
+use crate::odf::{PlaceholderInfo, LIST_KEY_SUFFIX};
+use crate::template::CertificateData;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Keys, die `CertificateData::to_replacements()` immer erzeugt.
+/// Sie werden nicht als "unbenutzt" gemeldet, da Vorlagen meist nur einen Teil davon verwenden.
+const BUILTIN_KEYS: [&str; 7] = ["NAME", "DATE", "DATE_FROM", "DATE_TO", "VON_AN", "AGENDA", "CERT_ID"];
+
+/// Platzhalter, die certgen beim Erzeugen selbst befüllt (z.B. die Zertifikats-ID aus dem Register)
+const GENERATED_KEYS: [&str; 2] = ["CERT_ID", "QR"];
+
+/// Prüfergebnis für einen einzelnen Datensatz
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RecordReport {
+    pub index: usize,
+    pub name: String,
+    /// Platzhalter der Vorlage, für die der Datensatz keinen Wert liefert
+    pub unfilled: Vec<String>,
+    /// Felder des Datensatzes, die in der Vorlage nicht vorkommen
+    pub unused: Vec<String>,
+}
+
+impl RecordReport {
+    /// Ein Datensatz ist gültig, wenn alle Platzhalter befüllt werden
+    pub fn is_ok(&self) -> bool {
+        self.unfilled.is_empty()
+    }
+}
+
+/// Prüfergebnis für alle Datensätze
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ValidationReport {
+    pub records: Vec<RecordReport>,
+}
+
+impl ValidationReport {
+    /// Anzahl der Datensätze mit unbefüllten Platzhaltern
+    pub fn failed_count(&self) -> usize {
+        self.records.iter().filter(|r| !r.is_ok()).count()
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.failed_count() == 0
+    }
+}
+
+/// Gleicht die Ersetzungen eines Datensatzes mit den Platzhaltern der Vorlage ab
+pub fn validate_record(
+    index: usize,
+    data: &CertificateData,
+    placeholders: &[PlaceholderInfo],
+) -> RecordReport {
+    let replacements = data.to_replacements();
+    let expected: BTreeSet<&str> = placeholders.iter().map(|p| p.name.as_str()).collect();
+
+    // Optionale Platzhalter (bedingte Abschnitte) werden erst beim Befüllen gemäß MissingPolicy geprüft
+    let optional: BTreeSet<&str> = placeholders
+        .iter()
+        .filter(|p| p.optional)
+        .map(|p| p.name.as_str())
+        .collect();
+
+    let unfilled = expected
+        .iter()
+        .filter(|name| !replacements.contains_key(**name) && !GENERATED_KEYS.contains(name) && !optional.contains(*name))
+        .map(|name| name.to_string())
+        .collect();
+
+    let mut unused: Vec<String> = replacements
+        .keys()
+        // Listen stehen zusätzlich als Text unter ihrem Namen und werden dort gemeldet
+        .filter(|key| {
+            !BUILTIN_KEYS.contains(&key.as_str())
+                && !expected.contains(key.as_str())
+                && !key.ends_with(LIST_KEY_SUFFIX)
+        })
+        .cloned()
+        .collect();
+    unused.sort();
+
+    RecordReport {
+        index,
+        name: data.name.clone(),
+        unfilled,
+        unused,
+    }
+}
+
+/// Prüft alle Datensätze gegen die Platzhalter der Vorlage
+pub fn validate_records(
+    records: &[CertificateData],
+    placeholders: &[PlaceholderInfo],
+) -> ValidationReport {
+    ValidationReport {
+        records: records
+            .iter()
+            .enumerate()
+            .map(|(idx, data)| validate_record(idx, data, placeholders))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::template::dates::parse_date;
+    use std::collections::BTreeMap;
+
+    fn placeholder(name: &str) -> PlaceholderInfo {
+        PlaceholderInfo {
+            name: name.to_string(),
+            count: 1,
+            parts: BTreeMap::new(),
+            optional: false,
+        }
+    }
+
+    #[test]
+    fn test_reports_unfilled_and_unused() {
+        let mut data = CertificateData::new("Max".into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        data.add_field("TITLE".into(), "Kurs".into());
+        data.add_field("HOURS".into(), "8".into());
+
+        let placeholders = vec![placeholder("NAME"), placeholder("TITLE"), placeholder("INSTRUCTOR")];
+        let report = validate_record(3, &data, &placeholders);
+
+        assert_eq!(report.index, 3);
+        assert_eq!(report.unfilled, vec!["INSTRUCTOR".to_string()]);
+        assert_eq!(report.unused, vec!["HOURS".to_string()]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn test_builtin_keys_not_reported_as_unused() {
+        let data = CertificateData::new("Max".into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        let report = validate_records(&[data], &[placeholder("NAME")]);
+
+        assert!(report.is_ok());
+        assert!(report.records[0].unused.is_empty());
+    }
+
+    #[test]
+    fn test_optional_placeholders_may_be_missing() {
+        let data = CertificateData::new("Max".into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        let exam = PlaceholderInfo {
+            optional: true,
+            ..placeholder("EXAM_PASSED")
+        };
+        let report = validate_records(&[data], &[placeholder("NAME"), exam]);
+
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn test_cert_id_is_generated() {
+        let data = CertificateData::new("Max".into(), parse_date("15.01.2024").unwrap(), "Rust".into());
+        let report = validate_records(&[data], &[placeholder("NAME"), placeholder("CERT_ID")]);
+
+        assert!(report.is_ok());
+    }
+}